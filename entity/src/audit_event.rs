@@ -0,0 +1,59 @@
+use chrono::Utc;
+use sea_orm::{prelude::*, Set};
+
+/// Security relevant event, rows are only ever inserted.
+/// There are no foreign keys, so events outlive purged users.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "audit_events")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    /// e.g. `login_failed`, `password_changed`
+    pub kind: String,
+    /// user that caused the event, `None` for anonymous requests
+    pub actor_id: Option<Uuid>,
+    /// user the event is about
+    pub target_id: Option<Uuid>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub details: Option<String>,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {
+    /// Create a new ActiveModel with default values. Also used by `Default::default()`.
+    fn new() -> Self {
+        Self {
+            id: Set(Uuid::new_v4()),
+            created_at: Set(Utc::now()),
+            ..ActiveModelTrait::default()
+        }
+    }
+
+    /// Will be triggered before insert / update
+    fn before_save(self, insert: bool) -> Result<Self, DbErr> {
+        if !insert {
+            return Err(DbErr::Custom("audit events can't be changed".to_owned()));
+        }
+        Ok(self)
+    }
+
+    /// Will be triggered after insert / update
+    fn after_save(model: Model, _insert: bool) -> Result<Model, DbErr> {
+        Ok(model)
+    }
+
+    /// Will be triggered before delete
+    fn before_delete(self) -> Result<Self, DbErr> {
+        Err(DbErr::Custom("audit events can't be deleted".to_owned()))
+    }
+
+    /// Will be triggered after delete
+    fn after_delete(self) -> Result<Self, DbErr> {
+        Ok(self)
+    }
+}
@@ -1,16 +1,28 @@
 use chrono::Utc;
 use sea_orm::{prelude::*, Set};
 
-use crate::{project, work_report};
+use crate::{customer_contact, organization, project, work_report};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "customers")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: Uuid,
+    pub organization_id: Uuid,
     pub name: String,
     pub identifier: String,
     pub note: Option<String>,
+    /// JSON object with name, street, postal code, city and country
+    pub billing_address: Option<Json>,
+    /// Same format as the billing address, `None` ships to the billing address
+    pub shipping_address: Option<Json>,
+    pub vat_id: Option<String>,
+    pub tax_exempt: bool,
+    pub iban: Option<String>,
+    pub payment_terms_days: i32,
+    /// ISO 4217 currency code
+    pub currency: String,
+    pub invoice_email: Option<String>,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
     pub deleted_at: Option<DateTimeUtc>,
@@ -18,11 +30,23 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "organization::Entity",
+        from = "Column::OrganizationId",
+        to = "organization::Column::Id",
+        on_update = "Cascade",
+        on_delete = "NoAction"
+    )]
+    Organization,
+
     #[sea_orm(has_many = "project::Entity")]
     Project,
 
     #[sea_orm(has_many = "work_report::Entity")]
     WorkReport,
+
+    #[sea_orm(has_many = "customer_contact::Entity")]
+    Contact,
 }
 
 impl Related<project::Entity> for Entity {
@@ -37,6 +61,18 @@ impl Related<work_report::Entity> for Entity {
     }
 }
 
+impl Related<customer_contact::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Contact.def()
+    }
+}
+
+impl Related<organization::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Organization.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {
     /// Create a new ActiveModel with default values. Also used by `Default::default()`.
     fn new() -> Self {
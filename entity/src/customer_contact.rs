@@ -0,0 +1,81 @@
+use chrono::Utc;
+use sea_orm::{prelude::*, Set};
+
+use crate::{customer, work_report};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "customer_contacts")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    pub customer_id: Uuid,
+    pub name: String,
+    pub role: Option<String>,
+    /// JSON array of strings
+    pub emails: Json,
+    /// JSON array of strings
+    pub phones: Json,
+    pub is_primary: bool,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "customer::Entity",
+        from = "Column::CustomerId",
+        to = "customer::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Customer,
+
+    #[sea_orm(has_many = "work_report::Entity")]
+    WorkReport,
+}
+
+impl Related<customer::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Customer.def()
+    }
+}
+
+impl Related<work_report::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WorkReport.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    /// Create a new ActiveModel with default values. Also used by `Default::default()`.
+    fn new() -> Self {
+        Self {
+            id: Set(Uuid::new_v4()),
+            created_at: Set(Utc::now()),
+            updated_at: Set(Utc::now()),
+            ..ActiveModelTrait::default()
+        }
+    }
+
+    /// Will be triggered before insert / update
+    fn before_save(mut self, _insert: bool) -> Result<Self, DbErr> {
+        self.updated_at = Set(Utc::now());
+        Ok(self)
+    }
+
+    /// Will be triggered after insert / update
+    fn after_save(model: Model, _insert: bool) -> Result<Model, DbErr> {
+        Ok(model)
+    }
+
+    /// Will be triggered before delete
+    fn before_delete(self) -> Result<Self, DbErr> {
+        Ok(self)
+    }
+
+    /// Will be triggered after delete
+    fn after_delete(self) -> Result<Self, DbErr> {
+        Ok(self)
+    }
+}
@@ -0,0 +1,57 @@
+use chrono::Utc;
+use sea_orm::{prelude::*, Set};
+
+use crate::{invitation, role};
+
+/// Roles the invited user gets when accepting the invitation
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "invitation_roles")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub invitation_id: Uuid,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub role_id: Uuid,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "invitation::Entity",
+        from = "Column::InvitationId",
+        to = "invitation::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Invitation,
+    #[sea_orm(
+        belongs_to = "role::Entity",
+        from = "Column::RoleId",
+        to = "role::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Role,
+}
+
+impl Related<invitation::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Invitation.def()
+    }
+}
+
+impl Related<role::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Role.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    /// Create a new ActiveModel with default values. Also used by `Default::default()`.
+    fn new() -> Self {
+        Self {
+            created_at: Set(Utc::now()),
+            ..ActiveModelTrait::default()
+        }
+    }
+}
@@ -1,5 +1,25 @@
+pub mod api_token;
+pub mod audit_event;
 pub mod customer;
+pub mod customer_contact;
+pub mod email_verification_token;
+pub mod invitation;
+pub mod invitation_role;
+pub mod login_event;
+pub mod organization;
+pub mod organization_member;
+pub mod password_reset_token;
 pub mod project;
+pub mod project_member;
+pub mod recovery_code;
+pub mod role;
+pub mod role_permission;
+pub mod session;
+pub mod team;
+pub mod team_member;
 pub mod time_record;
 pub mod user;
+pub mod user_identity;
+pub mod user_preference;
+pub mod user_role;
 pub mod work_report;
@@ -1,13 +1,14 @@
 use chrono::Utc;
 use sea_orm::{prelude::*, Set};
 
-use crate::{customer, work_report};
+use crate::{customer, organization, project_member, work_report};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "projects")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: Uuid,
+    pub organization_id: Uuid,
     pub customer_id: Uuid,
     pub name: String,
     pub note: Option<String>,
@@ -18,6 +19,15 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "organization::Entity",
+        from = "Column::OrganizationId",
+        to = "organization::Column::Id",
+        on_update = "Cascade",
+        on_delete = "NoAction"
+    )]
+    Organization,
+
     #[sea_orm(
         belongs_to = "customer::Entity",
         from = "Column::CustomerId",
@@ -27,6 +37,9 @@ pub enum Relation {
 
     #[sea_orm(has_many = "work_report::Entity")]
     WorkReport,
+
+    #[sea_orm(has_many = "project_member::Entity")]
+    ProjectMember,
 }
 
 impl Related<customer::Entity> for Entity {
@@ -41,6 +54,18 @@ impl Related<work_report::Entity> for Entity {
     }
 }
 
+impl Related<organization::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Organization.def()
+    }
+}
+
+impl Related<project_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProjectMember.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {
     /// Create a new ActiveModel with default values. Also used by `Default::default()`.
     fn new() -> Self {
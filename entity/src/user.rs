@@ -1,7 +1,10 @@
 use chrono::Utc;
 use sea_orm::{prelude::*, Set};
 
-use crate::work_report;
+use crate::{
+    api_token, invitation, login_event, organization_member, project_member, recovery_code,
+    session, team_member, user_identity, user_preference, user_role, work_report,
+};
 
 pub type UserId = Uuid;
 pub type UserEmail = String;
@@ -12,10 +15,22 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: Uuid,
     pub email: String,
+    pub email_verified_at: Option<DateTimeUtc>,
     pub password_hash: String,
     pub name: Option<String>,
     pub avatar_filename: Option<String>,
     pub is_admin: bool,
+    /// base32 encoded TOTP secret, set while setting up and after enabling TOTP
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTimeUtc>,
+    /// last TOTP time step a code was accepted for, used to prevent replays
+    pub totp_last_used_step: Option<i64>,
+    /// failed logins since the last successful one
+    pub failed_login_attempts: i32,
+    /// logins are rejected until this time
+    pub locked_until: Option<DateTimeUtc>,
+    /// logins and requests are refused while set
+    pub deactivated_at: Option<DateTimeUtc>,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
     pub deleted_at: Option<DateTimeUtc>,
@@ -25,6 +40,39 @@ pub struct Model {
 pub enum Relation {
     #[sea_orm(has_many = "work_report::Entity")]
     WorkReport,
+
+    #[sea_orm(has_many = "session::Entity")]
+    Session,
+
+    #[sea_orm(has_many = "recovery_code::Entity")]
+    RecoveryCode,
+
+    #[sea_orm(has_many = "user_role::Entity")]
+    UserRole,
+
+    #[sea_orm(has_many = "api_token::Entity")]
+    ApiToken,
+
+    #[sea_orm(has_many = "login_event::Entity")]
+    LoginEvent,
+
+    #[sea_orm(has_many = "user_identity::Entity")]
+    UserIdentity,
+
+    #[sea_orm(has_many = "invitation::Entity")]
+    Invitation,
+
+    #[sea_orm(has_many = "organization_member::Entity")]
+    OrganizationMember,
+
+    #[sea_orm(has_many = "team_member::Entity")]
+    TeamMember,
+
+    #[sea_orm(has_many = "project_member::Entity")]
+    ProjectMember,
+
+    #[sea_orm(has_one = "user_preference::Entity")]
+    UserPreference,
 }
 
 impl Related<work_report::Entity> for Entity {
@@ -33,11 +81,78 @@ impl Related<work_report::Entity> for Entity {
     }
 }
 
+impl Related<session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Session.def()
+    }
+}
+
+impl Related<recovery_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecoveryCode.def()
+    }
+}
+
+impl Related<user_role::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserRole.def()
+    }
+}
+
+impl Related<api_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiToken.def()
+    }
+}
+
+impl Related<login_event::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LoginEvent.def()
+    }
+}
+
+impl Related<user_identity::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserIdentity.def()
+    }
+}
+
+impl Related<invitation::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Invitation.def()
+    }
+}
+
+impl Related<organization_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OrganizationMember.def()
+    }
+}
+
+impl Related<team_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TeamMember.def()
+    }
+}
+
+impl Related<project_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProjectMember.def()
+    }
+}
+
+impl Related<user_preference::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserPreference.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {
     /// Create a new ActiveModel with default values. Also used by `Default::default()`.
     fn new() -> Self {
         Self {
             id: Set(Uuid::new_v4()),
+            failed_login_attempts: Set(0),
             created_at: Set(Utc::now()),
             updated_at: Set(Utc::now()),
             ..ActiveModelTrait::default()
@@ -57,9 +172,6 @@ impl ActiveModelBehavior for ActiveModel {
 
     /// Will be triggered before delete
     fn before_delete(self) -> Result<Self, DbErr> {
-        if let Some(file) = self.avatar_filename.as_ref() {
-            let _ = std::fs::remove_file(format!("static/avatar/{file}"));
-        }
         Ok(self)
     }
 
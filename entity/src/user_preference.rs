@@ -0,0 +1,78 @@
+use chrono::Utc;
+use sea_orm::{prelude::*, Set};
+
+use crate::user;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "user_preferences")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user_id: Uuid,
+    /// IANA time zone name, e.g. `Europe/Berlin`
+    pub timezone: String,
+    /// BCP 47 language tag, e.g. `de-DE`
+    pub locale: String,
+    /// lowercase english weekday name, e.g. `monday`
+    pub first_day_of_week: String,
+    /// date pattern for the frontend, e.g. `dd.MM.yyyy`
+    pub date_format: String,
+    /// contractual working time per week, the sum of the weekdays
+    pub weekly_minutes: i32,
+    pub monday_minutes: i32,
+    pub tuesday_minutes: i32,
+    pub wednesday_minutes: i32,
+    pub thursday_minutes: i32,
+    pub friday_minutes: i32,
+    pub saturday_minutes: i32,
+    pub sunday_minutes: i32,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "user::Entity",
+        from = "Column::UserId",
+        to = "user::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    /// Create a new ActiveModel with default values. Also used by `Default::default()`.
+    /// Defaults to a 40 hour week from monday to friday in UTC.
+    fn new() -> Self {
+        Self {
+            timezone: Set("UTC".to_owned()),
+            locale: Set("en".to_owned()),
+            first_day_of_week: Set("monday".to_owned()),
+            date_format: Set("yyyy-MM-dd".to_owned()),
+            weekly_minutes: Set(40 * 60),
+            monday_minutes: Set(8 * 60),
+            tuesday_minutes: Set(8 * 60),
+            wednesday_minutes: Set(8 * 60),
+            thursday_minutes: Set(8 * 60),
+            friday_minutes: Set(8 * 60),
+            saturday_minutes: Set(0),
+            sunday_minutes: Set(0),
+            created_at: Set(Utc::now()),
+            updated_at: Set(Utc::now()),
+            ..ActiveModelTrait::default()
+        }
+    }
+
+    /// Will be triggered before insert / update
+    fn before_save(mut self, _insert: bool) -> Result<Self, DbErr> {
+        self.updated_at = Set(Utc::now());
+        Ok(self)
+    }
+}
@@ -1,16 +1,19 @@
 use chrono::Utc;
 use sea_orm::{prelude::*, Set};
 
-use crate::{customer, project, time_record, user};
+use crate::{customer, customer_contact, organization, project, time_record, user};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "work_reports")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: Uuid,
+    pub organization_id: Uuid,
     pub owner_id: Uuid,
     pub customer_id: Uuid,
     pub project_id: Option<Uuid>,
+    /// Contact person of the customer who ordered the work
+    pub contact_id: Option<Uuid>,
     pub description: String,
     pub invoiced: bool,
     pub created_at: DateTimeUtc,
@@ -36,6 +39,14 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Customer,
+    #[sea_orm(
+        belongs_to = "customer_contact::Entity",
+        from = "Column::ContactId",
+        to = "customer_contact::Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    Contact,
     #[sea_orm(
         belongs_to = "project::Entity",
         from = "Column::ProjectId",
@@ -46,6 +57,14 @@ pub enum Relation {
     Project,
     #[sea_orm(has_many = "time_record::Entity")]
     TimeRecord,
+    #[sea_orm(
+        belongs_to = "organization::Entity",
+        from = "Column::OrganizationId",
+        to = "organization::Column::Id",
+        on_update = "Cascade",
+        on_delete = "NoAction"
+    )]
+    Organization,
 }
 
 impl Related<user::Entity> for Entity {
@@ -60,6 +79,12 @@ impl Related<customer::Entity> for Entity {
     }
 }
 
+impl Related<customer_contact::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Contact.def()
+    }
+}
+
 impl Related<project::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Project.def()
@@ -72,6 +97,12 @@ impl Related<time_record::Entity> for Entity {
     }
 }
 
+impl Related<organization::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Organization.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {
     /// Create a new ActiveModel with default values. Also used by `Default::default()`.
     fn new() -> Self {
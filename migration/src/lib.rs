@@ -5,6 +5,42 @@ mod m20220312_011800_create_customer_table;
 mod m20220312_011900_create_project_table;
 mod m20220312_012000_create_work_report_table;
 mod m20220312_012100_create_time_record_table;
+mod m20261017_100000_create_session_table;
+mod m20261017_100100_add_client_info_to_session_table;
+mod m20261017_100200_create_password_reset_token_table;
+mod m20261017_100300_create_email_verification_token_table;
+mod m20261017_100400_add_email_verified_at_to_user_table;
+mod m20261017_100500_add_totp_to_user_table;
+mod m20261017_100600_create_recovery_code_table;
+mod m20261017_100700_create_role_table;
+mod m20261017_100800_create_role_permission_table;
+mod m20261017_100900_create_user_role_table;
+mod m20261017_101000_seed_default_roles;
+mod m20261017_101100_grant_work_report_write_all;
+mod m20261017_101200_create_api_token_table;
+mod m20261017_101300_add_lockout_to_user_table;
+mod m20261017_101400_create_login_event_table;
+mod m20261017_101500_create_user_identity_table;
+mod m20261017_101600_add_deactivated_at_to_user_table;
+mod m20261017_101700_add_impersonator_to_session_table;
+mod m20261017_101800_create_invitation_table;
+mod m20261017_101900_create_invitation_role_table;
+mod m20261017_102000_create_audit_event_table;
+mod m20261017_102100_create_organization_table;
+mod m20261017_102200_create_organization_member_table;
+mod m20261017_102300_add_organization_to_customer_project_work_report_tables;
+mod m20261017_102400_add_organization_to_session_api_token_invitation_tables;
+mod m20261017_102500_create_team_table;
+mod m20261017_102600_create_team_member_table;
+mod m20261017_102700_create_project_member_table;
+mod m20261017_102800_grant_project_manage;
+mod m20261017_102900_create_user_preference_table;
+mod m20261017_103000_add_lower_email_index_to_user_table;
+mod m20261017_103100_create_customer_contact_table;
+mod m20261017_103200_add_contact_to_work_report_table;
+mod m20261017_103300_add_billing_profile_to_customer_table;
+mod m20261017_103400_assign_admin_role_to_admins;
+mod m20261017_103500_make_audit_events_append_only;
 
 pub struct Migrator;
 
@@ -17,6 +53,64 @@ impl MigratorTrait for Migrator {
             Box::new(m20220312_011900_create_project_table::Migration),
             Box::new(m20220312_012000_create_work_report_table::Migration),
             Box::new(m20220312_012100_create_time_record_table::Migration),
+            Box::new(m20261017_100000_create_session_table::Migration),
+            Box::new(m20261017_100100_add_client_info_to_session_table::Migration),
+            Box::new(m20261017_100200_create_password_reset_token_table::Migration),
+            Box::new(m20261017_100300_create_email_verification_token_table::Migration),
+            Box::new(m20261017_100400_add_email_verified_at_to_user_table::Migration),
+            Box::new(m20261017_100500_add_totp_to_user_table::Migration),
+            Box::new(m20261017_100600_create_recovery_code_table::Migration),
+            Box::new(m20261017_100700_create_role_table::Migration),
+            Box::new(m20261017_100800_create_role_permission_table::Migration),
+            Box::new(m20261017_100900_create_user_role_table::Migration),
+            Box::new(m20261017_101000_seed_default_roles::Migration),
+            Box::new(m20261017_101100_grant_work_report_write_all::Migration),
+            Box::new(m20261017_101200_create_api_token_table::Migration),
+            Box::new(m20261017_101300_add_lockout_to_user_table::Migration),
+            Box::new(m20261017_101400_create_login_event_table::Migration),
+            Box::new(m20261017_101500_create_user_identity_table::Migration),
+            Box::new(m20261017_101600_add_deactivated_at_to_user_table::Migration),
+            Box::new(m20261017_101700_add_impersonator_to_session_table::Migration),
+            Box::new(m20261017_101800_create_invitation_table::Migration),
+            Box::new(m20261017_101900_create_invitation_role_table::Migration),
+            Box::new(m20261017_102000_create_audit_event_table::Migration),
+            Box::new(m20261017_102100_create_organization_table::Migration),
+            Box::new(m20261017_102200_create_organization_member_table::Migration),
+            Box::new(
+                m20261017_102300_add_organization_to_customer_project_work_report_tables::Migration,
+            ),
+            Box::new(
+                m20261017_102400_add_organization_to_session_api_token_invitation_tables::Migration,
+            ),
+            Box::new(m20261017_102500_create_team_table::Migration),
+            Box::new(m20261017_102600_create_team_member_table::Migration),
+            Box::new(m20261017_102700_create_project_member_table::Migration),
+            Box::new(m20261017_102800_grant_project_manage::Migration),
+            Box::new(m20261017_102900_create_user_preference_table::Migration),
+            Box::new(m20261017_103000_add_lower_email_index_to_user_table::Migration),
+            Box::new(m20261017_103100_create_customer_contact_table::Migration),
+            Box::new(m20261017_103200_add_contact_to_work_report_table::Migration),
+            Box::new(m20261017_103300_add_billing_profile_to_customer_table::Migration),
+            Box::new(m20261017_103400_assign_admin_role_to_admins::Migration),
+            Box::new(m20261017_103500_make_audit_events_append_only::Migration),
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Applied migrations are matched by position against the versions sorted by name
+    #[test]
+    fn migrations_are_sorted_by_version() {
+        let names = Migrator::migrations()
+            .iter()
+            .map(|migration| migration.name().to_owned())
+            .collect::<Vec<_>>();
+        let mut sorted = names.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(names, sorted);
+    }
+}
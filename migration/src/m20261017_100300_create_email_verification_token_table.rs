@@ -0,0 +1,62 @@
+use entity::{email_verification_token::*, user};
+use sea_schema::migration::{sea_query::*, *};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_100300_create_email_verification_token_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Column::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(Column::UserId).uuid().not_null())
+                    .col(ColumnDef::new(Column::Email).text().not_null())
+                    .col(
+                        ColumnDef::new(Column::TokenHash)
+                            .text()
+                            .unique_key()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Column::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Column::UsedAt).timestamp_with_time_zone())
+                    .col(
+                        ColumnDef::new(Column::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("FK_email_verification_token-user")
+                            .from_tbl(Entity)
+                            .from_col(Column::UserId)
+                            .to_tbl(user::Entity)
+                            .to_col(user::Column::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
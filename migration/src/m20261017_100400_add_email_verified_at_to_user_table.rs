@@ -0,0 +1,46 @@
+use entity::user::*;
+use sea_schema::migration::{sea_query::*, *};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_100400_add_email_verified_at_to_user_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entity)
+                    .add_column(ColumnDef::new(Column::EmailVerifiedAt).timestamp_with_time_zone())
+                    .to_owned(),
+            )
+            .await?;
+        // accounts that existed before verification was introduced count as verified
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Entity)
+                    .value_expr(Column::EmailVerifiedAt, Expr::col(Column::CreatedAt).into())
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entity)
+                    .drop_column(Column::EmailVerifiedAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
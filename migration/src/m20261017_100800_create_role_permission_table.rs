@@ -0,0 +1,49 @@
+use entity::{role, role_permission::*};
+use sea_schema::migration::{sea_query::*, *};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_100800_create_role_permission_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Column::RoleId).uuid().not_null())
+                    .col(ColumnDef::new(Column::Permission).text().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(Column::RoleId)
+                            .col(Column::Permission),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("FK_role_permission-role")
+                            .from_tbl(Entity)
+                            .from_col(Column::RoleId)
+                            .to_tbl(role::Entity)
+                            .to_col(role::Column::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use sea_schema::migration::{
+    sea_orm::Statement,
+    *,
+};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_101000_seed_default_roles"
+    }
+}
+
+/// Permissions of the `member` role, every logged in user had them before roles existed
+const MEMBER_PERMISSIONS: &[&str] = &[
+    "customer:read",
+    "customer:write",
+    "project:read",
+    "project:write",
+    "work_report:read",
+    "work_report:write",
+    "user:read",
+];
+
+const ADMIN_PERMISSIONS: &[&str] = &[
+    "customer:read",
+    "customer:write",
+    "project:read",
+    "project:write",
+    "work_report:read",
+    "work_report:write",
+    "work_report:read_all",
+    "user:read",
+    "user:write",
+    "role:manage",
+    "merchandise:approve",
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        let mut statements = vec![
+            "INSERT INTO roles (id, name, description, is_default, created_at, updated_at) \
+             VALUES (gen_random_uuid(), 'member', 'Default role of every user', true, now(), now()), \
+             (gen_random_uuid(), 'admin', 'All permissions', false, now(), now())"
+                .to_owned(),
+            // every existing user gets the member role, so nobody loses access
+            "INSERT INTO user_roles (user_id, role_id, created_at) \
+             SELECT users.id, roles.id, now() FROM users, roles WHERE roles.name = 'member'"
+                .to_owned(),
+        ];
+        for (role, permissions) in [("member", MEMBER_PERMISSIONS), ("admin", ADMIN_PERMISSIONS)] {
+            for permission in permissions {
+                statements.push(format!(
+                    "INSERT INTO role_permissions (role_id, permission) \
+                     SELECT id, '{permission}' FROM roles WHERE name = '{role}'"
+                ));
+            }
+        }
+
+        for statement in statements {
+            db.execute(Statement::from_string(backend, statement))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "DELETE FROM roles WHERE name IN ('member', 'admin')".to_owned(),
+            ))
+            .await?;
+        Ok(())
+    }
+}
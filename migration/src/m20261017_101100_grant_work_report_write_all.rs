@@ -0,0 +1,40 @@
+use sea_schema::migration::{
+    sea_orm::Statement,
+    *,
+};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_101100_grant_work_report_write_all"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "INSERT INTO role_permissions (role_id, permission) \
+                 SELECT id, 'work_report:write_all' FROM roles WHERE name = 'admin'"
+                    .to_owned(),
+            ))
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "DELETE FROM role_permissions WHERE permission = 'work_report:write_all'"
+                    .to_owned(),
+            ))
+            .await?;
+        Ok(())
+    }
+}
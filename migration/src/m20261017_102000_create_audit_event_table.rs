@@ -0,0 +1,54 @@
+use entity::audit_event::*;
+use sea_schema::migration::{sea_query::*, *};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_102000_create_audit_event_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Column::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(Column::Kind).text().not_null())
+                    .col(ColumnDef::new(Column::ActorId).uuid())
+                    .col(ColumnDef::new(Column::TargetId).uuid())
+                    .col(ColumnDef::new(Column::Ip).text())
+                    .col(ColumnDef::new(Column::UserAgent).text())
+                    .col(ColumnDef::new(Column::RequestId).text())
+                    .col(ColumnDef::new(Column::Details).text())
+                    .col(
+                        ColumnDef::new(Column::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_audit_event-created_at")
+                    .table(Entity)
+                    .col(Column::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use entity::{customer, organization, project, work_report};
+use sea_schema::migration::{sea_orm::Statement, sea_query::*, *};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_102300_add_organization_to_customer_project_work_report_tables"
+    }
+}
+
+/// Adds `organization_id` to the table and moves all existing rows into the default organization
+async fn add_organization_id<E, C>(
+    manager: &SchemaManager<'_>,
+    entity: E,
+    column: C,
+    fk_name: &str,
+) -> Result<(), DbErr>
+where
+    E: Iden + Copy + 'static,
+    C: Iden + Copy + 'static,
+{
+    manager
+        .alter_table(
+            Table::alter()
+                .table(entity)
+                .add_column(ColumnDef::new(column).uuid())
+                .to_owned(),
+        )
+        .await?;
+
+    let table = entity.to_string();
+    let db = manager.get_connection();
+    let backend = manager.get_database_backend();
+    for statement in [
+        format!(
+            "UPDATE {table} SET organization_id = \
+             (SELECT id FROM organizations WHERE is_default LIMIT 1)"
+        ),
+        format!("ALTER TABLE {table} ALTER COLUMN organization_id SET NOT NULL"),
+    ] {
+        db.execute(Statement::from_string(backend, statement))
+            .await?;
+    }
+
+    manager
+        .create_foreign_key(
+            ForeignKey::create()
+                .name(fk_name)
+                .from(entity, column)
+                .to(organization::Entity, organization::Column::Id)
+                .on_update(ForeignKeyAction::Cascade)
+                .on_delete(ForeignKeyAction::NoAction)
+                .to_owned(),
+        )
+        .await
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        add_organization_id(
+            manager,
+            customer::Entity,
+            customer::Column::OrganizationId,
+            "FK_customer-organization",
+        )
+        .await?;
+        add_organization_id(
+            manager,
+            project::Entity,
+            project::Column::OrganizationId,
+            "FK_project-organization",
+        )
+        .await?;
+        add_organization_id(
+            manager,
+            work_report::Entity,
+            work_report::Column::OrganizationId,
+            "FK_work_report-organization",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(customer::Entity)
+                    .drop_column(customer::Column::OrganizationId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(project::Entity)
+                    .drop_column(project::Column::OrganizationId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(work_report::Entity)
+                    .drop_column(work_report::Column::OrganizationId)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
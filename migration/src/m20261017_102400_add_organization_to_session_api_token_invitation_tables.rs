@@ -0,0 +1,114 @@
+use entity::{api_token, invitation, organization, session};
+use sea_schema::migration::{sea_orm::Statement, sea_query::*, *};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_102400_add_organization_to_session_api_token_invitation_tables"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(session::Entity)
+                    .add_column(ColumnDef::new(session::Column::OrganizationId).uuid())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name("FK_session-organization")
+                    .from(session::Entity, session::Column::OrganizationId)
+                    .to(organization::Entity, organization::Column::Id)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .on_delete(ForeignKeyAction::SetNull)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(api_token::Entity)
+                    .add_column(ColumnDef::new(api_token::Column::OrganizationId).uuid())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name("FK_api_token-organization")
+                    .from(api_token::Entity, api_token::Column::OrganizationId)
+                    .to(organization::Entity, organization::Column::Id)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(invitation::Entity)
+                    .add_column(ColumnDef::new(invitation::Column::OrganizationId).uuid())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name("FK_invitation-organization")
+                    .from(invitation::Entity, invitation::Column::OrganizationId)
+                    .to(organization::Entity, organization::Column::Id)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await?;
+        // pending invitations were sent before there were organizations
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "UPDATE invitations SET organization_id = \
+                 (SELECT id FROM organizations WHERE is_default LIMIT 1)"
+                    .to_owned(),
+            ))
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(session::Entity)
+                    .drop_column(session::Column::OrganizationId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(api_token::Entity)
+                    .drop_column(api_token::Column::OrganizationId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(invitation::Entity)
+                    .drop_column(invitation::Column::OrganizationId)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
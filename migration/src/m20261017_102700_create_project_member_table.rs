@@ -0,0 +1,112 @@
+use entity::{project, project_member::*, team, user};
+use sea_schema::migration::{sea_orm::Statement, sea_query::*, *};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_102700_create_project_member_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Column::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(Column::ProjectId).uuid().not_null())
+                    .col(ColumnDef::new(Column::UserId).uuid())
+                    .col(ColumnDef::new(Column::TeamId).uuid())
+                    .col(ColumnDef::new(Column::Role).text().not_null())
+                    .col(
+                        ColumnDef::new(Column::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Column::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("FK_project_member-project")
+                            .from_tbl(Entity)
+                            .from_col(Column::ProjectId)
+                            .to_tbl(project::Entity)
+                            .to_col(project::Column::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("FK_project_member-user")
+                            .from_tbl(Entity)
+                            .from_col(Column::UserId)
+                            .to_tbl(user::Entity)
+                            .to_col(user::Column::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("FK_project_member-team")
+                            .from_tbl(Entity)
+                            .from_col(Column::TeamId)
+                            .to_tbl(team::Entity)
+                            .to_col(team::Column::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_project_member-project-user")
+                    .table(Entity)
+                    .col(Column::ProjectId)
+                    .col(Column::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_project_member-project-team")
+                    .table(Entity)
+                    .col(Column::ProjectId)
+                    .col(Column::TeamId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // whoever already booked time onto a project keeps access to it
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "INSERT INTO project_members (id, project_id, user_id, role, created_at, updated_at) \
+                 SELECT gen_random_uuid(), project_id, owner_id, 'member', now(), now() \
+                 FROM work_reports WHERE project_id IS NOT NULL \
+                 GROUP BY project_id, owner_id"
+                    .to_owned(),
+            ))
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use sea_schema::migration::{sea_orm::Statement, *};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_102800_grant_project_manage"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "INSERT INTO role_permissions (role_id, permission) \
+                 SELECT id, 'project:manage' FROM roles WHERE name = 'admin'"
+                    .to_owned(),
+            ))
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "DELETE FROM role_permissions WHERE permission = 'project:manage'".to_owned(),
+            ))
+            .await?;
+        Ok(())
+    }
+}
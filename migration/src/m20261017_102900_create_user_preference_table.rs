@@ -0,0 +1,98 @@
+use entity::{user, user_preference::*};
+use sea_schema::migration::{sea_query::*, *};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_102900_create_user_preference_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Column::UserId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Column::Timezone)
+                            .string()
+                            .not_null()
+                            .default("UTC"),
+                    )
+                    .col(
+                        ColumnDef::new(Column::Locale)
+                            .string()
+                            .not_null()
+                            .default("en"),
+                    )
+                    .col(
+                        ColumnDef::new(Column::FirstDayOfWeek)
+                            .string()
+                            .not_null()
+                            .default("monday"),
+                    )
+                    .col(
+                        ColumnDef::new(Column::DateFormat)
+                            .string()
+                            .not_null()
+                            .default("yyyy-MM-dd"),
+                    )
+                    .col(&mut minutes(Column::WeeklyMinutes, 40 * 60))
+                    .col(&mut minutes(Column::MondayMinutes, 8 * 60))
+                    .col(&mut minutes(Column::TuesdayMinutes, 8 * 60))
+                    .col(&mut minutes(Column::WednesdayMinutes, 8 * 60))
+                    .col(&mut minutes(Column::ThursdayMinutes, 8 * 60))
+                    .col(&mut minutes(Column::FridayMinutes, 8 * 60))
+                    .col(&mut minutes(Column::SaturdayMinutes, 0))
+                    .col(&mut minutes(Column::SundayMinutes, 0))
+                    .col(
+                        ColumnDef::new(Column::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Column::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("FK_user_preference-user")
+                            .from_tbl(Entity)
+                            .from_col(Column::UserId)
+                            .to_tbl(user::Entity)
+                            .to_col(user::Column::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Entity).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+fn minutes(column: Column, default: i32) -> ColumnDef {
+    ColumnDef::new(column)
+        .integer()
+        .not_null()
+        .default(default)
+        .to_owned()
+}
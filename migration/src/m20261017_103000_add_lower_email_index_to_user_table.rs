@@ -0,0 +1,55 @@
+use sea_schema::migration::{sea_orm::Statement, *};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_103000_add_lower_email_index_to_user_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        // logins match the email case-insensitive, accounts that only differ in case
+        // have to be merged by hand before the index can be created
+        let duplicates = db
+            .query_all(Statement::from_string(
+                backend,
+                "SELECT lower(email) AS email FROM users GROUP BY lower(email) HAVING count(*) > 1"
+                    .to_owned(),
+            ))
+            .await?
+            .into_iter()
+            .map(|row| row.try_get::<String>("", "email"))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !duplicates.is_empty() {
+            return Err(DbErr::Custom(format!(
+                "these emails are used by several users in different case: {}",
+                duplicates.join(", ")
+            )));
+        }
+
+        db.execute(Statement::from_string(
+            backend,
+            "CREATE UNIQUE INDEX IF NOT EXISTS \"idx-users-lower-email\" ON users (lower(email))"
+                .to_owned(),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "DROP INDEX IF EXISTS \"idx-users-lower-email\"".to_owned(),
+            ))
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use entity::customer::*;
+use sea_schema::migration::{sea_query::*, *};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_103300_add_billing_profile_to_customer_table"
+    }
+}
+
+const COLUMNS: [Column; 8] = [
+    Column::BillingAddress,
+    Column::ShippingAddress,
+    Column::VatId,
+    Column::TaxExempt,
+    Column::Iban,
+    Column::PaymentTermsDays,
+    Column::Currency,
+    Column::InvoiceEmail,
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in COLUMNS {
+            let mut def = ColumnDef::new(column);
+            match column {
+                Column::BillingAddress | Column::ShippingAddress => def.json_binary(),
+                Column::TaxExempt => def.boolean().not_null().default(false),
+                Column::PaymentTermsDays => def.integer().not_null().default(30),
+                Column::Currency => def.string().not_null().default("EUR"),
+                _ => def.string(),
+            };
+            manager
+                .alter_table(Table::alter().table(Entity).add_column(&mut def).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in COLUMNS {
+            manager
+                .alter_table(Table::alter().table(Entity).drop_column(column).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use sea_schema::migration::{sea_orm::Statement, *};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_103400_assign_admin_role_to_admins"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // `is_admin` no longer grants permissions on its own, admins need the role
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "INSERT INTO user_roles (user_id, role_id, created_at) \
+                 SELECT users.id, roles.id, now() FROM users, roles \
+                 WHERE users.is_admin AND roles.name = 'admin' \
+                 ON CONFLICT DO NOTHING"
+                    .to_owned(),
+            ))
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use sea_schema::migration::{sea_orm::Statement, *};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261017_103500_make_audit_events_append_only"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        let statements = [
+            "CREATE OR REPLACE FUNCTION audit_events_append_only() RETURNS trigger AS $$ \
+             BEGIN RAISE EXCEPTION 'audit_events is append-only'; END; \
+             $$ LANGUAGE plpgsql",
+            "CREATE TRIGGER audit_events_no_change BEFORE UPDATE OR DELETE ON audit_events \
+             FOR EACH ROW EXECUTE PROCEDURE audit_events_append_only()",
+            "CREATE TRIGGER audit_events_no_truncate BEFORE TRUNCATE ON audit_events \
+             FOR EACH STATEMENT EXECUTE PROCEDURE audit_events_append_only()",
+        ];
+        for statement in statements {
+            db.execute(Statement::from_string(backend, statement.to_owned()))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        let statements = [
+            "DROP TRIGGER IF EXISTS audit_events_no_truncate ON audit_events",
+            "DROP TRIGGER IF EXISTS audit_events_no_change ON audit_events",
+            "DROP FUNCTION IF EXISTS audit_events_append_only()",
+        ];
+        for statement in statements {
+            db.execute(Statement::from_string(backend, statement.to_owned()))
+                .await?;
+        }
+        Ok(())
+    }
+}
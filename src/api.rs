@@ -1,20 +1,36 @@
+use std::convert::TryFrom;
+
 use actix_web::{
+    http::header::HeaderMap,
     web::{self, Data},
-    HttpRequest, HttpResponse,
+    HttpMessage, HttpRequest, HttpResponse,
 };
 use async_graphql::{
     http::{playground_source, GraphQLPlaygroundConfig},
-    Context, Enum, ErrorExtensions, MergedObject, MergedSubscription, Object, Result, Schema,
+    Context, Enum, ErrorExtensions, MergedObject, MergedSubscription, Object, PathSegment, Result,
+    Schema, Value,
 };
 use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
-use serde::Deserialize;
+use log::error;
+use sea_orm::{DatabaseConnection, Order};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
-    claim::Token,
+    api_token::{authenticate_api_token, model::is_api_token, ApiTokenMutation, ApiTokenQuery},
+    audit::{audit_or_log, model::AuditEventKind, AuditQuery},
+    claim::{Claim, Token},
     config::CONFIG,
     customer::{CustomerMutation, CustomerQuery, CustomerSubscription},
     errors::Error,
+    invitation::{InvitationMutation, InvitationQuery},
+    organization::{OrganizationMutation, OrganizationQuery},
+    privacy::PrivacyMutation,
     project::{ProjectMutation, ProjectQuery, ProjectSubscription},
+    role::{RoleMutation, RoleQuery},
+    session::{model::ClientInfo, SessionMutation, SessionQuery},
+    team::{TeamMutation, TeamQuery},
+    two_factor::TwoFactorMutation,
     user::{UserMutation, UserQuery, UserSubscription},
     work_report::{WorkReportMutation, WorkReportQuery, WorkReportSubscription},
     API_VERSION,
@@ -22,10 +38,38 @@ use crate::{
 
 pub type RootSchema = Schema<Query, Mutation, Subscription>;
 
+/// Value of the `user-agent` header of the request
+pub struct UserAgent(pub String);
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Id to find a request in the logs, taken from the `x-request-id` header
+/// of a proxy or generated. It is sent back in the same header.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty() && value.len() <= 128)
+            .map(|value| RequestId(value.to_owned()))
+            .unwrap_or_else(|| RequestId(Uuid::new_v4().to_string()))
+    }
+}
+
 #[derive(MergedObject, Default)]
 pub struct Query(
     ServerQuery,
     UserQuery,
+    SessionQuery,
+    ApiTokenQuery,
+    RoleQuery,
+    InvitationQuery,
+    OrganizationQuery,
+    TeamQuery,
+    AuditQuery,
     CustomerQuery,
     ProjectQuery,
     WorkReportQuery,
@@ -34,6 +78,14 @@ pub struct Query(
 #[derive(Default, MergedObject)]
 pub struct Mutation(
     UserMutation,
+    PrivacyMutation,
+    SessionMutation,
+    TwoFactorMutation,
+    ApiTokenMutation,
+    RoleMutation,
+    InvitationMutation,
+    OrganizationMutation,
+    TeamMutation,
     CustomerMutation,
     ProjectMutation,
     WorkReportMutation,
@@ -54,6 +106,22 @@ pub enum MutationType {
     Updated,
 }
 
+#[derive(Enum, Eq, PartialEq, Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl From<SortDirection> for Order {
+    fn from(direction: SortDirection) -> Self {
+        match direction {
+            SortDirection::Asc => Order::Asc,
+            SortDirection::Desc => Order::Desc,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct ServerQuery;
 
@@ -66,10 +134,20 @@ impl ServerQuery {
     async fn registration_enabled(&self) -> bool {
         CONFIG.registration_enabled
     }
+
+    async fn password_login_enabled(&self) -> bool {
+        CONFIG.password_login_enabled
+    }
+
+    /// Login with OpenID Connect is started at `/auth/oidc/login`
+    async fn oidc_enabled(&self) -> bool {
+        CONFIG.oidc.enabled
+    }
 }
 
 pub async fn graphql(
     schema: Data<RootSchema>,
+    db: Data<DatabaseConnection>,
     http_request: HttpRequest,
     gql_request: GraphQLRequest,
 ) -> GraphQLResponse {
@@ -78,14 +156,80 @@ pub async fn graphql(
         .get("authorization")
         .and_then(|value| value.to_str().map(|s| Token(s.to_string())).ok());
     let mut request = gql_request.into_inner();
+    // only used to attribute permission denials in the audit log
+    let mut actor_id = None;
     if let Some(token) = token {
+        if is_api_token(&token.0) {
+            match authenticate_api_token(&db, &token.0).await {
+                Ok(Some(auth)) => {
+                    actor_id = Some(auth.user_id);
+                    request = request.data(auth);
+                }
+                Ok(None) => {}
+                Err(e) => error!("failed to authenticate api token: {}", e),
+            }
+        } else {
+            actor_id = Claim::try_from(token.0.clone())
+                .ok()
+                .and_then(|claim| claim.user_id().ok());
+        }
         request = request.data(token);
     }
 
+    let user_agent = http_request
+        .headers()
+        .get("user-agent")
+        .and_then(|value| value.to_str().map(|s| UserAgent(s.to_string())).ok());
+    if let Some(user_agent) = user_agent {
+        request = request.data(user_agent);
+    }
+
     let conn_info = http_request.connection_info().clone();
     request = request.data(conn_info);
+    if let Some(request_id) = http_request.extensions().get::<RequestId>() {
+        request = request.data(request_id.clone());
+    }
 
-    schema.execute(request).await.into()
+    let response = schema.execute(request).await;
+    audit_permission_denials(&db, &http_request, &response, actor_id).await;
+    response.into()
+}
+
+/// Records every `FORBIDDEN` error of the response in the audit log
+async fn audit_permission_denials(
+    db: &DatabaseConnection,
+    http_request: &HttpRequest,
+    response: &async_graphql::Response,
+    actor_id: Option<Uuid>,
+) {
+    let forbidden = Value::from("FORBIDDEN");
+    for error in &response.errors {
+        let code = error
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.get("code"));
+        if code != Some(&forbidden) {
+            continue;
+        }
+        let path = error
+            .path
+            .iter()
+            .map(|segment| match segment {
+                PathSegment::Field(name) => name.clone(),
+                PathSegment::Index(index) => index.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(".");
+        audit_or_log(
+            db,
+            AuditEventKind::PermissionDenied,
+            &ClientInfo::from_request(http_request),
+            actor_id,
+            None,
+            Some(path),
+        )
+        .await;
+    }
 }
 
 pub async fn graphql_ws(
@@ -0,0 +1,79 @@
+use chrono::Utc;
+use entity::api_token::{ActiveModel, Column, Entity, Model};
+use migration::sea_query::{Condition, Expr};
+use sea_orm::{prelude::*, DatabaseConnection, DbErr, Order, QueryOrder, Set};
+use uuid::Uuid;
+
+use super::model::{join_scopes, NewApiToken};
+
+pub async fn new_api_token(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    organization_id: Option<Uuid>,
+    token_hash: String,
+    new: NewApiToken,
+) -> Result<Model, DbErr> {
+    ActiveModel {
+        user_id: Set(user_id),
+        organization_id: Set(organization_id),
+        name: Set(new.name),
+        token_hash: Set(token_hash),
+        scopes: Set(join_scopes(&new.scopes)),
+        expires_at: Set(new.expires_at),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
+
+/// Lists the API tokens of the user that are not revoked, expired tokens are included
+pub async fn list_api_tokens(db: &DatabaseConnection, user_id: Uuid) -> Result<Vec<Model>, DbErr> {
+    Entity::find()
+        .filter(Column::UserId.eq(user_id))
+        .filter(Column::RevokedAt.is_null())
+        .order_by(Column::CreatedAt, Order::Desc)
+        .all(db)
+        .await
+}
+
+/// Returns the API token with the hash only if it is neither revoked nor expired.
+pub async fn active_api_token_by_hash(
+    db: &DatabaseConnection,
+    token_hash: &str,
+) -> Result<Option<Model>, DbErr> {
+    Entity::find()
+        .filter(Column::TokenHash.eq(token_hash))
+        .filter(Column::RevokedAt.is_null())
+        .filter(
+            Condition::any()
+                .add(Column::ExpiresAt.is_null())
+                .add(Column::ExpiresAt.gt(Utc::now())),
+        )
+        .one(db)
+        .await
+}
+
+pub async fn touch_api_token(db: &DatabaseConnection, id: Uuid) -> Result<(), DbErr> {
+    Entity::update_many()
+        .col_expr(Column::LastUsedAt, Expr::value(Utc::now()))
+        .filter(Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Revokes an API token of the user, returns `false` if there was no such token
+pub async fn revoke_api_token(
+    db: &DatabaseConnection,
+    id: Uuid,
+    user_id: Uuid,
+) -> Result<bool, DbErr> {
+    let res = Entity::update_many()
+        .col_expr(Column::RevokedAt, Expr::value(Utc::now()))
+        .filter(Column::Id.eq(id))
+        .filter(Column::UserId.eq(user_id))
+        .filter(Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected > 0)
+}
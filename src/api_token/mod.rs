@@ -0,0 +1,104 @@
+use async_graphql::{Context, Object};
+use sea_orm::DatabaseConnection;
+use uuid::Uuid;
+
+use crate::{
+    api::database,
+    audit::{audit, model::AuditEventKind},
+    claim::Claim,
+    crypto::{generate_token, hash_token},
+    errors::Result,
+    guards::TokenGuard,
+    session::model::ClientInfo,
+};
+
+use self::{
+    db::{
+        active_api_token_by_hash, list_api_tokens, new_api_token, revoke_api_token, touch_api_token,
+    },
+    model::{
+        bearer_token, ApiToken, ApiTokenAuth, NewApiToken, NewApiTokenResult, API_TOKEN_PREFIX,
+    },
+};
+
+mod db;
+pub mod model;
+
+#[derive(Default)]
+pub struct ApiTokenQuery;
+
+#[Object]
+impl ApiTokenQuery {
+    #[graphql(guard = "TokenGuard")]
+    async fn my_api_tokens(&self, ctx: &Context<'_>) -> Result<Vec<ApiToken>> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        let tokens = list_api_tokens(db, claim.user_id()?).await?;
+        Ok(tokens.into_iter().map(ApiToken::from).collect())
+    }
+}
+
+#[derive(Default)]
+pub struct ApiTokenMutation;
+
+#[Object]
+impl ApiTokenMutation {
+    /// Creates a personal API token, pass it as `Bearer` token in the `authorization` header
+    #[graphql(guard = "TokenGuard")]
+    async fn create_api_token(
+        &self,
+        ctx: &Context<'_>,
+        new: NewApiToken,
+    ) -> Result<NewApiTokenResult> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        let token = format!("{}{}", API_TOKEN_PREFIX, generate_token());
+        // the token is scoped to the organization it was created in
+        let model = new_api_token(
+            db,
+            claim.user_id()?,
+            claim.organization_id().ok(),
+            hash_token(&token),
+            new,
+        )
+        .await?;
+        Ok(NewApiTokenResult {
+            api_token: model.into(),
+            token,
+        })
+    }
+
+    #[graphql(guard = "TokenGuard")]
+    async fn revoke_api_token(&self, ctx: &Context<'_>, id: Uuid) -> Result<bool> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        let user_id = claim.user_id()?;
+        let revoked = revoke_api_token(db, id, user_id).await?;
+        if revoked {
+            audit(
+                db,
+                AuditEventKind::TokenRevoked,
+                &ClientInfo::from_ctx(ctx),
+                Some(user_id),
+                Some(user_id),
+                Some(format!("api token {}", id)),
+            )
+            .await?;
+        }
+        Ok(revoked)
+    }
+}
+
+/// Looks up the personal API token of an `authorization` header
+pub async fn authenticate_api_token(
+    db: &DatabaseConnection,
+    authorization: &str,
+) -> Result<Option<ApiTokenAuth>> {
+    let token_hash = hash_token(bearer_token(authorization));
+    let model = match active_api_token_by_hash(db, &token_hash).await? {
+        Some(model) => model,
+        None => return Ok(None),
+    };
+    touch_api_token(db, model.id).await?;
+    Ok(Some(model.into()))
+}
@@ -0,0 +1,198 @@
+use std::str::FromStr;
+
+use async_graphql::{Enum, InputObject, SimpleObject};
+use entity::api_token::Model;
+use sea_orm::prelude::DateTimeUtc;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{claim::Claim, errors::Error, role::model::Permission};
+
+/// Prefix of personal API tokens, used to tell them apart from JWTs
+pub const API_TOKEN_PREFIX: &str = "zpat_";
+
+/// Scope that limits what a personal API token can be used for
+#[derive(Enum, Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+pub enum ApiTokenScope {
+    CustomersRead,
+    CustomersWrite,
+    ProjectsRead,
+    ProjectsWrite,
+    WorkReportsRead,
+    WorkReportsWrite,
+    /// Start and end time records of work reports
+    TimeRecordsWrite,
+    UsersRead,
+}
+
+impl ApiTokenScope {
+    pub const ALL: [ApiTokenScope; 8] = [
+        ApiTokenScope::CustomersRead,
+        ApiTokenScope::CustomersWrite,
+        ApiTokenScope::ProjectsRead,
+        ApiTokenScope::ProjectsWrite,
+        ApiTokenScope::WorkReportsRead,
+        ApiTokenScope::WorkReportsWrite,
+        ApiTokenScope::TimeRecordsWrite,
+        ApiTokenScope::UsersRead,
+    ];
+
+    /// Name of the scope as it is stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiTokenScope::CustomersRead => "customers:read",
+            ApiTokenScope::CustomersWrite => "customers:write",
+            ApiTokenScope::ProjectsRead => "projects:read",
+            ApiTokenScope::ProjectsWrite => "projects:write",
+            ApiTokenScope::WorkReportsRead => "work_reports:read",
+            ApiTokenScope::WorkReportsWrite => "work_reports:write",
+            ApiTokenScope::TimeRecordsWrite => "time_records:write",
+            ApiTokenScope::UsersRead => "users:read",
+        }
+    }
+
+    /// Scope an API token needs to use a resolver that requires the permission.
+    /// Returns `None` for permissions that can't be used with API tokens at all.
+    pub fn for_permission(permission: Permission) -> Option<ApiTokenScope> {
+        match permission {
+            Permission::CustomerRead => Some(ApiTokenScope::CustomersRead),
+            Permission::CustomerWrite => Some(ApiTokenScope::CustomersWrite),
+            Permission::ProjectRead => Some(ApiTokenScope::ProjectsRead),
+            Permission::ProjectWrite => Some(ApiTokenScope::ProjectsWrite),
+            Permission::WorkReportRead | Permission::WorkReportReadAll => {
+                Some(ApiTokenScope::WorkReportsRead)
+            }
+            Permission::WorkReportWrite | Permission::WorkReportWriteAll => {
+                Some(ApiTokenScope::WorkReportsWrite)
+            }
+            Permission::UserRead => Some(ApiTokenScope::UsersRead),
+            Permission::ProjectManage
+            | Permission::UserWrite
+            | Permission::RoleManage
+            | Permission::MerchandiseApprove => None,
+        }
+    }
+}
+
+impl FromStr for ApiTokenScope {
+    type Err = Error;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        ApiTokenScope::ALL
+            .iter()
+            .find(|scope| scope.as_str() == value)
+            .copied()
+            .ok_or(Error::NotFound)
+    }
+}
+
+/// Parses the space separated scopes stored in the database
+pub fn parse_scopes(scopes: &str) -> Vec<ApiTokenScope> {
+    scopes
+        .split_whitespace()
+        .filter_map(|scope| scope.parse().ok())
+        .collect()
+}
+
+pub fn join_scopes(scopes: &[ApiTokenScope]) -> String {
+    let mut scopes = scopes.iter().map(ApiTokenScope::as_str).collect::<Vec<_>>();
+    scopes.sort_unstable();
+    scopes.dedup();
+    scopes.join(" ")
+}
+
+/// Returns `true` if the value of the `authorization` header is a personal API token
+pub fn is_api_token(authorization: &str) -> bool {
+    bearer_token(authorization).starts_with(API_TOKEN_PREFIX)
+}
+
+/// Strips the `Bearer` prefix from the value of the `authorization` header
+pub fn bearer_token(authorization: &str) -> &str {
+    let authorization = authorization.trim();
+    authorization
+        .strip_prefix("Bearer ")
+        .unwrap_or(authorization)
+        .trim()
+}
+
+#[derive(SimpleObject, Debug, Serialize, Clone)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<ApiTokenScope>,
+    pub expires_at: Option<DateTimeUtc>,
+    pub last_used_at: Option<DateTimeUtc>,
+    pub created_at: DateTimeUtc,
+}
+
+impl From<Model> for ApiToken {
+    fn from(model: Model) -> Self {
+        Self {
+            id: model.id,
+            name: model.name,
+            scopes: parse_scopes(&model.scopes),
+            expires_at: model.expires_at,
+            last_used_at: model.last_used_at,
+            created_at: model.created_at,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, InputObject)]
+pub struct NewApiToken {
+    pub name: String,
+    pub scopes: Vec<ApiTokenScope>,
+    /// The token never expires if not set
+    pub expires_at: Option<DateTimeUtc>,
+}
+
+#[derive(SimpleObject, Debug, Serialize)]
+pub struct NewApiTokenResult {
+    pub api_token: ApiToken,
+    /// The token itself, it is only shown once
+    pub token: String,
+}
+
+/// Personal API token a request was authenticated with, `api::graphql` puts it into the context.
+#[derive(Debug, Clone)]
+pub struct ApiTokenAuth {
+    pub token_id: Uuid,
+    pub user_id: Uuid,
+    pub scopes: Vec<ApiTokenScope>,
+    pub expires_at: Option<DateTimeUtc>,
+    pub organization_id: Option<Uuid>,
+}
+
+impl ApiTokenAuth {
+    pub fn has_scope(&self, scope: ApiTokenScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+
+    /// Claim resolvers can use like the one of a JWT, `jti` is the id of the API token
+    pub fn claim(&self) -> Claim {
+        let user_id = self.user_id.to_string();
+        let exp = self
+            .expires_at
+            .map(|expires_at| expires_at.timestamp() as usize)
+            .unwrap_or(usize::MAX);
+        Claim::new(
+            &user_id,
+            &user_id,
+            &self.token_id.to_string(),
+            self.organization_id,
+            exp,
+        )
+    }
+}
+
+impl From<Model> for ApiTokenAuth {
+    fn from(model: Model) -> Self {
+        Self {
+            token_id: model.id,
+            user_id: model.user_id,
+            scopes: parse_scopes(&model.scopes),
+            expires_at: model.expires_at,
+            organization_id: model.organization_id,
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use entity::audit_event::{ActiveModel, Column, Entity, Model};
+use sea_orm::{prelude::*, DatabaseConnection, DbErr, Order, QueryOrder, QuerySelect, Select, Set};
+use uuid::Uuid;
+
+use crate::session::model::ClientInfo;
+
+use super::model::{AuditEventFilter, AuditEventKind};
+
+pub async fn new_audit_event(
+    db: &DatabaseConnection,
+    kind: AuditEventKind,
+    client: &ClientInfo,
+    actor_id: Option<Uuid>,
+    target_id: Option<Uuid>,
+    details: Option<String>,
+) -> Result<Model, DbErr> {
+    ActiveModel {
+        kind: Set(kind.as_str().to_owned()),
+        actor_id: Set(actor_id),
+        target_id: Set(target_id),
+        ip: Set(client.ip.clone()),
+        user_agent: Set(client.user_agent.clone()),
+        request_id: Set(client.request_id.clone()),
+        details: Set(details),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
+
+fn filtered(filter: &AuditEventFilter) -> Select<Entity> {
+    let mut query = Entity::find();
+    if let Some(kinds) = &filter.kinds {
+        query = query.filter(Column::Kind.is_in(kinds.iter().map(|kind| kind.as_str())));
+    }
+    if let Some(actor_id) = filter.actor_id {
+        query = query.filter(Column::ActorId.eq(actor_id));
+    }
+    if let Some(target_id) = filter.target_id {
+        query = query.filter(Column::TargetId.eq(target_id));
+    }
+    if let Some(since) = filter.since {
+        query = query.filter(Column::CreatedAt.gte(since));
+    }
+    if let Some(until) = filter.until {
+        query = query.filter(Column::CreatedAt.lt(until));
+    }
+    query
+}
+
+pub async fn count_audit_events(
+    db: &DatabaseConnection,
+    filter: &AuditEventFilter,
+) -> Result<usize, DbErr> {
+    filtered(filter).count(db).await
+}
+
+/// Lists the events newest first, `limit` of `None` returns all
+pub async fn list_audit_events(
+    db: &DatabaseConnection,
+    filter: &AuditEventFilter,
+    start: u64,
+    limit: Option<u64>,
+) -> Result<Vec<Model>, DbErr> {
+    let mut query = filtered(filter)
+        .order_by(Column::CreatedAt, Order::Desc)
+        .offset(start);
+    if let Some(limit) = limit {
+        query = query.limit(limit);
+    }
+    query.all(db).await
+}
@@ -0,0 +1,120 @@
+use async_graphql::{
+    connection::{query, Connection, Edge, EmptyFields},
+    Context, Object,
+};
+use futures::{stream, StreamExt};
+use log::error;
+use sea_orm::DatabaseConnection;
+use uuid::Uuid;
+
+use crate::{
+    api::database,
+    errors::{Error, Result},
+    guards::AdminGuard,
+    session::model::ClientInfo,
+};
+
+use self::{
+    db::{count_audit_events, list_audit_events, new_audit_event},
+    model::{AuditEvent, AuditEventFilter, AuditEventKind, ListAuditEventOptions},
+};
+
+mod db;
+pub mod model;
+
+#[derive(Default)]
+pub struct AuditQuery;
+
+#[Object]
+impl AuditQuery {
+    /// Security audit log, newest first
+    #[graphql(guard = "AdminGuard")]
+    async fn audit_events(
+        &self,
+        ctx: &Context<'_>,
+        options: Option<ListAuditEventOptions>,
+    ) -> Result<Connection<usize, AuditEvent, EmptyFields, EmptyFields>> {
+        let db = database(ctx)?;
+        let options = options.unwrap_or_default();
+        let filter = options.filter.unwrap_or_default();
+
+        Ok(query(
+            options.after,
+            options.before,
+            options.first,
+            options.last,
+            |after, before, first, last| async move {
+                let count = count_audit_events(db, &filter).await?;
+                let mut start = after.map(|after| after + 1).unwrap_or(0);
+                let mut end = before.unwrap_or(count);
+                if let Some(first) = first {
+                    end = (start + first).min(end);
+                }
+                if let Some(last) = last {
+                    start = if last > end - start { end } else { end - last };
+                }
+
+                let events =
+                    list_audit_events(db, &filter, start as u64, Some((end - start) as u64))
+                        .await?;
+
+                let mut connection = Connection::new(start > 0, end < count);
+                connection
+                    .append_stream(
+                        stream::iter(events)
+                            .enumerate()
+                            .map(|(n, event)| Edge::new(n + start, AuditEvent::from(event))),
+                    )
+                    .await;
+                Ok::<_, Error>(connection)
+            },
+        )
+        .await?)
+    }
+
+    /// All events matching the filter as CSV, newest first
+    #[graphql(guard = "AdminGuard")]
+    async fn audit_events_csv(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<AuditEventFilter>,
+    ) -> Result<String> {
+        let db = database(ctx)?;
+        let events = list_audit_events(db, &filter.unwrap_or_default(), 0, None).await?;
+        let mut csv = String::from(AuditEvent::CSV_HEADER);
+        csv.push_str("\r\n");
+        for event in events {
+            csv.push_str(&AuditEvent::from(event).to_csv_row());
+            csv.push_str("\r\n");
+        }
+        Ok(csv)
+    }
+}
+
+/// Appends an event to the audit log
+pub async fn audit(
+    db: &DatabaseConnection,
+    kind: AuditEventKind,
+    client: &ClientInfo,
+    actor_id: Option<Uuid>,
+    target_id: Option<Uuid>,
+    details: Option<String>,
+) -> Result<()> {
+    new_audit_event(db, kind, client, actor_id, target_id, details).await?;
+    Ok(())
+}
+
+/// Like `audit`, but only logs when the event can't be written.
+/// For places where the request already failed for another reason.
+pub async fn audit_or_log(
+    db: &DatabaseConnection,
+    kind: AuditEventKind,
+    client: &ClientInfo,
+    actor_id: Option<Uuid>,
+    target_id: Option<Uuid>,
+    details: Option<String>,
+) {
+    if let Err(e) = audit(db, kind, client, actor_id, target_id, details).await {
+        error!("failed to write audit event {}: {}", kind.as_str(), e);
+    }
+}
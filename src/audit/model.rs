@@ -0,0 +1,158 @@
+use async_graphql::{Enum, InputObject, SimpleObject};
+use entity::audit_event::Model;
+use sea_orm::prelude::DateTimeUtc;
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Enum, Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+pub enum AuditEventKind {
+    LoginSucceeded,
+    LoginFailed,
+    PasswordChanged,
+    AvatarChanged,
+    AdminFlagChanged,
+    TokenRevoked,
+    PermissionDenied,
+    DataExported,
+    AccountDeleted,
+}
+
+impl AuditEventKind {
+    pub const ALL: [AuditEventKind; 9] = [
+        AuditEventKind::LoginSucceeded,
+        AuditEventKind::LoginFailed,
+        AuditEventKind::PasswordChanged,
+        AuditEventKind::AvatarChanged,
+        AuditEventKind::AdminFlagChanged,
+        AuditEventKind::TokenRevoked,
+        AuditEventKind::PermissionDenied,
+        AuditEventKind::DataExported,
+        AuditEventKind::AccountDeleted,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEventKind::LoginSucceeded => "login_succeeded",
+            AuditEventKind::LoginFailed => "login_failed",
+            AuditEventKind::PasswordChanged => "password_changed",
+            AuditEventKind::AvatarChanged => "avatar_changed",
+            AuditEventKind::AdminFlagChanged => "admin_flag_changed",
+            AuditEventKind::TokenRevoked => "token_revoked",
+            AuditEventKind::PermissionDenied => "permission_denied",
+            AuditEventKind::DataExported => "data_exported",
+            AuditEventKind::AccountDeleted => "account_deleted",
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Serialize, Clone)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub kind: Option<AuditEventKind>,
+    /// User that caused the event, empty for anonymous requests
+    pub actor_id: Option<Uuid>,
+    /// User the event is about
+    pub target_id: Option<Uuid>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    /// Value of the `x-request-id` header of the request
+    pub request_id: Option<String>,
+    pub details: Option<String>,
+    pub created_at: DateTimeUtc,
+}
+
+impl From<Model> for AuditEvent {
+    fn from(model: Model) -> Self {
+        let kind = AuditEventKind::ALL
+            .iter()
+            .find(|kind| kind.as_str() == model.kind)
+            .copied();
+        Self {
+            id: model.id,
+            kind,
+            actor_id: model.actor_id,
+            target_id: model.target_id,
+            ip: model.ip,
+            user_agent: model.user_agent,
+            request_id: model.request_id,
+            details: model.details,
+            created_at: model.created_at,
+        }
+    }
+}
+
+impl AuditEvent {
+    pub const CSV_HEADER: &'static str =
+        "id,created_at,kind,actor_id,target_id,ip,user_agent,request_id,details";
+
+    pub fn to_csv_row(&self) -> String {
+        let id = |id: Option<Uuid>| id.map(|id| id.to_string()).unwrap_or_default();
+        [
+            self.id.to_string(),
+            self.created_at.to_rfc3339(),
+            self.kind
+                .map(|kind| kind.as_str())
+                .unwrap_or_default()
+                .to_owned(),
+            id(self.actor_id),
+            id(self.target_id),
+            self.ip.clone().unwrap_or_default(),
+            self.user_agent.clone().unwrap_or_default(),
+            self.request_id.clone().unwrap_or_default(),
+            self.details.clone().unwrap_or_default(),
+        ]
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+}
+
+/// Quotes the field if needed (RFC 4180). Fields starting with a formula character
+/// are prefixed with `'`, so spreadsheets don't evaluate client controlled values.
+fn csv_field(field: &str) -> String {
+    let field = if field.starts_with(&['=', '+', '-', '@', '\t', '\r'][..]) {
+        format!("'{}", field)
+    } else {
+        field.to_owned()
+    };
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+#[derive(Serialize, Debug, InputObject, Default, Clone)]
+pub struct AuditEventFilter {
+    pub kinds: Option<Vec<AuditEventKind>>,
+    pub actor_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    /// Only events at or after this time
+    pub since: Option<DateTimeUtc>,
+    /// Only events before this time
+    pub until: Option<DateTimeUtc>,
+}
+
+#[derive(Serialize, Debug, InputObject, Default)]
+pub struct ListAuditEventOptions {
+    pub filter: Option<AuditEventFilter>,
+    pub after: Option<String>,
+    pub before: Option<String>,
+    pub first: Option<i32>,
+    pub last: Option<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_not_evaluated_as_formulas() {
+        assert_eq!(csv_field("firefox"), "firefox");
+        assert_eq!(csv_field("=1+1"), "'=1+1");
+        assert_eq!(csv_field("\t=1+1"), "'\t=1+1");
+        assert_eq!(csv_field("\r=1+1"), "\"'\r=1+1\"");
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+}
@@ -0,0 +1,119 @@
+use chrono::Utc;
+use entity::{
+    user::{ActiveModel, Entity, Model},
+    user_identity,
+};
+use sea_orm::{prelude::*, DatabaseConnection, Set};
+use uuid::Uuid;
+
+use crate::{
+    audit::{audit, model::AuditEventKind},
+    errors::Result,
+    organization::db::join_default_organization,
+    role::db::{assign_default_roles, sync_admin_role},
+    session::model::ClientInfo,
+};
+
+/// Password hash of users created by an external login, no password matches it
+const UNUSABLE_PASSWORD_HASH: &str = "!";
+
+/// User linked to the account `subject` of the provider `issuer`
+pub async fn user_by_identity(
+    db: &DatabaseConnection,
+    issuer: &str,
+    subject: &str,
+) -> Result<Option<Model>> {
+    let identity = user_identity::Entity::find()
+        .filter(user_identity::Column::Issuer.eq(issuer))
+        .filter(user_identity::Column::Subject.eq(subject))
+        .one(db)
+        .await?;
+    match identity {
+        Some(identity) => Ok(Entity::find_by_id(identity.user_id).one(db).await?),
+        None => Ok(None),
+    }
+}
+
+/// Links the account of a provider, later logins find the user even if the email changes
+pub async fn link_identity(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    issuer: &str,
+    subject: &str,
+) -> Result<()> {
+    user_identity::ActiveModel {
+        user_id: Set(user_id),
+        issuer: Set(issuer.to_owned()),
+        subject: Set(subject.to_owned()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+/// Whether the provider at `issuer` created the user. Local accounts and accounts of other
+/// providers must not be taken over just because the email matches.
+pub async fn provisioned_by(db: &DatabaseConnection, user: &Model, issuer: &str) -> Result<bool> {
+    if user.password_hash != UNUSABLE_PASSWORD_HASH {
+        return Ok(false);
+    }
+    let identities = user_identity::Entity::find()
+        .filter(user_identity::Column::UserId.eq(user.id))
+        .all(db)
+        .await?;
+    Ok(identities.iter().all(|identity| identity.issuer == issuer))
+}
+
+/// Creates a user that can only log in through an external provider
+pub async fn provision_user(
+    db: &DatabaseConnection,
+    email: String,
+    name: Option<String>,
+    email_verified: bool,
+) -> Result<Model> {
+    let user = ActiveModel {
+        email: Set(email),
+        email_verified_at: Set(email_verified.then(Utc::now)),
+        password_hash: Set(UNUSABLE_PASSWORD_HASH.to_owned()),
+        name: Set(name),
+        is_admin: Set(false),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    assign_default_roles(db, user.id).await?;
+    join_default_organization(db, user.id).await?;
+    Ok(user)
+}
+
+/// Sets the admin flag from the groups of the provider. Only accounts the provider created
+/// are changed, the flag of local accounts stays managed in zorius.
+pub async fn set_admin(db: &DatabaseConnection, user: Model, is_admin: bool) -> Result<Model> {
+    if user.is_admin == is_admin || user.password_hash != UNUSABLE_PASSWORD_HASH {
+        return Ok(user);
+    }
+    audit(
+        db,
+        AuditEventKind::AdminFlagChanged,
+        &ClientInfo::default(),
+        None,
+        Some(user.id),
+        Some(format!("{} by the identity provider", is_admin)),
+    )
+    .await?;
+    sync_admin_role(db, user.id, is_admin).await?;
+    let mut user: ActiveModel = user.into();
+    user.is_admin = Set(is_admin);
+    Ok(user.update(db).await?)
+}
+
+/// Updates the name with the one of the directory, `None` keeps the current name
+pub async fn set_name(db: &DatabaseConnection, user: Model, name: Option<String>) -> Result<Model> {
+    if name.is_none() || user.name == name {
+        return Ok(user);
+    }
+    let mut user: ActiveModel = user.into();
+    user.name = Set(name);
+    Ok(user.update(db).await?)
+}
@@ -0,0 +1,132 @@
+use async_trait::async_trait;
+use entity::user;
+use ldap3::{ldap_escape, LdapConnAsync, Scope, SearchEntry};
+use log::{error, info};
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    config::CONFIG,
+    errors::{Error, Result},
+    user::db::user_by_email,
+};
+
+use super::{
+    db::{link_identity, provision_user, provisioned_by, set_admin, set_name, user_by_identity},
+    AuthBackend,
+};
+
+/// Result code of a bind with a wrong password
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// Searches the user in an LDAP directory (e.g. Active Directory) and binds with its password.
+/// Users are created on their first login and linked to their entry, existing accounts with the
+/// same email are only linked if the directory created them. Their name and, for users created
+/// by the directory, the admin flag are updated from the directory.
+pub struct LdapBackend;
+
+#[async_trait]
+impl AuthBackend for LdapBackend {
+    async fn authenticate(
+        &self,
+        db: &DatabaseConnection,
+        email: &str,
+        password: &str,
+    ) -> Result<Option<user::Model>> {
+        // a bind without password is an anonymous bind and would always succeed
+        if password.is_empty() {
+            return Ok(None);
+        }
+        // an unreachable directory counts as a failed login, so the lockout still applies
+        let entry = match bind_user(email, password).await {
+            Ok(Some(entry)) => entry,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                error!("ldap login of {} failed: {}", email, e);
+                return Ok(None);
+            }
+        };
+
+        let config = &CONFIG.ldap;
+        let email =
+            first_attribute(&entry, &config.email_attribute).unwrap_or_else(|| email.to_owned());
+        let name = first_attribute(&entry, &config.name_attribute);
+        // the entry is identified by its DN in the directory at `url`
+        let user = match user_by_identity(db, &config.url, &entry.dn).await? {
+            Some(user) => user,
+            None => {
+                let user = match user_by_email(db, &email).await? {
+                    Some(user) if provisioned_by(db, &user, &config.url).await? => user,
+                    Some(_) => return Err(Error::EmailAlreadyRegistred),
+                    None => {
+                        info!("provisioning user {} from ldap", email);
+                        provision_user(db, email, name.clone(), true).await?
+                    }
+                };
+                link_identity(db, user.id, &config.url, &entry.dn).await?;
+                user
+            }
+        };
+        let user = set_name(db, user, name).await?;
+
+        if config.admin_groups.is_empty() {
+            return Ok(Some(user));
+        }
+        let groups = entry
+            .attrs
+            .get(&config.group_attribute)
+            .cloned()
+            .unwrap_or_default();
+        let is_admin = groups.iter().any(|group| {
+            config
+                .admin_groups
+                .iter()
+                .any(|admin_group| admin_group.eq_ignore_ascii_case(group))
+        });
+        Ok(Some(set_admin(db, user, is_admin).await?))
+    }
+}
+
+/// Searches the entry of the login and binds with its DN and the password.
+/// Returns `None` if there is no or more than one entry or the password is wrong.
+async fn bind_user(login: &str, password: &str) -> Result<Option<SearchEntry>> {
+    let config = &CONFIG.ldap;
+    let (conn, mut ldap) = LdapConnAsync::new(&config.url).await?;
+    ldap3::drive!(conn);
+
+    if !config.bind_dn.is_empty() {
+        ldap.simple_bind(&config.bind_dn, &config.bind_password)
+            .await?
+            .success()?;
+    }
+    let filter = config.user_filter.replace("{login}", &ldap_escape(login));
+    let attributes = vec![
+        config.email_attribute.as_str(),
+        config.name_attribute.as_str(),
+        config.group_attribute.as_str(),
+    ];
+    let (entries, _) = ldap
+        .search(&config.base_dn, Scope::Subtree, &filter, attributes)
+        .await?
+        .success()?;
+    if entries.len() != 1 {
+        let _ = ldap.unbind().await;
+        return Ok(None);
+    }
+    let entry = SearchEntry::construct(entries.into_iter().next().expect("one entry"));
+
+    let res = ldap.simple_bind(&entry.dn, password).await?;
+    let _ = ldap.unbind().await;
+    if res.rc == INVALID_CREDENTIALS {
+        return Ok(None);
+    }
+    res.success()?;
+    Ok(Some(entry))
+}
+
+fn first_attribute(entry: &SearchEntry, attribute: &str) -> Option<String> {
+    entry
+        .attrs
+        .get(attribute)
+        .and_then(|values| values.first())
+        .cloned()
+}
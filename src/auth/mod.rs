@@ -0,0 +1,57 @@
+use async_trait::async_trait;
+use entity::user;
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    config::CONFIG,
+    errors::{Error, Result},
+};
+
+use self::{ldap::LdapBackend, password::PasswordBackend};
+
+pub mod db;
+mod ldap;
+mod password;
+
+/// Checks the credentials `UserQuery::login` was called with
+#[async_trait]
+pub trait AuthBackend: Send + Sync {
+    /// Returns the user if the credentials are correct and `None` if they are not.
+    /// Backends that manage the users elsewhere create or update the zorius user.
+    async fn authenticate(
+        &self,
+        db: &DatabaseConnection,
+        email: &str,
+        password: &str,
+    ) -> Result<Option<user::Model>>;
+}
+
+/// The backends enabled in the config, in the order they are tried
+pub fn backends() -> Vec<Box<dyn AuthBackend>> {
+    let mut backends: Vec<Box<dyn AuthBackend>> = vec![];
+    if CONFIG.password_login_enabled {
+        backends.push(Box::new(PasswordBackend));
+    }
+    if CONFIG.ldap.enabled {
+        backends.push(Box::new(LdapBackend));
+    }
+    backends
+}
+
+/// Returns the user of the first backend that accepts the credentials
+pub async fn authenticate(
+    db: &DatabaseConnection,
+    email: &str,
+    password: &str,
+) -> Result<Option<user::Model>> {
+    let backends = backends();
+    if backends.is_empty() {
+        return Err(Error::PasswordLoginDisabled);
+    }
+    for backend in backends {
+        if let Some(user) = backend.authenticate(db, email, password).await? {
+            return Ok(Some(user));
+        }
+    }
+    Ok(None)
+}
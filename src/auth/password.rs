@@ -0,0 +1,41 @@
+use async_trait::async_trait;
+use entity::user;
+use log::debug;
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    errors::Result,
+    user::{
+        db::{reset_password, user_by_email},
+        model::User,
+    },
+};
+
+use super::AuthBackend;
+
+/// Checks the password against the hash stored in the database
+pub struct PasswordBackend;
+
+#[async_trait]
+impl AuthBackend for PasswordBackend {
+    async fn authenticate(
+        &self,
+        db: &DatabaseConnection,
+        email: &str,
+        password: &str,
+    ) -> Result<Option<user::Model>> {
+        let model = match user_by_email(db, email).await? {
+            Some(model) if model.deleted_at.is_none() => model,
+            _ => return Ok(None),
+        };
+        let user = User::from(model.clone());
+        if !user.is_password_correct(password).await {
+            return Ok(None);
+        }
+        if user.password_needs_rehash() {
+            debug!("upgrading password hash of user {}", user.get_id());
+            reset_password(db, *user.get_id(), &User::hash_password(password).await?).await?;
+        }
+        Ok(Some(model))
+    }
+}
@@ -0,0 +1,274 @@
+//! Avatars are stored in `static/avatar` in every size of `AVATAR_SIZES` as PNG and WebP,
+//! named `{stem}-{size}.{extension}`. The user only stores the stem.
+//! Users without an avatar get an identicon generated from their id.
+
+use std::{
+    io::{Cursor, ErrorKind},
+    path::PathBuf,
+};
+
+use actix_web::{http::header, web, HttpResponse};
+use async_graphql::Enum;
+use image::{
+    imageops::FilterType, io::Reader as ImageReader, DynamicImage, ImageBuffer, ImageFormat,
+    ImageOutputFormat, Rgba,
+};
+use log::{debug, error};
+use mime::Mime;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{
+    config::CONFIG,
+    errors::{Error, Result},
+};
+
+pub const AVATAR_DIR: &str = "static/avatar";
+/// Edge lengths in pixels the avatars are generated in
+pub const AVATAR_SIZES: [u32; 4] = [32, 64, 128, 512];
+/// Avatars uploaded before there were several sizes are a single 512x512 PNG
+const LEGACY_EXTENSION: &str = ".png";
+const WEBP_QUALITY: f32 = 80.0;
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AvatarFormat {
+    Png,
+    Webp,
+}
+
+impl AvatarFormat {
+    const ALL: [AvatarFormat; 2] = [AvatarFormat::Png, AvatarFormat::Webp];
+
+    fn extension(&self) -> &'static str {
+        match self {
+            AvatarFormat::Png => "png",
+            AvatarFormat::Webp => "webp",
+        }
+    }
+}
+
+/// Image format of an upload, PNG, JPEG, WebP and GIF are accepted
+pub fn upload_format(content_type: Option<&str>) -> Result<ImageFormat> {
+    let mime = content_type
+        .and_then(|content_type| content_type.parse::<Mime>().ok())
+        .ok_or(Error::WrongMediaType)?;
+    match (mime.type_(), mime.subtype().as_str()) {
+        (mime::IMAGE, "png") => Ok(ImageFormat::Png),
+        (mime::IMAGE, "jpeg") => Ok(ImageFormat::Jpeg),
+        (mime::IMAGE, "webp") => Ok(ImageFormat::WebP),
+        (mime::IMAGE, "gif") => Ok(ImageFormat::Gif),
+        _ => Err(Error::WrongMediaType),
+    }
+}
+
+/// Decodes the upload and writes it in all sizes and formats, returns the stem of the files.
+///
+/// The images are encoded from the pixels only, so EXIF and other metadata is dropped.
+/// The EXIF orientation is applied before. Animated GIFs keep their first frame.
+/// Blocks while encoding, call it with `spawn_blocking`.
+pub fn save_avatar(data: &[u8], format: ImageFormat) -> Result<String> {
+    if data.len() > CONFIG.avatar.max_bytes {
+        return Err(Error::AvatarTooLarge);
+    }
+    let (width, height) = ImageReader::with_format(Cursor::new(data), format).into_dimensions()?;
+    if u64::from(width) * u64::from(height) > CONFIG.avatar.max_pixels {
+        return Err(Error::AvatarTooLarge);
+    }
+    let img = image::load_from_memory_with_format(data, format)?;
+    let img = apply_orientation(img, exif_orientation(data));
+
+    let stem = Uuid::new_v4().to_string();
+    for size in AVATAR_SIZES {
+        let resized = img.resize_to_fill(size, size, FilterType::Lanczos3);
+        for format in AvatarFormat::ALL {
+            if let Err(e) = write_avatar(&resized, &stem, size, format) {
+                error!("failed to save avatar {stem} in {size}px as {format:?}: {e:?}");
+                remove_avatar_files(&stem);
+                return Err(e);
+            }
+        }
+        debug!("saved avatar {stem} in {size}px");
+    }
+    Ok(stem)
+}
+
+fn write_avatar(img: &DynamicImage, stem: &str, size: u32, format: AvatarFormat) -> Result<()> {
+    let path = avatar_path(stem, size, format);
+    match format {
+        AvatarFormat::Png => img.save_with_format(path, ImageFormat::Png)?,
+        AvatarFormat::Webp => {
+            let rgba = img.to_rgba8();
+            let encoded = webp::Encoder::from_rgba(rgba.as_raw(), rgba.width(), rgba.height())
+                .encode(WEBP_QUALITY);
+            std::fs::write(path, &*encoded)?;
+        }
+    }
+    Ok(())
+}
+
+/// EXIF orientation tag of the image, 1 (upright) if there is none
+fn exif_orientation(data: &[u8]) -> u32 {
+    exif::Reader::new()
+        .read_from_container(&mut Cursor::new(data))
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+        })
+        .unwrap_or(1)
+}
+
+fn apply_orientation(img: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+fn avatar_path(stem: &str, size: u32, format: AvatarFormat) -> PathBuf {
+    [AVATAR_DIR, &format!("{stem}-{size}.{}", format.extension())]
+        .iter()
+        .collect()
+}
+
+/// Largest PNG of the avatar
+pub fn largest_avatar_path(avatar_filename: &str) -> PathBuf {
+    if avatar_filename.ends_with(LEGACY_EXTENSION) {
+        return [AVATAR_DIR, avatar_filename].iter().collect();
+    }
+    let size = AVATAR_SIZES[AVATAR_SIZES.len() - 1];
+    avatar_path(avatar_filename, size, AvatarFormat::Png)
+}
+
+/// Removes all sizes and formats of the avatar, missing files are ignored
+pub fn remove_avatar_files(avatar_filename: &str) {
+    let paths: Vec<PathBuf> = if avatar_filename.ends_with(LEGACY_EXTENSION) {
+        vec![[AVATAR_DIR, avatar_filename].iter().collect()]
+    } else {
+        AVATAR_SIZES
+            .iter()
+            .flat_map(|size| {
+                AvatarFormat::ALL
+                    .iter()
+                    .map(move |format| avatar_path(avatar_filename, *size, *format))
+            })
+            .collect()
+    };
+    for path in paths {
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                error!("failed to remove avatar {}: {e}", path.display())
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Smallest generated size that is at least as large as the requested one
+fn avatar_size(size: u32) -> u32 {
+    AVATAR_SIZES
+        .iter()
+        .copied()
+        .find(|available| *available >= size)
+        .unwrap_or(AVATAR_SIZES[AVATAR_SIZES.len() - 1])
+}
+
+/// Url of the avatar in the next larger generated size.
+/// Identicons are only available as PNG.
+pub fn avatar_url(
+    user_id: Uuid,
+    avatar_filename: Option<&str>,
+    size: u32,
+    format: AvatarFormat,
+) -> String {
+    let base_url = CONFIG.base_url();
+    let size = avatar_size(size);
+    match avatar_filename {
+        Some(file) if file.ends_with(LEGACY_EXTENSION) => format!("{base_url}/avatar/{file}"),
+        Some(stem) => format!("{base_url}/avatar/{stem}-{size}.{}", format.extension()),
+        None => format!("{base_url}/avatar/identicon/{user_id}?size={size}"),
+    }
+}
+
+/// Symmetric 5x5 identicon on a light background, the same seed always gives the same image
+pub fn identicon(seed: &[u8], size: u32) -> Result<Vec<u8>> {
+    let hash = Sha256::digest(seed);
+    // keep the color dark enough to contrast with the background
+    let color = Rgba([hash[0] / 2 + 32, hash[1] / 2 + 32, hash[2] / 2 + 32, 255]);
+    let background = Rgba([240, 240, 240, 255]);
+    // the left three columns are taken from the hash and mirrored to the right
+    let filled = |row: u32, col: u32| {
+        let col = col.min(4 - col);
+        let bit = (row * 3 + col) as usize;
+        hash[3 + bit / 8] & (1 << (bit % 8)) != 0
+    };
+
+    // 5 cells plus half a cell of margin on every side
+    let cell = (size / 6).max(1);
+    let margin = (size - cell * 5) / 2;
+    let img = ImageBuffer::from_fn(size, size, |x, y| {
+        let inside =
+            (margin..margin + cell * 5).contains(&x) && (margin..margin + cell * 5).contains(&y);
+        if inside && filled((y - margin) / cell, (x - margin) / cell) {
+            color
+        } else {
+            background
+        }
+    });
+
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(img).write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)?;
+    Ok(png)
+}
+
+#[derive(Deserialize)]
+pub struct IdenticonQuery {
+    size: Option<u32>,
+}
+
+/// Serves the generated avatar of users without an uploaded one
+pub async fn identicon_handler(
+    user_id: web::Path<Uuid>,
+    query: web::Query<IdenticonQuery>,
+) -> HttpResponse {
+    let size = avatar_size(query.size.unwrap_or(128));
+    match identicon(user_id.as_bytes(), size) {
+        Ok(png) => HttpResponse::Ok()
+            .content_type(mime::IMAGE_PNG)
+            .insert_header((header::CACHE_CONTROL, "public, max-age=604800"))
+            .body(png),
+        Err(e) => {
+            error!("failed to generate identicon: {e:?}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_round_up_to_generated_ones() {
+        assert_eq!(avatar_size(1), 32);
+        assert_eq!(avatar_size(64), 64);
+        assert_eq!(avatar_size(100), 128);
+        assert_eq!(avatar_size(4096), 512);
+    }
+
+    #[test]
+    fn identicons_are_deterministic() {
+        let id = Uuid::new_v4();
+        let first = identicon(id.as_bytes(), 64).unwrap();
+        assert_eq!(first, identicon(id.as_bytes(), 64).unwrap());
+        let img = image::load_from_memory_with_format(&first, ImageFormat::Png).unwrap();
+        assert_eq!((img.width(), img.height()), (64, 64));
+    }
+}
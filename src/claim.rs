@@ -2,11 +2,18 @@ use std::convert::{TryFrom, TryInto};
 
 use async_graphql::{Context, Result};
 use chrono::Local;
-use jsonwebtoken::{decode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{
+    decode, errors::ErrorKind, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{config::CONFIG, errors::Error};
+use crate::{
+    api_token::model::{is_api_token, ApiTokenAuth},
+    config::CONFIG,
+    errors::Error,
+    jwks::JWT_KEYS,
+};
 
 pub struct Token(pub String);
 
@@ -25,40 +32,47 @@ pub struct Claim {
     nbf: usize,
     /// Time at which the JWT was issued; can be used to determine age of the JWT
     iat: usize,
+    /// Id of the session the JWT belongs to
+    jti: String,
+    /// Id of the organization the requests are scoped to (not in JWT standard)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    org: Option<String>,
 }
 
 impl Claim {
-    /// Creates a new Claim with the users email, id and sets the time when the token expires.
-    pub fn new(sub: &str, id: &str, exp: usize) -> Self {
-        let mut iss = String::new();
-        if CONFIG.web.enable_ssl {
-            iss.push_str("https://");
-        } else {
-            iss.push_str("http://");
-        };
-        iss.push_str(&CONFIG.domain.clone());
+    /// Creates a new Claim with the users email, id, the session id, the current organization
+    /// and sets the time when the token expires.
+    pub fn new(sub: &str, id: &str, jti: &str, org: Option<Uuid>, exp: usize) -> Self {
         Self {
-            iss,
+            iss: CONFIG.base_url(),
             sub: sub.to_owned(),
             id: id.to_owned(),
             exp,
             nbf: Local::now().timestamp() as usize,
             iat: Local::now().timestamp() as usize,
+            jti: jti.to_owned(),
+            org: org.map(|org| org.to_string()),
         }
     }
 
     /// Gets the Claim from async_graphql context
-    /// `Token(Sting)` must be present in context
+    /// `Token(Sting)` or `ApiTokenAuth` must be present in context
     pub fn from_ctx(ctx: &Context<'_>) -> Result<Self, Error> {
+        if let Some(auth) = ctx.data_opt::<ApiTokenAuth>() {
+            return Ok(auth.claim());
+        }
         let value: &Token = match ctx.data::<Token>() {
             Err(_e) => return Err(Error::MissingToken),
             Ok(r) => r,
         };
-        let claim = Claim::try_from(value.0.to_owned())?;
-        if claim.token_expired() {
-            return Err(Error::ExpiredToken);
+        // `api::graphql` only leaves the API token in the context if it could not be authenticated
+        if is_api_token(&value.0) {
+            return Err(Error::InvalidApiToken);
         }
-        Ok(claim)
+        Claim::try_from(value.0.to_owned()).map_err(|e| match e.kind() {
+            ErrorKind::ExpiredSignature | ErrorKind::ImmatureSignature => Error::ExpiredToken,
+            _ => Error::Jwt(e),
+        })
     }
 
     /// Return a reference to the `user_id`
@@ -69,10 +83,21 @@ impl Claim {
         }
     }
 
-    /// Chekcs if the token is expired
-    pub fn token_expired(&self) -> bool {
-        let now = Local::now().timestamp() as usize;
-        self.nbf >= now && self.exp <= now
+    /// Return the id of the session this token was issued for
+    pub fn session_id(&self) -> Result<Uuid, Error> {
+        match Uuid::parse_str(&self.jti) {
+            Ok(r) => Ok(r),
+            Err(_) => Err(Error::MalformedToken),
+        }
+    }
+
+    /// Return the id of the organization the token is scoped to
+    pub fn organization_id(&self) -> Result<Uuid, Error> {
+        match self.org.as_deref().map(Uuid::parse_str) {
+            Some(Ok(r)) => Ok(r),
+            Some(Err(_)) => Err(Error::MalformedToken),
+            None => Err(Error::NoOrganization),
+        }
     }
 }
 
@@ -80,10 +105,7 @@ impl TryInto<String> for Claim {
     type Error = jsonwebtoken::errors::Error;
 
     fn try_into(self) -> Result<String, Self::Error> {
-        let key = EncodingKey::from_secret(CONFIG.secret_key.as_bytes());
-        let algo = Algorithm::HS512;
-
-        jsonwebtoken::encode(&Header::new(algo), &self, &key)
+        JWT_KEYS.encode(&self)
     }
 }
 
@@ -91,13 +113,74 @@ impl TryFrom<String> for Claim {
     type Error = jsonwebtoken::errors::Error;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        let secret = CONFIG.secret_key.as_bytes();
         let split = value.split(' ').collect::<Vec<&str>>();
         let token = split.get(1).unwrap_or(&"");
 
-        let dec = DecodingKey::from_secret(secret);
+        JWT_KEYS.decode::<Claim>(token.trim_matches(' '))
+    }
+}
+
+/// Short lived token `login` hands out instead of a `Claim`
+/// when the user has to confirm the login with a second factor.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorChallenge {
+    /// Issuer of the JWT
+    iss: String,
+    /// Id of the user that has to confirm the login
+    sub: String,
+    /// Time after which the JWT expires
+    exp: usize,
+    /// Time at which the JWT was issued
+    iat: usize,
+    /// Always `TWO_FACTOR_PURPOSE`, so the challenge can't be mixed up with other tokens
+    purpose: String,
+}
+
+const TWO_FACTOR_PURPOSE: &str = "two_factor";
+
+impl TwoFactorChallenge {
+    pub fn new(user_id: &Uuid) -> Self {
+        let now = Local::now().timestamp();
+        Self {
+            iss: CONFIG.base_url(),
+            sub: user_id.to_string(),
+            exp: (now + CONFIG.two_factor_challenge_lifetime) as usize,
+            iat: now as usize,
+            purpose: TWO_FACTOR_PURPOSE.to_owned(),
+        }
+    }
+
+    /// Return the id of the user that has to confirm the login
+    pub fn user_id(&self) -> Result<Uuid, Error> {
+        match Uuid::parse_str(&self.sub) {
+            Ok(r) => Ok(r),
+            Err(_) => Err(Error::MalformedToken),
+        }
+    }
+}
+
+impl TryInto<String> for TwoFactorChallenge {
+    type Error = jsonwebtoken::errors::Error;
+
+    fn try_into(self) -> Result<String, Self::Error> {
+        let key = EncodingKey::from_secret(CONFIG.secret_key.as_bytes());
+        let algo = Algorithm::HS512;
+
+        jsonwebtoken::encode(&Header::new(algo), &self, &key)
+    }
+}
+
+impl TryFrom<String> for TwoFactorChallenge {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let dec = DecodingKey::from_secret(CONFIG.secret_key.as_bytes());
         let vali = Validation::new(Algorithm::HS512);
 
-        Ok(decode::<Claim>(token.trim_matches(' '), &dec, &vali)?.claims)
+        let challenge = decode::<TwoFactorChallenge>(value.trim(), &dec, &vali)?.claims;
+        if challenge.purpose != TWO_FACTOR_PURPOSE {
+            return Err(Error::MalformedToken);
+        }
+        Ok(challenge)
     }
 }
@@ -1,7 +1,8 @@
 use lazy_static::lazy_static;
-use std::{net::IpAddr, result::Result};
+use std::{collections::HashMap, net::IpAddr, result::Result};
 
 use config::{Config, ConfigError, Environment, File, FileFormat};
+use jsonwebtoken::Algorithm;
 use serde::Deserialize;
 
 lazy_static! {
@@ -13,10 +14,25 @@ pub struct Settings {
     pub web: WebServerConfig,
     pub db: DbServerConfig,
     pub secret_key: String,
+    pub jwt: JwtConfig,
     pub domain: String,
     pub token_lifetime: i64,
+    pub refresh_token_lifetime: i64,
+    pub password_reset_token_lifetime: i64,
+    pub two_factor_challenge_lifetime: i64,
     pub registration_enabled: bool,
+    pub require_email_verification: bool,
+    pub email_verification_token_lifetime: i64,
+    pub invitation_lifetime: i64,
+    pub data_export_lifetime: i64,
+    pub trashed_customer_lifetime: i64,
     pub mailer: MailConfig,
+    pub password_hash: PasswordHashConfig,
+    pub login_throttle: LoginThrottleConfig,
+    pub avatar: AvatarConfig,
+    pub password_login_enabled: bool,
+    pub oidc: OidcConfig,
+    pub ldap: LdapConfig,
     pub log_level: String,
 }
 
@@ -28,6 +44,26 @@ pub struct WebServerConfig {
     pub cert_path: Option<String>,
     pub key_path: Option<String>,
     pub log_format: String,
+    /// Reverse proxies whose `Forwarded` / `X-Forwarded-For` headers are trusted
+    // empty lists are left out by the config crate
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JwtConfig {
+    pub signing_key: String,
+    pub leeway: u64,
+    // empty lists are left out by the config crate
+    #[serde(default)]
+    pub keys: Vec<JwtKeyConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JwtKeyConfig {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    pub private_key: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -48,6 +84,62 @@ pub struct MailConfig {
     pub merchandise_email_send_to: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PasswordHashConfig {
+    pub memory_cost: u32,
+    pub time_cost: u32,
+    pub parallelism: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginThrottleConfig {
+    pub free_attempts: i32,
+    pub base_delay: i64,
+    pub max_delay: i64,
+    pub lockout_attempts: i32,
+    pub lockout_duration: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AvatarConfig {
+    pub max_bytes: usize,
+    pub max_pixels: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcConfig {
+    pub enabled: bool,
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_url: String,
+    pub frontend_url: String,
+    pub scopes: String,
+    pub provision_users: bool,
+    pub groups_claim: String,
+    // empty lists and tables are left out by the config crate
+    #[serde(default)]
+    pub admin_groups: Vec<String>,
+    #[serde(default)]
+    pub role_mapping: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LdapConfig {
+    pub enabled: bool,
+    pub url: String,
+    pub bind_dn: String,
+    pub bind_password: String,
+    pub base_dn: String,
+    pub user_filter: String,
+    pub email_attribute: String,
+    pub name_attribute: String,
+    pub group_attribute: String,
+    // empty lists are left out by the config crate
+    #[serde(default)]
+    pub admin_groups: Vec<String>,
+}
+
 impl Settings {
     pub fn new() -> Result<Self, ConfigError> {
         let mut builder = Config::builder();
@@ -67,4 +159,13 @@ impl Settings {
 
         config.try_deserialize()
     }
+
+    /// Url zorius is reachable at, build from `domain` and `enable_ssl`
+    pub fn base_url(&self) -> String {
+        if self.web.enable_ssl {
+            format!("https://{}", self.domain)
+        } else {
+            format!("http://{}", self.domain)
+        }
+    }
 }
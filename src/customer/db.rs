@@ -1,40 +1,103 @@
-use entity::customer::{ActiveModel, Column, Entity, Model};
-use migration::sea_query::{Expr, IntoCondition};
-use sea_orm::{prelude::*, Condition, DatabaseConnection, Order, QueryOrder, QuerySelect, Set};
+use chrono::Utc;
+use entity::{
+    customer::{ActiveModel, Column, Entity, Model},
+    customer_contact, project, work_report,
+};
+use migration::sea_query::{Expr, IntoCondition, Query};
+use sea_orm::{
+    prelude::*, Condition, DatabaseConnection, Order, QueryOrder, QuerySelect, Select, Set,
+    TransactionError, TransactionTrait,
+};
 use uuid::Uuid;
 
-use super::model::{DbListOptions, NewCustomer, UpdateCustomer};
+use crate::validators::compact_identifier;
+
+use super::model::{
+    Address, DbListOptions, NewCustomer, NewCustomerContact, UpdateCustomer, UpdateCustomerContact,
+};
 
 pub async fn new_customer(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     update: NewCustomer,
 ) -> Result<Option<Model>, sea_orm::error::DbErr> {
-    let customer_id = ActiveModel {
+    let mut customer_id = ActiveModel {
+        organization_id: Set(organization_id),
         identifier: Set(update.identifier),
         name: Set(update.name),
         note: Set(update.note),
+        billing_address: Set(update
+            .billing_address
+            .map(|address| Address::from(address).to_json())),
+        shipping_address: Set(update
+            .shipping_address
+            .map(|address| Address::from(address).to_json())),
+        vat_id: Set(update.vat_id.as_deref().map(compact_identifier)),
+        tax_exempt: Set(update.tax_exempt),
+        iban: Set(update.iban.as_deref().map(compact_identifier)),
+        invoice_email: Set(update.invoice_email),
         ..Default::default()
     };
+    if let Some(payment_terms_days) = update.payment_terms_days {
+        customer_id.payment_terms_days = Set(payment_terms_days);
+    }
+    if let Some(currency) = update.currency {
+        customer_id.currency = Set(currency);
+    }
     let customer_id = Entity::insert(customer_id).exec(db).await?.last_insert_id;
-    customer_by_id(db, customer_id).await
+    customer_by_id(db, organization_id, customer_id).await
 }
 
 pub async fn customer_by_id(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     id: uuid::Uuid,
 ) -> Result<Option<Model>, sea_orm::error::DbErr> {
-    Entity::find_by_id(id).one(db).await
+    Entity::find_by_id(id)
+        .filter(Column::OrganizationId.eq(organization_id))
+        .filter(Column::DeletedAt.is_null())
+        .one(db)
+        .await
+}
+
+pub async fn trashed_customer_by_id(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    id: Uuid,
+) -> Result<Option<Model>, sea_orm::error::DbErr> {
+    Entity::find_by_id(id)
+        .filter(Column::OrganizationId.eq(organization_id))
+        .filter(Column::DeletedAt.is_not_null())
+        .one(db)
+        .await
 }
 
-pub async fn count_customers(db: &DatabaseConnection) -> Result<usize, sea_orm::error::DbErr> {
-    Entity::find().count(db).await
+pub async fn count_customers(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    options: &DbListOptions,
+) -> Result<usize, sea_orm::error::DbErr> {
+    let mut entity = Entity::find()
+        .filter(Column::OrganizationId.eq(organization_id))
+        .filter(Column::DeletedAt.is_null());
+    if let Some(visible_ids) = &options.visible_ids {
+        entity = entity.filter(Column::Id.is_in(visible_ids.clone()));
+    }
+    entity.count(db).await
 }
 
 pub async fn list_customers(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     options: DbListOptions,
 ) -> Result<Vec<Model>, sea_orm::error::DbErr> {
-    let mut entity = Entity::find();
+    let mut entity = Entity::find()
+        .filter(Column::OrganizationId.eq(organization_id))
+        .filter(Column::DeletedAt.is_null());
+
+    if let Some(visible_ids) = options.visible_ids {
+        entity = entity.filter(Column::Id.is_in(visible_ids));
+    }
 
     if let Some(ids) = options.ids {
         let con = ids.into_iter().fold(Condition::all(), |acc, id| {
@@ -52,23 +115,112 @@ pub async fn list_customers(
         .await
 }
 
+/// Moves the customer to the trash, its work reports, projects and contacts are kept
 pub async fn delete_customer(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     id: Uuid,
 ) -> Result<u64, sea_orm::error::DbErr> {
-    let customer: ActiveModel = match Entity::find_by_id(id).one(db).await? {
+    let mut customer: ActiveModel = match customer_by_id(db, organization_id, id).await? {
         Some(customer) => customer.into(),
         None => return Ok(0),
     };
-    Ok(Entity::delete(customer).exec(db).await?.rows_affected)
+    customer.deleted_at = Set(Some(Utc::now()));
+    customer.update(db).await?;
+    Ok(1)
+}
+
+pub async fn restore_customer(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    id: Uuid,
+) -> Result<Option<Model>, sea_orm::error::DbErr> {
+    let mut customer: ActiveModel = match trashed_customer_by_id(db, organization_id, id).await? {
+        Some(customer) => customer.into(),
+        None => return Ok(None),
+    };
+    customer.deleted_at = Set(None);
+    Ok(Some(customer.update(db).await?))
+}
+
+/// Customers in the trash, the most recently deleted first
+pub async fn trashed_customers(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+) -> Result<Vec<Model>, sea_orm::error::DbErr> {
+    Entity::find()
+        .filter(Column::OrganizationId.eq(organization_id))
+        .filter(Column::DeletedAt.is_not_null())
+        .order_by(Column::DeletedAt, Order::Desc)
+        .all(db)
+        .await
+}
+
+/// Customers of all organizations trashed before `deleted_before`.
+/// Customers with work reports on them or their projects are kept,
+/// the reports are needed for invoices.
+pub async fn purgeable_customers(
+    db: &DatabaseConnection,
+    deleted_before: DateTimeUtc,
+) -> Result<Vec<Model>, sea_orm::error::DbErr> {
+    purgeable_customers_query(deleted_before).all(db).await
+}
+
+fn purgeable_customers_query(deleted_before: DateTimeUtc) -> Select<Entity> {
+    Entity::find()
+        .filter(Column::DeletedAt.lt(deleted_before))
+        .filter(
+            Column::Id.not_in_subquery(
+                Query::select()
+                    .column(work_report::Column::CustomerId)
+                    .from(work_report::Entity)
+                    .to_owned(),
+            ),
+        )
+        // the projects are deleted with the customer, reports booked onto them have to stay
+        .filter(
+            Column::Id.not_in_subquery(
+                Query::select()
+                    .column((project::Entity, project::Column::CustomerId))
+                    .from(project::Entity)
+                    .inner_join(
+                        work_report::Entity,
+                        Expr::tbl(work_report::Entity, work_report::Column::ProjectId)
+                            .equals(project::Entity, project::Column::Id),
+                    )
+                    .to_owned(),
+            ),
+        )
+}
+
+/// Permanently deletes the customer with its projects and contacts
+pub async fn purge_customer(
+    db: &DatabaseConnection,
+    customer: Model,
+) -> Result<u64, sea_orm::error::DbErr> {
+    // a customer without its projects must not be left behind if the second delete fails
+    db.transaction::<_, _, DbErr>(|txn| {
+        Box::pin(async move {
+            project::Entity::delete_many()
+                .filter(project::Column::CustomerId.eq(customer.id))
+                .exec(txn)
+                .await?;
+            Ok(customer.delete(txn).await?.rows_affected)
+        })
+    })
+    .await
+    .map_err(|e| match e {
+        TransactionError::Connection(e) | TransactionError::Transaction(e) => e,
+    })
 }
 
 pub async fn update_customer(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     id: Uuid,
     update: UpdateCustomer,
 ) -> Result<Option<Model>, sea_orm::error::DbErr> {
-    let customer = Entity::find_by_id(id).one(db).await?;
+    let customer = customer_by_id(db, organization_id, id).await?;
     if let Some(customer) = customer {
         let mut customer: ActiveModel = customer.into();
         if let Some(name) = update.name {
@@ -80,8 +232,135 @@ pub async fn update_customer(
         if let Some(note) = update.note {
             customer.note = Set(note)
         }
+        if let Some(address) = update.billing_address {
+            customer.billing_address = Set(address.map(|address| Address::from(address).to_json()))
+        }
+        if let Some(address) = update.shipping_address {
+            customer.shipping_address = Set(address.map(|address| Address::from(address).to_json()))
+        }
+        if let Some(vat_id) = update.vat_id {
+            customer.vat_id = Set(vat_id.as_deref().map(compact_identifier))
+        }
+        if let Some(tax_exempt) = update.tax_exempt {
+            customer.tax_exempt = Set(tax_exempt)
+        }
+        if let Some(iban) = update.iban {
+            customer.iban = Set(iban.as_deref().map(compact_identifier))
+        }
+        if let Some(payment_terms_days) = update.payment_terms_days {
+            customer.payment_terms_days = Set(payment_terms_days)
+        }
+        if let Some(currency) = update.currency {
+            customer.currency = Set(currency)
+        }
+        if let Some(invoice_email) = update.invoice_email {
+            customer.invoice_email = Set(invoice_email)
+        }
         customer.update(db).await?;
-        return customer_by_id(db, id).await;
+        return customer_by_id(db, organization_id, id).await;
     }
     Ok(None)
 }
+
+/// Contact of a customer of the organization
+pub async fn contact_by_id(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    id: Uuid,
+) -> Result<Option<customer_contact::Model>, sea_orm::error::DbErr> {
+    customer_contact::Entity::find_by_id(id)
+        .inner_join(Entity)
+        .filter(Column::OrganizationId.eq(organization_id))
+        .filter(Column::DeletedAt.is_null())
+        .one(db)
+        .await
+}
+
+/// Clears the primary flag of all contacts of the customer
+async fn unset_primary_contact(
+    db: &DatabaseConnection,
+    customer_id: Uuid,
+) -> Result<(), sea_orm::error::DbErr> {
+    customer_contact::Entity::update_many()
+        .col_expr(customer_contact::Column::IsPrimary, Expr::value(false))
+        .filter(customer_contact::Column::CustomerId.eq(customer_id))
+        .filter(customer_contact::Column::IsPrimary.eq(true))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn new_contact(
+    db: &DatabaseConnection,
+    new: NewCustomerContact,
+) -> Result<customer_contact::Model, sea_orm::error::DbErr> {
+    if new.is_primary {
+        unset_primary_contact(db, new.customer_id).await?;
+    }
+    customer_contact::ActiveModel {
+        customer_id: Set(new.customer_id),
+        name: Set(new.name),
+        role: Set(new.role),
+        emails: Set(new.emails.into()),
+        phones: Set(new.phones.into()),
+        is_primary: Set(new.is_primary),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
+
+pub async fn update_contact(
+    db: &DatabaseConnection,
+    contact: customer_contact::Model,
+    update: UpdateCustomerContact,
+) -> Result<customer_contact::Model, sea_orm::error::DbErr> {
+    if update.is_primary == Some(true) && !contact.is_primary {
+        unset_primary_contact(db, contact.customer_id).await?;
+    }
+    let mut contact: customer_contact::ActiveModel = contact.into();
+    if let Some(name) = update.name {
+        contact.name = Set(name);
+    }
+    if let Some(role) = update.role {
+        contact.role = Set(role);
+    }
+    if let Some(emails) = update.emails {
+        contact.emails = Set(emails.into());
+    }
+    if let Some(phones) = update.phones {
+        contact.phones = Set(phones.into());
+    }
+    if let Some(is_primary) = update.is_primary {
+        contact.is_primary = Set(is_primary);
+    }
+    contact.update(db).await
+}
+
+pub async fn delete_contact(
+    db: &DatabaseConnection,
+    contact: customer_contact::Model,
+) -> Result<u64, sea_orm::error::DbErr> {
+    Ok(contact.delete(db).await?.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{DbBackend, QueryTrait};
+
+    use super::*;
+
+    #[test]
+    fn customers_with_work_reports_are_not_purged() {
+        let sql = purgeable_customers_query(Utc::now())
+            .build(DbBackend::Postgres)
+            .to_string();
+        assert!(sql.contains(r#""customers"."deleted_at" <"#));
+        assert!(
+            sql.contains(r#""customers"."id" NOT IN (SELECT "customer_id" FROM "work_reports")"#)
+        );
+        assert!(sql.contains(
+            r#""customers"."id" NOT IN (SELECT "projects"."customer_id" FROM "projects" INNER JOIN "work_reports" ON "work_reports"."project_id" = "projects"."id")"#
+        ));
+    }
+}
@@ -2,22 +2,35 @@ use async_graphql::{
     connection::{query, Connection, Edge, EmptyFields},
     Context, Object, Subscription,
 };
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, Utc};
 use futures::{stream, StreamExt};
 use futures_util::Stream;
+use log::{error, info};
+use sea_orm::DatabaseConnection;
 use uuid::Uuid;
 
 use crate::{
     api::{database, MutationType},
     claim::Claim,
+    config::CONFIG,
     errors::Result,
-    guards::TokenGuard,
+    guards::PermissionGuard,
+    policy::ProjectScope,
+    role::model::Permission,
     simple_broker::SimpleBroker,
 };
 
 use self::{
-    db::{count_customers, delete_customer, list_customers, new_customer, update_customer},
+    db::{
+        contact_by_id, count_customers, customer_by_id, delete_contact, delete_customer,
+        list_customers, new_contact, new_customer, purge_customer, purgeable_customers,
+        restore_customer, trashed_customers, update_contact, update_customer,
+    },
     model::{
-        Customer, CustomerChanged, DbListOptions, ListCustomerOptions, NewCustomer, UpdateCustomer,
+        Customer, CustomerChanged, CustomerContact, DbListOptions, ListCustomerOptions,
+        NewCustomer, NewCustomerContact, UpdateCustomer, UpdateCustomerContact,
     },
 };
 
@@ -29,20 +42,22 @@ pub struct CustomerQuery;
 
 #[Object]
 impl CustomerQuery {
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::CustomerRead)")]
     async fn customers(
         &self,
         ctx: &Context<'_>,
         options: Option<ListCustomerOptions>,
     ) -> async_graphql::Result<Connection<usize, Customer, EmptyFields, EmptyFields>> {
-        let _ = Claim::from_ctx(ctx)?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
         let db = database(ctx)?;
-        let count = count_customers(db).await? as usize;
+        let scope = ProjectScope::from_ctx(ctx).await?;
         let options = options.unwrap_or_default();
         let mut db_options = DbListOptions {
             ids: options.ids,
+            visible_ids: scope.customer_ids(),
             ..Default::default()
         };
+        let count = count_customers(db, organization_id, &db_options).await? as usize;
 
         query(
             options.after,
@@ -61,7 +76,7 @@ impl CustomerQuery {
                 db_options.start = start as u64;
                 db_options.limit = end as u64;
 
-                let customers = match list_customers(db, db_options).await {
+                let customers = match list_customers(db, organization_id, db_options).await {
                     Ok(r) => r,
                     Err(_e) => return Err(async_graphql::Error::new("")),
                 };
@@ -79,6 +94,15 @@ impl CustomerQuery {
         )
         .await
     }
+
+    /// Deleted customers of the organization, they are purged after `trashed_customer_lifetime`
+    #[graphql(guard = "PermissionGuard::new(Permission::CustomerWrite)")]
+    async fn trashed_customers(&self, ctx: &Context<'_>) -> Result<Vec<Customer>> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        let customers = trashed_customers(db, organization_id).await?;
+        Ok(customers.into_iter().map(Customer::from).collect())
+    }
 }
 
 #[derive(Default)]
@@ -86,12 +110,12 @@ pub struct CustomerMutation;
 
 #[Object]
 impl CustomerMutation {
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::CustomerWrite)")]
     async fn new_customer(&self, ctx: &Context<'_>, new: NewCustomer) -> Result<Option<Customer>> {
-        let _ = Claim::from_ctx(ctx)?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
         let db = database(ctx)?;
 
-        if let Some(customer) = new_customer(db, new).await? {
+        if let Some(customer) = new_customer(db, organization_id, new).await? {
             SimpleBroker::publish(CustomerChanged {
                 mutation_type: MutationType::Created,
                 id: customer.id,
@@ -101,17 +125,20 @@ impl CustomerMutation {
         Ok(None)
     }
 
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::CustomerWrite)")]
     async fn update_customer(
         &self,
         ctx: &Context<'_>,
         id: Uuid,
         update: UpdateCustomer,
     ) -> Result<Option<Customer>> {
-        let _ = Claim::from_ctx(ctx)?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
         let db = database(ctx)?;
+        if !ProjectScope::from_ctx(ctx).await?.has_customer(id) {
+            return Ok(None);
+        }
 
-        if let Some(customer) = update_customer(db, id, update).await? {
+        if let Some(customer) = update_customer(db, organization_id, id, update).await? {
             SimpleBroker::publish(CustomerChanged {
                 mutation_type: MutationType::Updated,
                 id: customer.id,
@@ -121,11 +148,15 @@ impl CustomerMutation {
         Ok(None)
     }
 
-    #[graphql(guard = "TokenGuard")]
+    /// Moves the customer to the trash, it can be restored until it is purged
+    #[graphql(guard = "PermissionGuard::new(Permission::CustomerWrite)")]
     async fn delete_customer(&self, ctx: &Context<'_>, id: Uuid) -> Result<bool> {
-        let _ = Claim::from_ctx(ctx)?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
         let db = database(ctx)?;
-        let res = delete_customer(db, id).await? >= 1;
+        if !ProjectScope::from_ctx(ctx).await?.has_customer(id) {
+            return Ok(false);
+        }
+        let res = delete_customer(db, organization_id, id).await? >= 1;
         SimpleBroker::publish(CustomerChanged {
             mutation_type: MutationType::Deleted,
             id,
@@ -133,6 +164,101 @@ impl CustomerMutation {
 
         Ok(res)
     }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::CustomerWrite)")]
+    async fn restore_customer(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<Customer>> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        if !ProjectScope::from_ctx(ctx).await?.has_customer(id) {
+            return Ok(None);
+        }
+
+        if let Some(customer) = restore_customer(db, organization_id, id).await? {
+            SimpleBroker::publish(CustomerChanged {
+                mutation_type: MutationType::Updated,
+                id: customer.id,
+            });
+            return Ok(Some(customer.into()));
+        }
+        Ok(None)
+    }
+
+    /// Adds a contact person to the customer
+    #[graphql(guard = "PermissionGuard::new(Permission::CustomerWrite)")]
+    async fn new_customer_contact(
+        &self,
+        ctx: &Context<'_>,
+        new: NewCustomerContact,
+    ) -> Result<Option<CustomerContact>> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        if !ProjectScope::from_ctx(ctx)
+            .await?
+            .has_customer(new.customer_id)
+            || customer_by_id(db, organization_id, new.customer_id)
+                .await?
+                .is_none()
+        {
+            return Ok(None);
+        }
+
+        let contact = new_contact(db, new).await?;
+        SimpleBroker::publish(CustomerChanged {
+            mutation_type: MutationType::Updated,
+            id: contact.customer_id,
+        });
+        Ok(Some(contact.into()))
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::CustomerWrite)")]
+    async fn update_customer_contact(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        update: UpdateCustomerContact,
+    ) -> Result<Option<CustomerContact>> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        let contact = match contact_by_id(db, organization_id, id).await? {
+            Some(contact) => contact,
+            None => return Ok(None),
+        };
+        if !ProjectScope::from_ctx(ctx)
+            .await?
+            .has_customer(contact.customer_id)
+        {
+            return Ok(None);
+        }
+
+        let contact = update_contact(db, contact, update).await?;
+        SimpleBroker::publish(CustomerChanged {
+            mutation_type: MutationType::Updated,
+            id: contact.customer_id,
+        });
+        Ok(Some(contact.into()))
+    }
+
+    /// Work reports ordered by the contact keep their customer but lose the contact
+    #[graphql(guard = "PermissionGuard::new(Permission::CustomerWrite)")]
+    async fn delete_customer_contact(&self, ctx: &Context<'_>, id: Uuid) -> Result<bool> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        let contact = match contact_by_id(db, organization_id, id).await? {
+            Some(contact) => contact,
+            None => return Ok(false),
+        };
+        let customer_id = contact.customer_id;
+        if !ProjectScope::from_ctx(ctx).await?.has_customer(customer_id) {
+            return Ok(false);
+        }
+
+        let res = delete_contact(db, contact).await? >= 1;
+        SimpleBroker::publish(CustomerChanged {
+            mutation_type: MutationType::Updated,
+            id: customer_id,
+        });
+        Ok(res)
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -140,7 +266,7 @@ pub struct CustomerSubscription;
 
 #[Subscription]
 impl CustomerSubscription {
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::CustomerRead)")]
     async fn customers(
         &self,
         mutation_type: Option<MutationType>,
@@ -155,3 +281,26 @@ impl CustomerSubscription {
         })
     }
 }
+
+/// Purges customers that are in the trash for longer than `trashed_customer_lifetime` once an hour
+pub async fn purge_trashed_customers(db: DatabaseConnection) {
+    let mut interval = tokio::time::interval(StdDuration::from_secs(3600));
+    loop {
+        interval.tick().await;
+        let deleted_before = Utc::now() - Duration::seconds(CONFIG.trashed_customer_lifetime);
+        let customers = match purgeable_customers(&db, deleted_before).await {
+            Ok(customers) => customers,
+            Err(e) => {
+                error!("failed to list trashed customers: {e}");
+                continue;
+            }
+        };
+        for customer in customers {
+            let id = customer.id;
+            match purge_customer(&db, customer).await {
+                Ok(_) => info!("purged trashed customer {id}"),
+                Err(e) => error!("failed to purge trashed customer {id}: {e}"),
+            }
+        }
+    }
+}
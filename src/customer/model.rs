@@ -1,14 +1,19 @@
 use async_graphql::{ComplexObject, Context, InputObject, Object, SimpleObject};
 
-use entity::{customer::Model, project};
-use sea_orm::{prelude::DateTimeUtc, ColumnTrait, EntityTrait, QueryFilter};
-use serde::Serialize;
+use entity::{customer::Model, customer_contact, project};
+use sea_orm::{
+    prelude::{DateTimeUtc, Json},
+    ColumnTrait, EntityTrait, Order, QueryFilter, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
     api::{database, MutationType},
     errors::Result,
+    policy::ProjectScope,
     project::model::Project,
+    validators::{Country, Currency, Iban, Phone, VatId},
 };
 
 #[derive(Serialize, Debug, Clone, SimpleObject)]
@@ -18,6 +23,18 @@ pub struct Customer {
     pub name: String,
     pub identifier: String,
     pub note: Option<String>,
+    pub billing_address: Option<Address>,
+    /// `None` if goods are shipped to the billing address
+    pub shipping_address: Option<Address>,
+    pub vat_id: Option<String>,
+    pub tax_exempt: bool,
+    pub iban: Option<String>,
+    /// Days until an invoice is due
+    pub payment_terms_days: i32,
+    /// ISO 4217 currency code invoices are issued in
+    pub currency: String,
+    /// Invoices are sent here instead of the primary contact
+    pub invoice_email: Option<String>,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
     pub deleted_at: Option<DateTimeUtc>,
@@ -25,14 +42,32 @@ pub struct Customer {
 
 #[ComplexObject]
 impl Customer {
+    /// Projects the current user is a member of
     async fn projects(&self, ctx: &Context<'_>) -> Result<Vec<Project>> {
         let db = database(ctx)?;
+        let scope = ProjectScope::from_ctx(ctx).await?;
         let models = project::Entity::find()
             .filter(project::Column::CustomerId.eq(self.id))
             .all(db)
             .await?;
 
-        Ok(models.into_iter().map(Project::from).collect())
+        Ok(models
+            .into_iter()
+            .filter(|project| scope.has_project(project.id))
+            .map(Project::from)
+            .collect())
+    }
+
+    /// Contact people of the customer, the primary contact first
+    async fn contacts(&self, ctx: &Context<'_>) -> Result<Vec<CustomerContact>> {
+        let db = database(ctx)?;
+        let models = customer_contact::Entity::find()
+            .filter(customer_contact::Column::CustomerId.eq(self.id))
+            .order_by(customer_contact::Column::IsPrimary, Order::Desc)
+            .order_by(customer_contact::Column::Name, Order::Asc)
+            .all(db)
+            .await?;
+        Ok(models.into_iter().map(CustomerContact::from).collect())
     }
 }
 
@@ -43,6 +78,14 @@ impl From<Model> for Customer {
             name: model.name,
             identifier: model.identifier,
             note: model.note,
+            billing_address: model.billing_address.and_then(Address::from_json),
+            shipping_address: model.shipping_address.and_then(Address::from_json),
+            vat_id: model.vat_id,
+            tax_exempt: model.tax_exempt,
+            iban: model.iban,
+            payment_terms_days: model.payment_terms_days,
+            currency: model.currency,
+            invoice_email: model.invoice_email,
             created_at: model.created_at,
             updated_at: model.updated_at,
             deleted_at: model.deleted_at,
@@ -50,6 +93,52 @@ impl From<Model> for Customer {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, SimpleObject)]
+pub struct Address {
+    /// Recipient if it differs from the customer name
+    pub name: Option<String>,
+    pub street: String,
+    pub postal_code: String,
+    pub city: String,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+}
+
+impl Address {
+    fn from_json(json: Json) -> Option<Self> {
+        serde_json::from_value(json).ok()
+    }
+
+    pub fn to_json(&self) -> Json {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Debug, Clone, InputObject)]
+pub struct AddressInput {
+    pub name: Option<String>,
+    #[graphql(validator(min_length = 1))]
+    pub street: String,
+    #[graphql(validator(min_length = 1))]
+    pub postal_code: String,
+    #[graphql(validator(min_length = 1))]
+    pub city: String,
+    #[graphql(validator(custom = "Country"))]
+    pub country: String,
+}
+
+impl From<AddressInput> for Address {
+    fn from(input: AddressInput) -> Self {
+        Self {
+            name: input.name,
+            street: input.street,
+            postal_code: input.postal_code,
+            city: input.city,
+            country: input.country,
+        }
+    }
+}
+
 #[derive(Serialize, Debug, InputObject, Default)]
 pub struct ListCustomerOptions {
     pub ids: Option<Vec<Uuid>>,
@@ -62,6 +151,8 @@ pub struct ListCustomerOptions {
 #[derive(Debug, Default)]
 pub struct DbListOptions {
     pub ids: Option<Vec<Uuid>>,
+    /// Only these customers are listed, `None` lists all of the organization
+    pub visible_ids: Option<Vec<Uuid>>,
     pub start: u64,
     pub limit: u64,
 }
@@ -72,6 +163,22 @@ pub struct NewCustomer {
     pub identifier: String,
     pub note: Option<String>,
     pub project_ids: Option<Vec<Uuid>>,
+    pub billing_address: Option<AddressInput>,
+    pub shipping_address: Option<AddressInput>,
+    #[graphql(validator(custom = "VatId"))]
+    pub vat_id: Option<String>,
+    #[graphql(default)]
+    pub tax_exempt: bool,
+    #[graphql(validator(custom = "Iban"))]
+    pub iban: Option<String>,
+    /// 30 days if not set
+    #[graphql(validator(minimum = 0, maximum = 365))]
+    pub payment_terms_days: Option<i32>,
+    /// `EUR` if not set
+    #[graphql(validator(custom = "Currency"))]
+    pub currency: Option<String>,
+    #[graphql(validator(email))]
+    pub invoice_email: Option<String>,
 }
 
 #[derive(Serialize, InputObject)]
@@ -79,6 +186,78 @@ pub struct UpdateCustomer {
     pub name: Option<String>,
     pub identifier: Option<String>,
     pub note: Option<Option<String>>,
+    pub billing_address: Option<Option<AddressInput>>,
+    pub shipping_address: Option<Option<AddressInput>>,
+    #[graphql(validator(custom = "VatId"))]
+    pub vat_id: Option<Option<String>>,
+    pub tax_exempt: Option<bool>,
+    #[graphql(validator(custom = "Iban"))]
+    pub iban: Option<Option<String>>,
+    #[graphql(validator(minimum = 0, maximum = 365))]
+    pub payment_terms_days: Option<i32>,
+    #[graphql(validator(custom = "Currency"))]
+    pub currency: Option<String>,
+    #[graphql(validator(email))]
+    pub invoice_email: Option<Option<String>>,
+}
+
+#[derive(Serialize, Debug, Clone, SimpleObject)]
+pub struct CustomerContact {
+    pub id: Uuid,
+    pub customer_id: Uuid,
+    pub name: String,
+    /// Position at the customer, like `purchasing` or `site manager`
+    pub role: Option<String>,
+    pub emails: Vec<String>,
+    pub phones: Vec<String>,
+    /// Every customer has at most one primary contact
+    pub is_primary: bool,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+impl From<customer_contact::Model> for CustomerContact {
+    fn from(model: customer_contact::Model) -> Self {
+        Self {
+            id: model.id,
+            customer_id: model.customer_id,
+            name: model.name,
+            role: model.role,
+            emails: serde_json::from_value(model.emails).unwrap_or_default(),
+            phones: serde_json::from_value(model.phones).unwrap_or_default(),
+            is_primary: model.is_primary,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, InputObject)]
+pub struct NewCustomerContact {
+    pub customer_id: Uuid,
+    #[graphql(validator(min_length = 1))]
+    pub name: String,
+    pub role: Option<String>,
+    #[graphql(default, validator(list, email))]
+    pub emails: Vec<String>,
+    #[graphql(default, validator(list, custom = "Phone"))]
+    pub phones: Vec<String>,
+    /// Makes this the primary contact, replacing the previous one
+    #[graphql(default)]
+    pub is_primary: bool,
+}
+
+#[derive(Serialize, Debug, InputObject)]
+pub struct UpdateCustomerContact {
+    #[graphql(validator(min_length = 1))]
+    pub name: Option<String>,
+    pub role: Option<Option<String>>,
+    #[graphql(validator(list, email))]
+    pub emails: Option<Vec<String>>,
+    #[graphql(validator(list, custom = "Phone"))]
+    pub phones: Option<Vec<String>>,
+    /// Makes this the primary contact, replacing the previous one
+    pub is_primary: Option<bool>,
 }
 
 #[derive(Clone)]
@@ -1,11 +1,14 @@
 use std::io::Error as StdIoError;
 
 use actix_web::error::Error as ActixError;
+use askama::Error as TemplateError;
 use async_graphql::{Error as GqlError, ErrorExtensions, FieldError};
 use image::error::ImageError;
 use jsonwebtoken::errors::Error as JwtError;
+use ldap3::LdapError;
 use log::error;
-use sea_orm::error::DbErr;
+use reqwest::Error as ReqwestError;
+use sea_orm::{error::DbErr, prelude::DateTimeUtc, TransactionError};
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -24,6 +27,12 @@ pub enum Error {
     Image(#[from] ImageError),
     #[error("graphql error")]
     GraphQl(GqlError),
+    #[error("template error")]
+    Template(#[from] TemplateError),
+    #[error("request to another server failed")]
+    Http(#[from] ReqwestError),
+    #[error("ldap error")]
+    Ldap(#[from] LdapError),
 
     #[error("missing database in context")]
     MissingDatabase,
@@ -33,6 +42,14 @@ pub enum Error {
     EmailAlreadyRegistred,
     #[error("incorrect password")]
     IncorrectPassword,
+    #[error("failed to hash password: {0}")]
+    PasswordHash(String),
+    #[error("account is temporarily locked until {0}")]
+    AccountLocked(DateTimeUtc),
+    #[error("account is deactivated")]
+    AccountDeactivated,
+    #[error("email address is not verified")]
+    EmailNotVerified,
     #[error("not found")]
     NotFound,
     #[error("forbidden")]
@@ -43,6 +60,42 @@ pub enum Error {
     MalformedToken,
     #[error("expired token")]
     ExpiredToken,
+    #[error("session was revoked")]
+    RevokedSession,
+    #[error("invalid two factor code")]
+    InvalidTotpCode,
+    #[error("two factor authentication is not set up")]
+    TotpNotSetUp,
+    #[error("two factor authentication is already enabled")]
+    TotpAlreadyEnabled,
+    #[error("login with password is disabled")]
+    PasswordLoginDisabled,
+    #[error("openid connect login failed: {0}")]
+    Oidc(String),
+    #[error("invalid or revoked api token")]
+    InvalidApiToken,
+    #[error("the api token is missing the scope for this request")]
+    MissingScope,
+    #[error("invalid jwt key configuration: {0}")]
+    JwtKey(String),
+    #[error("only deleted users can be purged")]
+    UserNotDeleted,
+    #[error("the user still owns work reports")]
+    UserHasWorkReports,
+    #[error("a role with this name already exists")]
+    RoleNameTaken,
+    #[error("a team with this name already exists")]
+    TeamNameTaken,
+    #[error("an organization with this name already exists")]
+    OrganizationNameTaken,
+    #[error("the token is not scoped to an organization")]
+    NoOrganization,
+    #[error("an organization needs at least one admin")]
+    LastOrganizationAdmin,
+    #[error("working hours must be between 0 and 24 per day and add up to the weekly hours")]
+    InvalidWorkingHours,
+    #[error("the image is too large")]
+    AvatarTooLarge,
     #[error("wrong media type")]
     WrongMediaType,
     #[error("a time record is still running. end the other on before staring a new one")]
@@ -66,16 +119,41 @@ impl ErrorExtensions for Error {
             Error::SeaOrm(_) => e.set("code", "DATABASE_ERROR"),
             Error::Image(_) => e.set("code", "IMAGE_ERROR"),
             Error::GraphQl(_) => e.set("code", "GRAPHQL_ERROR"),
+            Error::Template(_) => e.set("code", "TEMPLATE_ERROR"),
+            Error::Http(_) => e.set("code", "HTTP_ERROR"),
+            Error::Ldap(_) => e.set("code", "LDAP_ERROR"),
 
             Error::IncorrectPassword => e.set("code", "INCORRECT_PASSWORD"),
+            Error::AccountDeactivated => e.set("code", "ACCOUNT_DEACTIVATED"),
+            Error::EmailNotVerified => e.set("code", "EMAIL_NOT_VERIFIED"),
             Error::NotFound => e.set("code", "NOT_FOUND"),
             Error::Forbidden => e.set("code", "FORBIDDEN"),
+            Error::JwtKey(_) => e.set("code", "JWT_KEY_ERROR"),
+            Error::PasswordHash(_) => e.set("code", "PASSWORD_HASH_ERROR"),
             Error::MalformedToken => e.set("code", "MALFORMED_TOKEN"),
             Error::ExpiredToken => e.set("code", "EXPIRED_TOKEN"),
+            Error::RevokedSession => e.set("code", "REVOKED_SESSION"),
             Error::MissingToken => e.set("code", "MISSING_TOKEN"),
             Error::EmailAlreadyRegistred => e.set("code", "EMAIL_ALREADY_REGISTERED"),
             Error::RegistrationNotEnabled => e.set("code", "REGISTRATION_NOT_ENABLED"),
             Error::MissingDatabase => e.set("code", "MISSING_DATABASE"),
+            Error::InvalidTotpCode => e.set("code", "INVALID_TOTP_CODE"),
+            Error::TotpNotSetUp => e.set("code", "TOTP_NOT_SET_UP"),
+            Error::TotpAlreadyEnabled => e.set("code", "TOTP_ALREADY_ENABLED"),
+            Error::UserNotDeleted => e.set("code", "USER_NOT_DELETED"),
+            Error::UserHasWorkReports => e.set("code", "USER_HAS_WORK_REPORTS"),
+            Error::RoleNameTaken => e.set("code", "ROLE_NAME_TAKEN"),
+            Error::TeamNameTaken => e.set("code", "TEAM_NAME_TAKEN"),
+            Error::OrganizationNameTaken => e.set("code", "ORGANIZATION_NAME_TAKEN"),
+            Error::NoOrganization => e.set("code", "NO_ORGANIZATION"),
+            Error::LastOrganizationAdmin => e.set("code", "LAST_ORGANIZATION_ADMIN"),
+            Error::AccountLocked(_) => e.set("code", "ACCOUNT_LOCKED"),
+            Error::InvalidApiToken => e.set("code", "INVALID_API_TOKEN"),
+            Error::PasswordLoginDisabled => e.set("code", "PASSWORD_LOGIN_DISABLED"),
+            Error::Oidc(_) => e.set("code", "OIDC_ERROR"),
+            Error::MissingScope => e.set("code", "MISSING_SCOPE"),
+            Error::InvalidWorkingHours => e.set("code", "INVALID_WORKING_HOURS"),
+            Error::AvatarTooLarge => e.set("code", "AVATAR_TOO_LARGE"),
             Error::WrongMediaType => e.set("code", "WRONG_MEDIA_TYPE"),
             Error::TimeRecordStillRunning => e.set("code", "TIME_RECORD_STILL_RUNNING"),
             Error::NoTimeRecordRunning => e.set("code", "NO_TIME_RECORD_RUNNING"),
@@ -90,3 +168,13 @@ impl From<GqlError> for Error {
         Error::GraphQl(err)
     }
 }
+
+impl From<TransactionError<DbErr>> for Error {
+    fn from(err: TransactionError<DbErr>) -> Self {
+        match err {
+            TransactionError::Connection(err) | TransactionError::Transaction(err) => {
+                Error::SeaOrm(err)
+            }
+        }
+    }
+}
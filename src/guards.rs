@@ -1,19 +1,68 @@
-use crate::{api::database, claim::Claim, errors::Error};
+use crate::{
+    api::database,
+    api_token::model::{ApiTokenAuth, ApiTokenScope},
+    claim::Claim,
+    errors::Error,
+    organization::db::membership,
+    role::{db::has_permission, model::Permission},
+    session::db::active_session_by_id,
+    user::model::ensure_active,
+};
 use async_graphql::{Context, ErrorExtensions, Guard, Result};
 use entity::user;
 use sea_orm::EntityTrait;
 
+/// Loads the user of the token, fails if the session was revoked.
+/// Personal API tokens are only accepted if they have one of the `scopes`.
+async fn authenticated_user(
+    ctx: &Context<'_>,
+    scopes: &[ApiTokenScope],
+) -> Result<Option<user::Model>> {
+    if let Some(auth) = ctx.data_opt::<ApiTokenAuth>() {
+        if !scopes.iter().any(|scope| auth.has_scope(*scope)) {
+            return Err(Error::MissingScope.extend());
+        }
+        let db = database(ctx)?;
+        if let Some(organization_id) = auth.organization_id {
+            // API tokens live long, so leaving the organization has to end them right away
+            if membership(db, organization_id, auth.user_id)
+                .await?
+                .is_none()
+            {
+                return Err(Error::InvalidApiToken.extend());
+            }
+        }
+        let user = user::Entity::find_by_id(auth.user_id).one(db).await?;
+        return active(user);
+    }
+
+    let claim = Claim::from_ctx(ctx)?;
+    let user_id = claim.user_id()?;
+    let db = database(ctx)?;
+    if active_session_by_id(db, claim.session_id()?)
+        .await?
+        .is_none()
+    {
+        return Err(Error::RevokedSession.extend());
+    }
+    let user = user::Entity::find_by_id(user_id).one(db).await?;
+    active(user)
+}
+
+/// Refuses deactivated users right away, not only after their token expired
+fn active(user: Option<user::Model>) -> Result<Option<user::Model>> {
+    if let Some(user) = &user {
+        ensure_active(user).map_err(|e| e.extend())?;
+    }
+    Ok(user)
+}
+
 pub struct AdminGuard;
 
 #[async_trait::async_trait]
 impl Guard for AdminGuard {
     async fn check(&self, ctx: &Context<'_>) -> Result<()> {
-        let claim = Claim::from_ctx(ctx)?;
-        let user_id = claim.user_id()?;
-        let db = database(ctx)?;
-        let model = user::Entity::find_by_id(user_id).one(db).await?;
-
-        if let Some(user) = model {
+        if let Some(user) = authenticated_user(ctx, &[]).await? {
             if user.is_admin {
                 return Ok(());
             }
@@ -22,20 +71,59 @@ impl Guard for AdminGuard {
     }
 }
 
+/// Requires a logged in user. Personal API tokens are rejected,
+/// resolvers that should be usable with them declare a permission instead.
 pub struct TokenGuard;
 
 #[async_trait::async_trait]
 impl Guard for TokenGuard {
     async fn check(&self, ctx: &Context<'_>) -> Result<()> {
-        let claim = Claim::from_ctx(ctx)?;
-        let user_id = claim.user_id()?;
-        let db = database(ctx)?;
-        let model = user::Entity::find_by_id(user_id).one(db).await?;
-
-        if model.is_none() {
+        if authenticated_user(ctx, &[]).await?.is_none() {
             Err(Error::ExpiredToken.extend())
         } else {
             Ok(())
         }
     }
 }
+
+/// Requires a permission granted through one of the roles of the user.
+/// Admins need it too, they usually get it through the `admin` role.
+///
+/// Personal API tokens additionally need the scope matching the permission.
+pub struct PermissionGuard {
+    permission: Permission,
+    scopes: Vec<ApiTokenScope>,
+}
+
+impl PermissionGuard {
+    pub fn new(permission: Permission) -> Self {
+        Self {
+            permission,
+            scopes: ApiTokenScope::for_permission(permission)
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Accepts API tokens with `scope` as well, the resolver has to check what they may do.
+    pub fn or_scope(mut self, scope: ApiTokenScope) -> Self {
+        self.scopes.push(scope);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Guard for PermissionGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let user = match authenticated_user(ctx, &self.scopes).await? {
+            Some(user) => user,
+            None => return Err(Error::ExpiredToken.extend()),
+        };
+        let db = database(ctx)?;
+        if has_permission(db, &user, self.permission).await? {
+            Ok(())
+        } else {
+            Err(Error::Forbidden.extend())
+        }
+    }
+}
@@ -10,10 +10,12 @@ mod model;
 
 use crate::{
     api::{calc_list_params, claim::Claim, database},
+    guards::PermissionGuard,
     models::intern_merchandise::{
         count_intern_merch, delete_intern_merch, incoming_intern_merch, intern_merch_by_id,
         list_intern_merch, new_intern_merch,
     },
+    role::model::Permission,
     view::intern_merchandise::{
         IncomingInternMerchandise, InternMerchandise, NewInternMerchandise,
     },
@@ -90,10 +92,7 @@ pub struct InternMerchandiseMutation;
 
 #[Object]
 impl InternMerchandiseMutation {
-    // #[graphql(guard(race(
-    //     RoleGuard(role = "Role::Admin"),
-    //     RoleGuard(role = "Role::MerchandiseModerator")
-    // )))]
+    #[graphql(guard = "PermissionGuard::new(Permission::MerchandiseApprove)")]
     async fn new_intern_merch(
         &self,
         ctx: &Context<'_>,
@@ -108,10 +107,7 @@ impl InternMerchandiseMutation {
         Ok(None)
     }
 
-    // #[graphql(guard(race(
-    //     RoleGuard(role = "Role::Admin"),
-    //     RoleGuard(role = "Role::MerchandiseModerator")
-    // )))]
+    #[graphql(guard = "PermissionGuard::new(Permission::MerchandiseApprove)")]
     async fn incoming_intern_merchandise(
         &self,
         ctx: &Context<'_>,
@@ -126,10 +122,7 @@ impl InternMerchandiseMutation {
         Ok(None)
     }
 
-    // #[graphql(guard(race(
-    //     RoleGuard(role = "Role::Admin"),
-    //     RoleGuard(role = "Role::MerchandiseModerator")
-    // )))]
+    #[graphql(guard = "PermissionGuard::new(Permission::MerchandiseApprove)")]
     async fn delete_intern_merch(&self, ctx: &Context<'_>, id: Uuid) -> Result<bool> {
         let _ = Claim::from_ctx(ctx)?;
         let db = &database(ctx)?.db();
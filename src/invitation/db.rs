@@ -0,0 +1,165 @@
+use chrono::Utc;
+use entity::{
+    invitation::{ActiveModel, Column, Entity, Model},
+    invitation_role, role,
+};
+use migration::sea_query::Expr;
+use sea_orm::{prelude::*, DatabaseConnection, DbErr, Order, QueryOrder, Set};
+use uuid::Uuid;
+
+pub async fn new_invitation(
+    db: &DatabaseConnection,
+    email: String,
+    token_hash: String,
+    invited_by: Uuid,
+    organization_id: Uuid,
+    expires_at: DateTimeUtc,
+    role_ids: &[Uuid],
+) -> Result<Model, DbErr> {
+    let invitation = ActiveModel {
+        email: Set(email),
+        token_hash: Set(token_hash),
+        invited_by: Set(Some(invited_by)),
+        organization_id: Set(Some(organization_id)),
+        expires_at: Set(expires_at),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    for role_id in role_ids {
+        invitation_role::ActiveModel {
+            invitation_id: Set(invitation.id),
+            role_id: Set(*role_id),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+    Ok(invitation)
+}
+
+/// Returns the invitation if it is neither accepted nor revoked, it may be expired
+pub async fn pending_invitation_by_id(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    id: Uuid,
+) -> Result<Option<Model>, DbErr> {
+    Entity::find_by_id(id)
+        .filter(Column::OrganizationId.eq(organization_id))
+        .filter(Column::AcceptedAt.is_null())
+        .filter(Column::RevokedAt.is_null())
+        .one(db)
+        .await
+}
+
+/// Lists the invitations that are neither accepted nor revoked, expired ones are included
+pub async fn list_pending_invitations(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+) -> Result<Vec<Model>, DbErr> {
+    Entity::find()
+        .filter(Column::OrganizationId.eq(organization_id))
+        .filter(Column::AcceptedAt.is_null())
+        .filter(Column::RevokedAt.is_null())
+        .order_by(Column::CreatedAt, Order::Desc)
+        .all(db)
+        .await
+}
+
+/// Returns the invitation with the given hash if it is pending and not expired.
+pub async fn valid_invitation_by_hash(
+    db: &DatabaseConnection,
+    token_hash: &str,
+) -> Result<Option<Model>, DbErr> {
+    Entity::find()
+        .filter(Column::TokenHash.eq(token_hash))
+        .filter(Column::AcceptedAt.is_null())
+        .filter(Column::RevokedAt.is_null())
+        .filter(Column::ExpiresAt.gt(Utc::now()))
+        .one(db)
+        .await
+}
+
+/// Replaces the token of a pending invitation, the old link stops working
+pub async fn renew_invitation(
+    db: &DatabaseConnection,
+    invitation: Model,
+    token_hash: String,
+    expires_at: DateTimeUtc,
+) -> Result<Model, DbErr> {
+    let mut invitation: ActiveModel = invitation.into();
+    invitation.token_hash = Set(token_hash);
+    invitation.expires_at = Set(expires_at);
+    invitation.update(db).await
+}
+
+/// Returns `false` if there was no pending invitation with the id
+pub async fn revoke_invitation(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    id: Uuid,
+) -> Result<bool, DbErr> {
+    let res = Entity::update_many()
+        .col_expr(Column::RevokedAt, Expr::value(Utc::now()))
+        .filter(Column::Id.eq(id))
+        .filter(Column::OrganizationId.eq(organization_id))
+        .filter(Column::AcceptedAt.is_null())
+        .filter(Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected > 0)
+}
+
+/// Revokes the pending invitations of the organization for the email, ignoring its case,
+/// and returns how many were revoked.
+pub async fn revoke_invitations_for_email(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    email: &str,
+) -> Result<u64, DbErr> {
+    let res = Entity::update_many()
+        .col_expr(Column::RevokedAt, Expr::value(Utc::now()))
+        .filter(Column::OrganizationId.eq(organization_id))
+        .filter(Expr::cust_with_values(
+            "lower(\"invitations\".\"email\") = ?",
+            vec![email.to_lowercase()],
+        ))
+        .filter(Column::AcceptedAt.is_null())
+        .filter(Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected)
+}
+
+/// Marks the invitation as accepted, returns `false` if it was accepted or revoked in the meantime
+pub async fn accept_invitation(db: &DatabaseConnection, id: Uuid) -> Result<bool, DbErr> {
+    let res = Entity::update_many()
+        .col_expr(Column::AcceptedAt, Expr::value(Utc::now()))
+        .filter(Column::Id.eq(id))
+        .filter(Column::AcceptedAt.is_null())
+        .filter(Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected > 0)
+}
+
+pub async fn invitation_roles(
+    db: &DatabaseConnection,
+    invitation_id: Uuid,
+) -> Result<Vec<role::Model>, DbErr> {
+    let role_ids = invitation_role::Entity::find()
+        .filter(invitation_role::Column::InvitationId.eq(invitation_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|invitation_role| invitation_role.role_id)
+        .collect::<Vec<_>>();
+    if role_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    role::Entity::find()
+        .filter(role::Column::Id.is_in(role_ids))
+        .order_by(role::Column::Name, Order::Asc)
+        .all(db)
+        .await
+}
@@ -0,0 +1,209 @@
+use askama::Template;
+use async_graphql::{Context, Object};
+use chrono::{Duration, Utc};
+use entity::{invitation, user};
+use log::info;
+use sea_orm::DatabaseConnection;
+use tokio::task::spawn_blocking;
+use uuid::Uuid;
+
+use crate::{
+    api::{database, MutationType},
+    claim::Claim,
+    config::CONFIG,
+    crypto::{generate_token, hash_token},
+    errors::{Error, Result},
+    guards::PermissionGuard,
+    mailer::mailer,
+    organization::db::{join_default_organization, set_member},
+    policy::Actor,
+    role::{
+        db::{assign_default_roles, assign_role, role_by_id},
+        model::Permission,
+    },
+    session::{model::ClientInfo, start_session},
+    simple_broker::SimpleBroker,
+    user::{
+        db::{new_user, user_by_email, user_by_id},
+        model::{LoginResult, NewUser, UserChanged},
+    },
+    validators::Password,
+};
+
+use self::{
+    db::{
+        accept_invitation, invitation_roles, list_pending_invitations, new_invitation,
+        pending_invitation_by_id, renew_invitation, revoke_invitation,
+        revoke_invitations_for_email, valid_invitation_by_hash,
+    },
+    model::{Invitation, InvitationTemplate},
+};
+
+mod db;
+pub mod model;
+
+#[derive(Default)]
+pub struct InvitationQuery;
+
+#[Object]
+impl InvitationQuery {
+    /// Invitations that are neither accepted nor revoked, newest first
+    #[graphql(guard = "PermissionGuard::new(Permission::UserRead)")]
+    async fn invitations(&self, ctx: &Context<'_>) -> Result<Vec<Invitation>> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        let invitations = list_pending_invitations(db, organization_id).await?;
+        Ok(invitations.into_iter().map(Invitation::from).collect())
+    }
+}
+
+#[derive(Default)]
+pub struct InvitationMutation;
+
+#[Object]
+impl InvitationMutation {
+    /// Emails a link to create an account, also works when registration is disabled.
+    ///
+    /// The user joins the organization the request was made in.
+    /// Pending invitations for the same email are revoked.
+    /// Handing out `roles` additionally requires the role manage permission.
+    #[graphql(guard = "PermissionGuard::new(Permission::UserWrite)")]
+    async fn invite_user(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(email))] email: String,
+        roles: Option<Vec<Uuid>>,
+    ) -> Result<Invitation> {
+        let actor = Actor::from_ctx(ctx).await?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        let roles = roles.unwrap_or_default();
+        if !roles.is_empty() && !actor.has(Permission::RoleManage) {
+            return Err(Error::Forbidden);
+        }
+        for role_id in &roles {
+            if role_by_id(db, *role_id).await?.is_none() {
+                return Err(Error::NotFound);
+            }
+        }
+        if user_by_email(db, &email).await?.is_some() {
+            return Err(Error::EmailAlreadyRegistred);
+        }
+
+        revoke_invitations_for_email(db, organization_id, &email).await?;
+        let token = generate_token();
+        let invitation = new_invitation(
+            db,
+            email,
+            hash_token(&token),
+            actor.id,
+            organization_id,
+            invitation_expiry(),
+            &roles,
+        )
+        .await?;
+        send_invitation_email(db, &invitation, &token).await?;
+        info!("{} invited by {}", invitation.email, actor.id);
+        Ok(Invitation::from(invitation))
+    }
+
+    /// Sends the invitation again with a new link, the old link stops working
+    #[graphql(guard = "PermissionGuard::new(Permission::UserWrite)")]
+    async fn resend_invitation(&self, ctx: &Context<'_>, id: Uuid) -> Result<Invitation> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        let invitation = pending_invitation_by_id(db, organization_id, id)
+            .await?
+            .ok_or(Error::NotFound)?;
+        let token = generate_token();
+        let invitation =
+            renew_invitation(db, invitation, hash_token(&token), invitation_expiry()).await?;
+        send_invitation_email(db, &invitation, &token).await?;
+        Ok(Invitation::from(invitation))
+    }
+
+    /// Returns `false` if there was no pending invitation with the id
+    #[graphql(guard = "PermissionGuard::new(Permission::UserWrite)")]
+    async fn revoke_invitation(&self, ctx: &Context<'_>, id: Uuid) -> Result<bool> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        Ok(revoke_invitation(db, organization_id, id).await?)
+    }
+
+    /// Creates the account with the token from an invitation email and logs the user in.
+    ///
+    /// The email counts as verified, it received the invitation.
+    async fn accept_invitation(
+        &self,
+        ctx: &Context<'_>,
+        token: String,
+        #[graphql(validator(custom = "Password"))] password: String,
+        name: Option<String>,
+    ) -> Result<LoginResult> {
+        if !CONFIG.password_login_enabled {
+            return Err(Error::PasswordLoginDisabled);
+        }
+        let db = database(ctx)?;
+        let invitation = match valid_invitation_by_hash(db, &hash_token(&token)).await? {
+            None => return Err(Error::ExpiredToken),
+            Some(invitation) => invitation,
+        };
+        if user_by_email(db, &invitation.email).await?.is_some() {
+            return Err(Error::EmailAlreadyRegistred);
+        }
+
+        // accepting first makes sure the invitation can only be used once
+        if !accept_invitation(db, invitation.id).await? {
+            return Err(Error::ExpiredToken);
+        }
+        let new = NewUser {
+            email: invitation.email.clone(),
+            password,
+            name,
+            is_admin: None,
+        };
+        let user = new_user(db, new, true).await?.ok_or(Error::NotFound)?;
+        assign_default_roles(db, user.id).await?;
+        for role in invitation_roles(db, invitation.id).await? {
+            assign_role(db, user.id, role.id).await?;
+        }
+        match invitation.organization_id {
+            Some(organization_id) => {
+                set_member(db, organization_id, user.id, false).await?;
+            }
+            None => join_default_organization(db, user.id).await?,
+        }
+        SimpleBroker::publish(UserChanged {
+            mutation_type: MutationType::Created,
+            id: user.id,
+        });
+
+        start_session(db, user.id, &user.email, ClientInfo::from_ctx(ctx)).await
+    }
+}
+
+fn invitation_expiry() -> chrono::DateTime<Utc> {
+    Utc::now() + Duration::seconds(CONFIG.invitation_lifetime)
+}
+
+async fn send_invitation_email(
+    db: &DatabaseConnection,
+    invitation: &invitation::Model,
+    token: &str,
+) -> Result<()> {
+    let inviter = match invitation.invited_by {
+        Some(id) => user_by_id(db, id).await?,
+        None => None,
+    };
+    let body = InvitationTemplate {
+        inviter: inviter.map(|user: user::Model| user.name.unwrap_or(user.email)),
+        email: invitation.email.clone(),
+        link: format!("{}/accept-invitation?token={}", CONFIG.base_url(), token),
+        valid_days: CONFIG.invitation_lifetime / 86400,
+    }
+    .render()?;
+    let to = invitation.email.clone();
+    spawn_blocking(move || mailer(&to, "You are invited to zorius", &body));
+
+    Ok(())
+}
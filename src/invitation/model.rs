@@ -0,0 +1,56 @@
+use askama::Template;
+use async_graphql::{ComplexObject, Context, SimpleObject};
+use entity::invitation::Model;
+use sea_orm::prelude::DateTimeUtc;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{api::database, errors::Result, role::model::Role};
+
+use super::db::invitation_roles;
+
+#[derive(SimpleObject, Debug, Serialize, Clone)]
+#[graphql(complex)]
+pub struct Invitation {
+    pub id: Uuid,
+    pub email: String,
+    /// User that sent the invitation
+    pub invited_by: Option<Uuid>,
+    pub expires_at: DateTimeUtc,
+    pub accepted_at: Option<DateTimeUtc>,
+    pub revoked_at: Option<DateTimeUtc>,
+    pub created_at: DateTimeUtc,
+}
+
+#[ComplexObject]
+impl Invitation {
+    /// Roles the user gets in addition to the default roles
+    async fn roles(&self, ctx: &Context<'_>) -> Result<Vec<Role>> {
+        let db = database(ctx)?;
+        let roles = invitation_roles(db, self.id).await?;
+        Ok(roles.into_iter().map(Role::from).collect())
+    }
+}
+
+impl From<Model> for Invitation {
+    fn from(model: Model) -> Self {
+        Self {
+            id: model.id,
+            email: model.email,
+            invited_by: model.invited_by,
+            expires_at: model.expires_at,
+            accepted_at: model.accepted_at,
+            revoked_at: model.revoked_at,
+            created_at: model.created_at,
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "invitation.html")]
+pub struct InvitationTemplate {
+    pub(crate) inviter: Option<String>,
+    pub(crate) email: String,
+    pub(crate) link: String,
+    pub(crate) valid_days: i64,
+}
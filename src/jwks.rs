@@ -0,0 +1,227 @@
+use std::{collections::HashMap, fs};
+
+use actix_web::HttpResponse;
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    errors::{Error as JwtError, ErrorKind},
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use lazy_static::lazy_static;
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    config::{JwtKeyConfig, CONFIG},
+    errors::{Error, Result},
+};
+
+lazy_static! {
+    pub static ref JWT_KEYS: JwtKeys = JwtKeys::load().expect("Failed to load jwt keys");
+}
+
+/// Key the access tokens are signed with
+struct SigningKey {
+    kid: String,
+    algorithm: Algorithm,
+    key: EncodingKey,
+}
+
+struct VerificationKey {
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+/// Public part of a key as published in `/.well-known/jwks.json`
+#[derive(Debug, Clone, Serialize)]
+pub struct Jwk {
+    kty: &'static str,
+    #[serde(rename = "use")]
+    public_key_use: &'static str,
+    alg: Algorithm,
+    kid: String,
+    /// RSA modulus
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<String>,
+    /// RSA exponent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    e: Option<String>,
+    /// Curve of an octet key pair
+    #[serde(skip_serializing_if = "Option::is_none")]
+    crv: Option<&'static str>,
+    /// Public key of an octet key pair
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+/// Keys to sign and verify access tokens.
+///
+/// Without `jwt.signing_key` the tokens are signed with `secret_key` (HS512), which is only
+/// accepted for tokens without `kid`. All keys in `jwt.keys` verify tokens, so a key can stay
+/// configured after a rotation until the tokens signed with it expired.
+pub struct JwtKeys {
+    signing: Option<SigningKey>,
+    verification: HashMap<String, VerificationKey>,
+    secret: DecodingKey,
+    jwks: JwkSet,
+}
+
+impl JwtKeys {
+    fn load() -> Result<Self> {
+        let mut signing = None;
+        let mut verification = HashMap::new();
+        let mut keys = vec![];
+        for config in &CONFIG.jwt.keys {
+            if verification.contains_key(&config.kid) {
+                return Err(Error::JwtKey(format!("duplicate kid {}", config.kid)));
+            }
+            let (encoding_key, decoding_key, jwk) = load_key(config)?;
+            if config.kid == CONFIG.jwt.signing_key {
+                signing = Some(SigningKey {
+                    kid: config.kid.clone(),
+                    algorithm: config.algorithm,
+                    key: encoding_key,
+                });
+            }
+            verification.insert(
+                config.kid.clone(),
+                VerificationKey {
+                    algorithm: config.algorithm,
+                    key: decoding_key,
+                },
+            );
+            keys.push(jwk);
+        }
+        if !CONFIG.jwt.signing_key.is_empty() && signing.is_none() {
+            return Err(Error::JwtKey(format!(
+                "signing key {} is not in jwt.keys",
+                CONFIG.jwt.signing_key
+            )));
+        }
+
+        Ok(Self {
+            signing,
+            verification,
+            secret: DecodingKey::from_secret(CONFIG.secret_key.as_bytes()),
+            jwks: JwkSet { keys },
+        })
+    }
+
+    /// Signs the claims with the signing key and puts its id in the `kid` header
+    pub fn encode<T: Serialize>(&self, claims: &T) -> std::result::Result<String, JwtError> {
+        match &self.signing {
+            Some(signing) => {
+                let mut header = Header::new(signing.algorithm);
+                header.kid = Some(signing.kid.clone());
+                encode(&header, claims, &signing.key)
+            }
+            None => {
+                let key = EncodingKey::from_secret(CONFIG.secret_key.as_bytes());
+                encode(&Header::new(Algorithm::HS512), claims, &key)
+            }
+        }
+    }
+
+    /// Verifies the token with the key of its `kid` and checks `exp`, `nbf` and `iss`
+    pub fn decode<T: DeserializeOwned>(&self, token: &str) -> std::result::Result<T, JwtError> {
+        let header = decode_header(token)?;
+        let (algorithm, key) = match &header.kid {
+            Some(kid) => {
+                let key = self
+                    .verification
+                    .get(kid)
+                    .ok_or_else(|| JwtError::from(ErrorKind::InvalidToken))?;
+                (key.algorithm, &key.key)
+            }
+            // tokens signed with the secret key have no kid
+            None if self.signing.is_none() => (Algorithm::HS512, &self.secret),
+            None => return Err(ErrorKind::InvalidToken.into()),
+        };
+
+        // only the algorithm of the key, never the one the token asks for
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = CONFIG.jwt.leeway;
+        validation.validate_nbf = true;
+        validation.set_issuer(&[CONFIG.base_url()]);
+        Ok(decode::<T>(token, key, &validation)?.claims)
+    }
+}
+
+fn load_key(config: &JwtKeyConfig) -> Result<(EncodingKey, DecodingKey, Jwk)> {
+    let pem = fs::read(&config.private_key).map_err(|e| {
+        Error::JwtKey(format!(
+            "can't read private key {}: {}",
+            config.private_key, e
+        ))
+    })?;
+    let der = pem::parse(&pem)
+        .map_err(|e| Error::JwtKey(format!("{} is not a pem file: {}", config.private_key, e)))?
+        .contents;
+    let invalid_key = |e| {
+        Error::JwtKey(format!(
+            "{} is not a valid {:?} key: {}",
+            config.private_key, config.algorithm, e
+        ))
+    };
+
+    let mut jwk = Jwk {
+        kty: "",
+        public_key_use: "sig",
+        alg: config.algorithm,
+        kid: config.kid.clone(),
+        n: None,
+        e: None,
+        crv: None,
+        x: None,
+    };
+    match config.algorithm {
+        Algorithm::RS256 => {
+            // PKCS#8 ("PRIVATE KEY") or PKCS#1 ("RSA PRIVATE KEY")
+            let key_pair = RsaKeyPair::from_pkcs8(&der)
+                .or_else(|_| RsaKeyPair::from_der(&der))
+                .map_err(invalid_key)?;
+            let n = key_pair.public_key().modulus();
+            let e = key_pair.public_key().exponent();
+            let decoding_key = DecodingKey::from_rsa_raw_components(
+                n.big_endian_without_leading_zero(),
+                e.big_endian_without_leading_zero(),
+            );
+            jwk.kty = "RSA";
+            jwk.n = Some(base64url(n.big_endian_without_leading_zero()));
+            jwk.e = Some(base64url(e.big_endian_without_leading_zero()));
+            Ok((EncodingKey::from_rsa_pem(&pem)?, decoding_key, jwk))
+        }
+        Algorithm::EdDSA => {
+            // PKCS#8 ("PRIVATE KEY") as written by `openssl genpkey -algorithm ed25519`
+            let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(&der).map_err(invalid_key)?;
+            let public_key = key_pair.public_key().as_ref();
+            jwk.kty = "OKP";
+            jwk.crv = Some("Ed25519");
+            jwk.x = Some(base64url(public_key));
+            Ok((
+                EncodingKey::from_ed_pem(&pem)?,
+                DecodingKey::from_ed_der(public_key),
+                jwk,
+            ))
+        }
+        algorithm => Err(Error::JwtKey(format!(
+            "unsupported algorithm {:?} for key {}, use RS256 or EdDSA",
+            algorithm, config.kid
+        ))),
+    }
+}
+
+fn base64url(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Public keys to verify access tokens, `GET /.well-known/jwks.json`
+pub async fn jwks() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(("cache-control", "public, max-age=300"))
+        .json(&JWT_KEYS.jwks)
+}
@@ -5,14 +5,11 @@ use std::{fs::File, time::Duration};
 use actix_cors::Cors;
 use actix_files::Files;
 use actix_governor::{Governor, GovernorConfigBuilder};
+use actix_web::dev::Service;
 use actix_web::guard;
-use actix_web::http::header;
+use actix_web::http::header::{self, HeaderName, HeaderValue};
 use actix_web::web::{self, Data};
-use actix_web::{
-    http::Method,
-    middleware::{DefaultHeaders, Logger},
-    App, HttpServer,
-};
+use actix_web::{http::Method, middleware::Logger, App, HttpMessage, HttpServer};
 use async_graphql::http::MultipartOptions;
 use async_graphql::Schema;
 use log::{debug, error, info};
@@ -21,28 +18,47 @@ use rustls::{Certificate, PrivateKey, ServerConfig};
 use rustls_pemfile::{read_one, Item};
 use sea_orm::{Database as SeaOrmDatabase, DatabaseConnection};
 use tokio::time::sleep;
-use uuid::Uuid;
 
 use crate::api::{graphql_ws, Subscription};
 use crate::errors::Error;
 
 mod api;
+mod api_token;
+mod audit;
+mod auth;
+mod avatar;
 mod claim;
 mod config;
+mod crypto;
 mod customer;
 mod errors;
 mod guards;
+mod invitation;
+mod jwks;
 mod mailer;
+mod oidc;
+mod organization;
+mod policy;
+mod privacy;
 mod project;
+mod role;
+mod session;
 mod simple_broker;
+mod team;
+mod two_factor;
 mod upload;
 mod user;
 mod validators;
 mod work_report;
 
 use crate::{
-    api::{graphql, playground, Mutation, Query},
+    api::{graphql, playground, Mutation, Query, RequestId, REQUEST_ID_HEADER},
+    avatar::{identicon_handler, AVATAR_DIR},
     config::CONFIG,
+    jwks::{jwks, JWT_KEYS},
+    oidc::{oidc_callback, oidc_login},
+    privacy::download_export,
+    user::model::ARGON2_PARAMS,
 };
 
 const API_VERSION: &str = "v1";
@@ -98,8 +114,8 @@ fn check_folders() -> Result<(), Error> {
     if !Path::new("files").exists() {
         std::fs::create_dir("files")?;
     }
-    if !Path::new("static").exists() {
-        std::fs::create_dir("static/avatar")?;
+    if !Path::new(AVATAR_DIR).exists() {
+        std::fs::create_dir_all(AVATAR_DIR)?;
     }
     Ok(())
 }
@@ -108,6 +124,9 @@ fn check_folders() -> Result<(), Error> {
 async fn main() -> Result<(), Error> {
     setup_log();
     check_folders()?;
+    // fail on startup and not with the first login if a key or hash parameter is misconfigured
+    lazy_static::initialize(&JWT_KEYS);
+    lazy_static::initialize(&ARGON2_PARAMS);
 
     let mut db_connect_trys: i32 = 1;
     let database = loop {
@@ -130,7 +149,9 @@ async fn main() -> Result<(), Error> {
     Migrator::up(&database, None)
         .await
         .expect("migrations failed");
+    tokio::spawn(customer::purge_trashed_customers(database.clone()));
 
+    let database_data = Data::new(database.clone());
     let schema = Schema::build(
         Query::default(),
         Mutation::default(),
@@ -158,6 +179,7 @@ async fn main() -> Result<(), Error> {
     let http_server = HttpServer::new(move || {
         App::new()
             .app_data(Data::new(schema.clone()))
+            .app_data(database_data.clone())
             .wrap(
                 Cors::default()
                     .allowed_methods(&[Method::GET, Method::POST, Method::OPTIONS])
@@ -169,7 +191,20 @@ async fn main() -> Result<(), Error> {
                     .max_age(3600),
             )
             .wrap(Governor::new(&governor_conf))
-            .wrap(DefaultHeaders::new().add(("x-request-id", Uuid::new_v4().to_string())))
+            .wrap_fn(|req, srv| {
+                let request_id = RequestId::from_headers(req.headers());
+                req.extensions_mut().insert(request_id.clone());
+                let response = srv.call(req);
+                async move {
+                    let mut response = response.await?;
+                    if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+                        response
+                            .headers_mut()
+                            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                    }
+                    Ok(response)
+                }
+            })
             .wrap(Logger::new(&log_format))
             .service(
                 web::resource("/graphql")
@@ -177,6 +212,31 @@ async fn main() -> Result<(), Error> {
                     .to(graphql)
                     .app_data(MultipartOptions::default().max_num_files(5)),
             )
+            .service(
+                web::resource("/.well-known/jwks.json")
+                    .guard(guard::Get())
+                    .to(jwks),
+            )
+            .service(
+                web::resource("/auth/oidc/login")
+                    .guard(guard::Get())
+                    .to(oidc_login),
+            )
+            .service(
+                web::resource("/auth/oidc/callback")
+                    .guard(guard::Get())
+                    .to(oidc_callback),
+            )
+            .service(
+                web::resource("/avatar/identicon/{user_id}")
+                    .guard(guard::Get())
+                    .to(identicon_handler),
+            )
+            .service(
+                web::resource("/exports/{token}")
+                    .guard(guard::Get())
+                    .to(download_export),
+            )
             .service(
                 web::resource("/playground")
                     .guard(guard::Get())
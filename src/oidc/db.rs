@@ -0,0 +1,42 @@
+use std::collections::{HashMap, HashSet};
+
+use log::warn;
+use sea_orm::DatabaseConnection;
+use uuid::Uuid;
+
+use crate::{
+    errors::Result,
+    role::db::{assign_role, role_by_name, unassign_role},
+};
+
+/// Assigns the roles mapped to the groups of the user and removes the mapped roles of other groups.
+/// Roles that are not part of the mapping are left untouched.
+pub async fn sync_mapped_roles(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    mapping: &HashMap<String, String>,
+    groups: &[String],
+) -> Result<()> {
+    let granted = mapping
+        .iter()
+        .filter(|(group, _)| groups.contains(group))
+        .map(|(_, role_name)| role_name)
+        .collect::<HashSet<_>>();
+    let mapped = mapping.values().collect::<HashSet<_>>();
+
+    for role_name in mapped {
+        let role = match role_by_name(db, role_name).await? {
+            Some(role) => role,
+            None => {
+                warn!("role {} of the oidc role mapping does not exist", role_name);
+                continue;
+            }
+        };
+        if granted.contains(role_name) {
+            assign_role(db, user_id, role.id).await?;
+        } else {
+            unassign_role(db, user_id, role.id).await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,463 @@
+use std::convert::{TryFrom, TryInto};
+
+use actix_web::{
+    cookie::{time::Duration as CookieDuration, Cookie, SameSite},
+    http::header,
+    web::{Data, Query},
+    HttpRequest, HttpResponse,
+};
+use chrono::Utc;
+use entity::user;
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{AlgorithmParameters, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use log::{error, info};
+use sea_orm::DatabaseConnection;
+use tokio::sync::OnceCell;
+use url::Url;
+
+use crate::{
+    auth::db::{link_identity, provision_user, set_admin, user_by_identity},
+    claim::TwoFactorChallenge,
+    config::CONFIG,
+    errors::{Error, Result},
+    session::{model::ClientInfo, start_session},
+    user::{
+        db::user_by_email,
+        model::{ensure_active, LoginResult},
+    },
+};
+
+use self::{
+    db::sync_mapped_roles,
+    model::{
+        CallbackQuery, IdTokenClaims, OidcLoginState, ProviderMetadata, TokenResponse,
+        STATE_COOKIE, STATE_LIFETIME,
+    },
+};
+
+mod db;
+pub mod model;
+
+static PROVIDER_METADATA: OnceCell<ProviderMetadata> = OnceCell::const_new();
+
+/// Redirects the browser to the provider to log in, `GET /auth/oidc/login`
+pub async fn oidc_login() -> HttpResponse {
+    match authorization_redirect().await {
+        Ok(response) => response,
+        Err(e) => {
+            error!("failed to start oidc login: {}", e);
+            redirect_to_frontend(&format!("error={}", urlencode("oidc_unavailable")))
+        }
+    }
+}
+
+/// The provider redirects back here after the login, `GET /auth/oidc/callback`.
+/// Sends the browser to `oidc.frontend_url` with the tokens of a new session.
+pub async fn oidc_callback(
+    db: Data<DatabaseConnection>,
+    http_request: HttpRequest,
+    query: Query<CallbackQuery>,
+) -> HttpResponse {
+    let mut response = match finish_login(&db, &http_request, query.into_inner()).await {
+        Ok(LoginResult {
+            two_factor_challenge: Some(challenge),
+            ..
+        }) => redirect_to_frontend(&format!("two_factor_challenge={}", urlencode(&challenge))),
+        Ok(login) => redirect_to_frontend(&format!(
+            "token={}&refresh_token={}",
+            urlencode(&login.token.unwrap_or_default()),
+            urlencode(&login.refresh_token.unwrap_or_default())
+        )),
+        Err(e) => {
+            info!("oidc login failed: {}", e);
+            redirect_to_frontend(&format!("error={}", urlencode(&e.to_string())))
+        }
+    };
+    // the login state can only be used once
+    let mut cookie = state_cookie(String::new());
+    cookie.make_removal();
+    let _ = response.add_cookie(&cookie);
+    response
+}
+
+async fn provider_metadata() -> Result<&'static ProviderMetadata> {
+    PROVIDER_METADATA
+        .get_or_try_init(|| async {
+            let url = format!(
+                "{}/.well-known/openid-configuration",
+                CONFIG.oidc.issuer_url.trim_end_matches('/')
+            );
+            let metadata = reqwest::get(url)
+                .await?
+                .error_for_status()?
+                .json::<ProviderMetadata>()
+                .await?;
+            verify_issuer(&metadata, &CONFIG.oidc.issuer_url)?;
+            Ok(metadata)
+        })
+        .await
+}
+
+/// The discovery document has to be the one of the configured provider (OpenID Connect
+/// Discovery 1.0, section 4.3), otherwise it could point to the endpoints of another one.
+fn verify_issuer(metadata: &ProviderMetadata, issuer_url: &str) -> Result<()> {
+    if metadata.issuer != issuer_url {
+        return Err(Error::Oidc(format!(
+            "issuer {} of the discovery document does not match {}",
+            metadata.issuer, issuer_url
+        )));
+    }
+    Ok(())
+}
+
+async fn authorization_redirect() -> Result<HttpResponse> {
+    if !CONFIG.oidc.enabled {
+        return Err(Error::Oidc("openid connect is not enabled".to_owned()));
+    }
+    let metadata = provider_metadata().await?;
+    let login_state = OidcLoginState::new();
+
+    let mut url = Url::parse(&metadata.authorization_endpoint)
+        .map_err(|e| Error::Oidc(format!("invalid authorization endpoint: {}", e)))?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &CONFIG.oidc.client_id)
+        .append_pair("redirect_uri", &CONFIG.oidc.redirect_url)
+        .append_pair("scope", &CONFIG.oidc.scopes)
+        .append_pair("state", &login_state.state)
+        .append_pair("nonce", &login_state.nonce)
+        .append_pair("code_challenge", &login_state.code_challenge())
+        .append_pair("code_challenge_method", "S256");
+
+    let cookie = state_cookie(login_state.try_into()?);
+    Ok(HttpResponse::Found()
+        .append_header((header::LOCATION, url.to_string()))
+        .cookie(cookie)
+        .finish())
+}
+
+async fn finish_login(
+    db: &DatabaseConnection,
+    http_request: &HttpRequest,
+    query: CallbackQuery,
+) -> Result<LoginResult> {
+    if !CONFIG.oidc.enabled {
+        return Err(Error::Oidc("openid connect is not enabled".to_owned()));
+    }
+    let cookie = http_request
+        .cookie(STATE_COOKIE)
+        .ok_or_else(|| Error::Oidc("login state is missing or expired".to_owned()))?;
+    let login_state = OidcLoginState::try_from(cookie.value())?;
+    let code = login_state.verify_callback(query)?;
+
+    let metadata = provider_metadata().await?;
+    let id_token = exchange_code(metadata, &code, &login_state.code_verifier).await?;
+    let claims = verify_id_token(metadata, &id_token).await?;
+    login_state.verify_nonce(&claims)?;
+
+    let user = login_user(db, &claims).await?;
+    ensure_active(&user)?;
+    if let Some(locked_until) = user.locked_until {
+        if locked_until > Utc::now() {
+            return Err(Error::AccountLocked(locked_until));
+        }
+    }
+    if CONFIG.require_email_verification && user.email_verified_at.is_none() {
+        return Err(Error::EmailNotVerified);
+    }
+    // the provider only replaces the password, the second factor is still required
+    if user.totp_enabled_at.is_some() {
+        let challenge = TwoFactorChallenge::new(&user.id).try_into()?;
+        return Ok(LoginResult {
+            two_factor_challenge: Some(challenge),
+            ..Default::default()
+        });
+    }
+    start_session(
+        db,
+        user.id,
+        &user.email,
+        ClientInfo::from_request(http_request),
+    )
+    .await
+}
+
+async fn exchange_code(
+    metadata: &ProviderMetadata,
+    code: &str,
+    code_verifier: &str,
+) -> Result<String> {
+    let response = reqwest::Client::new()
+        .post(&metadata.token_endpoint)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &CONFIG.oidc.redirect_url),
+            ("client_id", &CONFIG.oidc.client_id),
+            ("client_secret", &CONFIG.oidc.client_secret),
+            ("code_verifier", code_verifier),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<TokenResponse>()
+        .await?;
+    Ok(response.id_token)
+}
+
+/// Checks the signature of the id token with the keys of the provider.
+/// Only RSA keys are supported.
+async fn verify_id_token(metadata: &ProviderMetadata, id_token: &str) -> Result<IdTokenClaims> {
+    let header = decode_header(id_token)?;
+    let jwks = reqwest::get(&metadata.jwks_uri)
+        .await?
+        .error_for_status()?
+        .json::<JwkSet>()
+        .await?;
+    let jwk = match &header.kid {
+        Some(kid) => jwks.find(kid),
+        None => jwks.keys.first(),
+    }
+    .ok_or_else(|| Error::Oidc("signing key of the id token is unknown".to_owned()))?;
+    let key = match &jwk.algorithm {
+        AlgorithmParameters::RSA(rsa) => DecodingKey::from_rsa_components(&rsa.n, &rsa.e)?,
+        _ => {
+            return Err(Error::Oidc(
+                "only RSA signing keys are supported".to_owned(),
+            ))
+        }
+    };
+
+    // never let the token choose a symmetric algorithm
+    if !matches!(
+        header.alg,
+        Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512
+    ) {
+        return Err(Error::Oidc("id token is not signed with RSA".to_owned()));
+    }
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&[&CONFIG.oidc.client_id]);
+    validation.set_issuer(&[&metadata.issuer]);
+    Ok(decode::<IdTokenClaims>(id_token, &key, &validation)?.claims)
+}
+
+/// Finds the user of the identity, links it to an existing user with the same verified email
+/// or provisions a new user. Admin flag and mapped roles are updated from the groups.
+async fn login_user(db: &DatabaseConnection, claims: &IdTokenClaims) -> Result<user::Model> {
+    let user = match user_by_identity(db, &claims.iss, &claims.sub).await? {
+        Some(user) => user,
+        None => {
+            let email = claims
+                .email
+                .clone()
+                .ok_or_else(|| Error::Oidc("the provider did not return an email".to_owned()))?;
+            let email_verified = claims.email_verified.unwrap_or(false);
+            let existing = user_by_email(db, &email).await?;
+            let user =
+                match match_existing_user(existing, email_verified, CONFIG.oidc.provision_users)? {
+                    Some(user) => user,
+                    None => {
+                        info!("provisioning user {} from oidc", email);
+                        provision_user(db, email, claims.name.clone(), email_verified).await?
+                    }
+                };
+            link_identity(db, user.id, &claims.iss, &claims.sub).await?;
+            user
+        }
+    };
+
+    let groups = claims.groups();
+    let user = if CONFIG.oidc.admin_groups.is_empty() {
+        user
+    } else {
+        let is_admin = CONFIG
+            .oidc
+            .admin_groups
+            .iter()
+            .any(|group| groups.contains(group));
+        set_admin(db, user, is_admin).await?
+    };
+    sync_mapped_roles(db, user.id, &CONFIG.oidc.role_mapping, &groups).await?;
+    Ok(user)
+}
+
+/// Decides what to do with an identity that is not linked yet. Returns the user to link
+/// it to or `None` if a new user has to be provisioned.
+fn match_existing_user(
+    existing: Option<user::Model>,
+    email_verified: bool,
+    provision_users: bool,
+) -> Result<Option<user::Model>> {
+    match existing {
+        // only trust the email for existing users if the provider verified it
+        Some(_) if !email_verified => Err(Error::EmailAlreadyRegistred),
+        Some(user) => Ok(Some(user)),
+        None if provision_users => Ok(None),
+        None => Err(Error::RegistrationNotEnabled),
+    }
+}
+
+fn state_cookie(value: String) -> Cookie<'static> {
+    Cookie::build(STATE_COOKIE, value)
+        .path("/auth/oidc")
+        .http_only(true)
+        .secure(CONFIG.web.enable_ssl)
+        // Lax, the cookie has to be sent on the redirect back from the provider
+        .same_site(SameSite::Lax)
+        .max_age(CookieDuration::seconds(STATE_LIFETIME))
+        .finish()
+}
+
+/// The fragment is not sent to servers, so the tokens don't end up in access logs
+fn redirect_to_frontend(fragment: &str) -> HttpResponse {
+    HttpResponse::Found()
+        .append_header((
+            header::LOCATION,
+            format!("{}#{}", CONFIG.oidc.frontend_url, fragment),
+        ))
+        .finish()
+}
+
+fn urlencode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn callback(state: &str, code: Option<&str>) -> CallbackQuery {
+        CallbackQuery {
+            code: code.map(str::to_owned),
+            state: Some(state.to_owned()),
+            error: None,
+            error_description: None,
+        }
+    }
+
+    fn claims(nonce: Option<&str>) -> IdTokenClaims {
+        IdTokenClaims {
+            iss: "https://id.example.com".to_owned(),
+            sub: "1234".to_owned(),
+            email: Some("user@example.com".to_owned()),
+            email_verified: Some(true),
+            name: None,
+            nonce: nonce.map(str::to_owned),
+            other: HashMap::new(),
+        }
+    }
+
+    fn user() -> user::Model {
+        user::Model {
+            id: Uuid::new_v4(),
+            email: "user@example.com".to_owned(),
+            email_verified_at: None,
+            password_hash: String::new(),
+            name: None,
+            avatar_filename: None,
+            is_admin: false,
+            totp_secret: None,
+            totp_enabled_at: None,
+            totp_last_used_step: None,
+            failed_login_attempts: 0,
+            locked_until: None,
+            deactivated_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn login_state_survives_the_cookie() {
+        let login_state = OidcLoginState::new();
+        let state = login_state.state.clone();
+        let code_verifier = login_state.code_verifier.clone();
+        let cookie: String = login_state.try_into().unwrap();
+        let login_state = OidcLoginState::try_from(cookie.as_str()).unwrap();
+        assert_eq!(login_state.state, state);
+        assert_eq!(login_state.code_verifier, code_verifier);
+
+        let challenge: String = TwoFactorChallenge::new(&Uuid::new_v4()).try_into().unwrap();
+        assert!(OidcLoginState::try_from(challenge.as_str()).is_err());
+    }
+
+    #[test]
+    fn callback_has_to_match_the_state() {
+        let login_state = OidcLoginState::new();
+        let code = login_state
+            .verify_callback(callback(&login_state.state, Some("code")))
+            .unwrap();
+        assert_eq!(code, "code");
+        assert!(login_state
+            .verify_callback(callback("other", Some("code")))
+            .is_err());
+        assert!(login_state
+            .verify_callback(callback(&login_state.state, None))
+            .is_err());
+        let mut query = callback(&login_state.state, Some("code"));
+        query.error = Some("access_denied".to_owned());
+        assert!(login_state.verify_callback(query).is_err());
+    }
+
+    #[test]
+    fn id_token_has_to_match_the_nonce() {
+        let login_state = OidcLoginState::new();
+        assert!(login_state
+            .verify_nonce(&claims(Some(&login_state.nonce)))
+            .is_ok());
+        assert!(login_state.verify_nonce(&claims(Some("other"))).is_err());
+        assert!(login_state.verify_nonce(&claims(None)).is_err());
+    }
+
+    #[test]
+    fn code_challenge_is_the_s256_hash_of_the_verifier() {
+        let mut login_state = OidcLoginState::new();
+        login_state.code_verifier = "dBjftJeZ4CVP-mJ92IyMHBlJMwA0HcD1N3DyX0Ixbw".to_owned();
+        assert_eq!(
+            login_state.code_challenge(),
+            "4Fq9u1jvCKS3kqvmEpfgd2mhrYumQmEuhzySIdDasnI"
+        );
+    }
+
+    #[test]
+    fn discovery_has_to_match_the_issuer() {
+        let metadata = ProviderMetadata {
+            issuer: "https://id.example.com".to_owned(),
+            authorization_endpoint: "https://id.example.com/authorize".to_owned(),
+            token_endpoint: "https://id.example.com/token".to_owned(),
+            jwks_uri: "https://id.example.com/jwks".to_owned(),
+        };
+        assert!(verify_issuer(&metadata, "https://id.example.com").is_ok());
+        assert!(verify_issuer(&metadata, "https://id.example.com/").is_err());
+        assert!(verify_issuer(&metadata, "https://evil.example.com").is_err());
+    }
+
+    #[test]
+    fn unlinked_identities_are_linked_or_provisioned() {
+        let existing = user();
+        let linked = match_existing_user(Some(existing.clone()), true, false).unwrap();
+        assert_eq!(linked.map(|user| user.id), Some(existing.id));
+        assert!(matches!(
+            match_existing_user(Some(existing), false, true),
+            Err(Error::EmailAlreadyRegistred)
+        ));
+        assert!(match_existing_user(None, true, true).unwrap().is_none());
+        assert!(matches!(
+            match_existing_user(None, true, false),
+            Err(Error::RegistrationNotEnabled)
+        ));
+    }
+}
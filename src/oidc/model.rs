@@ -0,0 +1,148 @@
+use std::{
+    collections::HashMap,
+    convert::{TryFrom, TryInto},
+};
+
+use chrono::Local;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{config::CONFIG, crypto::generate_token, errors::Error};
+
+/// Name of the cookie that keeps the `OidcLoginState` between login and callback
+pub const STATE_COOKIE: &str = "zorius_oidc";
+/// How long the user has to log in at the provider in seconds
+pub const STATE_LIFETIME: i64 = 600;
+
+/// Subset of the provider metadata from `/.well-known/openid-configuration`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenResponse {
+    pub id_token: String,
+}
+
+/// Claims of the id token, `aud`, `exp` and `iss` are checked while decoding
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    pub nonce: Option<String>,
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+impl IdTokenClaims {
+    /// Groups of the user from the claim configured in `oidc.groups_claim`
+    pub fn groups(&self) -> Vec<String> {
+        match self.other.get(&CONFIG.oidc.groups_claim) {
+            Some(serde_json::Value::Array(groups)) => groups
+                .iter()
+                .filter_map(|group| group.as_str().map(str::to_owned))
+                .collect(),
+            Some(serde_json::Value::String(group)) => vec![group.to_owned()],
+            _ => vec![],
+        }
+    }
+}
+
+/// Query parameters the provider redirects back with
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// State of a login that is in progress, it is signed and kept in a cookie
+/// so the callback can check `state` and `nonce` and prove the PKCE `code_verifier`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OidcLoginState {
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    /// Time after which the JWT expires
+    exp: usize,
+    /// Always `OIDC_PURPOSE`, so the state can't be mixed up with other tokens
+    purpose: String,
+}
+
+const OIDC_PURPOSE: &str = "oidc_login";
+
+impl OidcLoginState {
+    pub fn new() -> Self {
+        Self {
+            state: generate_token(),
+            nonce: generate_token(),
+            code_verifier: generate_token(),
+            exp: (Local::now().timestamp() + STATE_LIFETIME) as usize,
+            purpose: OIDC_PURPOSE.to_owned(),
+        }
+    }
+
+    /// Checks that the provider redirected back for this login and returns the authorization code
+    pub fn verify_callback(&self, query: CallbackQuery) -> Result<String, Error> {
+        if let Some(error) = query.error {
+            return Err(Error::Oidc(query.error_description.unwrap_or(error)));
+        }
+        if query.state.as_deref() != Some(self.state.as_str()) {
+            return Err(Error::Oidc("state does not match".to_owned()));
+        }
+        query
+            .code
+            .ok_or_else(|| Error::Oidc("authorization code is missing".to_owned()))
+    }
+
+    /// Checks that the id token was issued for this login
+    pub fn verify_nonce(&self, claims: &IdTokenClaims) -> Result<(), Error> {
+        if claims.nonce.as_deref() != Some(self.nonce.as_str()) {
+            return Err(Error::Oidc("nonce does not match".to_owned()));
+        }
+        Ok(())
+    }
+
+    /// PKCE `S256` code challenge of the code verifier
+    pub fn code_challenge(&self) -> String {
+        base64::encode_config(
+            Sha256::digest(self.code_verifier.as_bytes()),
+            base64::URL_SAFE_NO_PAD,
+        )
+    }
+}
+
+impl TryInto<String> for OidcLoginState {
+    type Error = jsonwebtoken::errors::Error;
+
+    fn try_into(self) -> Result<String, Self::Error> {
+        let key = EncodingKey::from_secret(CONFIG.secret_key.as_bytes());
+        let algo = Algorithm::HS512;
+
+        jsonwebtoken::encode(&Header::new(algo), &self, &key)
+    }
+}
+
+impl TryFrom<&str> for OidcLoginState {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let dec = DecodingKey::from_secret(CONFIG.secret_key.as_bytes());
+        let vali = Validation::new(Algorithm::HS512);
+
+        let state = decode::<OidcLoginState>(value.trim(), &dec, &vali)?.claims;
+        if state.purpose != OIDC_PURPOSE {
+            return Err(Error::MalformedToken);
+        }
+        Ok(state)
+    }
+}
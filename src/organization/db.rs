@@ -0,0 +1,147 @@
+use entity::{
+    organization::{ActiveModel, Column, Entity, Model},
+    organization_member,
+};
+use sea_orm::{prelude::*, DatabaseConnection, Order, QueryOrder, Set};
+use uuid::Uuid;
+
+use crate::errors::Result;
+
+pub async fn organization_by_id(db: &DatabaseConnection, id: Uuid) -> Result<Option<Model>> {
+    Ok(Entity::find_by_id(id).one(db).await?)
+}
+
+pub async fn organization_by_name(db: &DatabaseConnection, name: &str) -> Result<Option<Model>> {
+    Ok(Entity::find().filter(Column::Name.eq(name)).one(db).await?)
+}
+
+pub async fn default_organization(db: &DatabaseConnection) -> Result<Option<Model>> {
+    Ok(Entity::find()
+        .filter(Column::IsDefault.eq(true))
+        .order_by(Column::CreatedAt, Order::Asc)
+        .one(db)
+        .await?)
+}
+
+pub async fn new_organization(db: &DatabaseConnection, name: String) -> Result<Model> {
+    Ok(ActiveModel {
+        name: Set(name),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?)
+}
+
+pub async fn rename_organization(
+    db: &DatabaseConnection,
+    org: Model,
+    name: String,
+) -> Result<Model> {
+    let mut org: ActiveModel = org.into();
+    org.name = Set(name);
+    Ok(org.update(db).await?)
+}
+
+/// Organizations the user is a member of together with the membership, oldest membership first
+pub async fn organizations_of_user(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<Vec<(organization_member::Model, Model)>> {
+    let memberships = organization_member::Entity::find()
+        .filter(organization_member::Column::UserId.eq(user_id))
+        .order_by(organization_member::Column::CreatedAt, Order::Asc)
+        .find_also_related(Entity)
+        .all(db)
+        .await?;
+    Ok(memberships
+        .into_iter()
+        .filter_map(|(member, org)| org.map(|org| (member, org)))
+        .collect())
+}
+
+/// Organization a new session of the user starts in
+pub async fn first_organization_of_user(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<Option<Uuid>> {
+    Ok(organization_member::Entity::find()
+        .filter(organization_member::Column::UserId.eq(user_id))
+        .order_by(organization_member::Column::CreatedAt, Order::Asc)
+        .one(db)
+        .await?
+        .map(|member| member.organization_id))
+}
+
+pub async fn membership(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<organization_member::Model>> {
+    Ok(
+        organization_member::Entity::find_by_id((organization_id, user_id))
+            .one(db)
+            .await?,
+    )
+}
+
+pub async fn list_members(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+) -> Result<Vec<organization_member::Model>> {
+    Ok(organization_member::Entity::find()
+        .filter(organization_member::Column::OrganizationId.eq(organization_id))
+        .order_by(organization_member::Column::CreatedAt, Order::Asc)
+        .all(db)
+        .await?)
+}
+
+pub async fn count_admins(db: &DatabaseConnection, organization_id: Uuid) -> Result<usize> {
+    Ok(organization_member::Entity::find()
+        .filter(organization_member::Column::OrganizationId.eq(organization_id))
+        .filter(organization_member::Column::IsAdmin.eq(true))
+        .count(db)
+        .await?)
+}
+
+/// Adds the user to the organization or changes the admin flag of an existing membership
+pub async fn set_member(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    user_id: Uuid,
+    is_admin: bool,
+) -> Result<organization_member::Model> {
+    if let Some(member) = membership(db, organization_id, user_id).await? {
+        let mut member: organization_member::ActiveModel = member.into();
+        member.is_admin = Set(is_admin);
+        return Ok(member.update(db).await?);
+    }
+    Ok(organization_member::ActiveModel {
+        organization_id: Set(organization_id),
+        user_id: Set(user_id),
+        is_admin: Set(is_admin),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?)
+}
+
+pub async fn remove_member(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<u64> {
+    Ok(organization_member::Entity::delete_many()
+        .filter(organization_member::Column::OrganizationId.eq(organization_id))
+        .filter(organization_member::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?
+        .rows_affected)
+}
+
+/// Adds a new user to the default organization, if there is one
+pub async fn join_default_organization(db: &DatabaseConnection, user_id: Uuid) -> Result<()> {
+    if let Some(org) = default_organization(db).await? {
+        set_member(db, org.id, user_id, false).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use async_graphql::{Context, Object};
+use entity::user;
+use sea_orm::{DatabaseConnection, EntityTrait};
+use uuid::Uuid;
+
+use crate::{
+    api::database,
+    claim::Claim,
+    errors::{Error, Result},
+    guards::{AdminGuard, TokenGuard},
+    session::switch_session_organization,
+    user::model::LoginResult,
+};
+
+use self::{
+    db::{
+        count_admins, list_members, membership, new_organization, organization_by_id,
+        organization_by_name, organizations_of_user, remove_member, rename_organization,
+        set_member,
+    },
+    model::{MyOrganization, Organization, OrganizationMember},
+};
+
+pub mod db;
+pub mod model;
+
+#[derive(Default)]
+pub struct OrganizationQuery;
+
+#[Object]
+impl OrganizationQuery {
+    /// Lists the organizations the current user is a member of
+    #[graphql(guard = "TokenGuard")]
+    async fn my_organizations(&self, ctx: &Context<'_>) -> Result<Vec<MyOrganization>> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        let current = claim.organization_id().ok();
+        let organizations = organizations_of_user(db, claim.user_id()?).await?;
+
+        Ok(organizations
+            .into_iter()
+            .map(|(member, org)| MyOrganization {
+                current: current == Some(org.id),
+                is_admin: member.is_admin,
+                organization: org.into(),
+            })
+            .collect())
+    }
+
+    /// Lists the members of an organization the current user is a member of
+    #[graphql(guard = "TokenGuard")]
+    async fn organization_members(
+        &self,
+        ctx: &Context<'_>,
+        organization_id: Uuid,
+    ) -> Result<Vec<OrganizationMember>> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        let user_id = claim.user_id()?;
+        if membership(db, organization_id, user_id).await?.is_none()
+            && !is_instance_admin(db, user_id).await?
+        {
+            return Err(Error::Forbidden);
+        }
+        let members = list_members(db, organization_id).await?;
+        Ok(members.into_iter().map(OrganizationMember::from).collect())
+    }
+}
+
+#[derive(Default)]
+pub struct OrganizationMutation;
+
+#[Object]
+impl OrganizationMutation {
+    /// Creates a new organization, the current user becomes its first org admin
+    #[graphql(guard = "AdminGuard")]
+    async fn new_organization(&self, ctx: &Context<'_>, name: String) -> Result<Organization> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        if organization_by_name(db, &name).await?.is_some() {
+            return Err(Error::OrganizationNameTaken);
+        }
+        let org = new_organization(db, name).await?;
+        set_member(db, org.id, claim.user_id()?, true).await?;
+        Ok(org.into())
+    }
+
+    #[graphql(guard = "TokenGuard")]
+    async fn rename_organization(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        name: String,
+    ) -> Result<Option<Organization>> {
+        let db = database(ctx)?;
+        authorize_organization_admin(ctx, db, id).await?;
+        let org = match organization_by_id(db, id).await? {
+            Some(org) => org,
+            None => return Ok(None),
+        };
+        if org.name != name && organization_by_name(db, &name).await?.is_some() {
+            return Err(Error::OrganizationNameTaken);
+        }
+        Ok(Some(rename_organization(db, org, name).await?.into()))
+    }
+
+    /// Changes whether a member is an org admin. Instance admins may also add users,
+    /// org admins add new members with `inviteUser`.
+    #[graphql(guard = "TokenGuard")]
+    async fn set_organization_member(
+        &self,
+        ctx: &Context<'_>,
+        organization_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<OrganizationMember> {
+        let db = database(ctx)?;
+        authorize_organization_admin(ctx, db, organization_id).await?;
+        if membership(db, organization_id, user_id).await?.is_none() {
+            // same error for unknown users, so org admins can't probe the users of the instance
+            if !is_instance_admin(db, Claim::from_ctx(ctx)?.user_id()?).await? {
+                return Err(Error::NotFound);
+            }
+            match user::Entity::find_by_id(user_id).one(db).await? {
+                Some(user) if user.deleted_at.is_none() => (),
+                _ => return Err(Error::NotFound),
+            }
+        }
+        if !is_admin {
+            ensure_other_admin(db, organization_id, user_id).await?;
+        }
+        Ok(set_member(db, organization_id, user_id, is_admin)
+            .await?
+            .into())
+    }
+
+    /// Removes a user from the organization.
+    /// Their access tokens for it stop working once they expire.
+    #[graphql(guard = "TokenGuard")]
+    async fn remove_organization_member(
+        &self,
+        ctx: &Context<'_>,
+        organization_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<bool> {
+        let db = database(ctx)?;
+        authorize_organization_admin(ctx, db, organization_id).await?;
+        ensure_other_admin(db, organization_id, user_id).await?;
+        Ok(remove_member(db, organization_id, user_id).await? > 0)
+    }
+
+    /// Scopes the current session to another organization of the user
+    /// and returns a new access token for it
+    #[graphql(guard = "TokenGuard")]
+    async fn switch_organization(
+        &self,
+        ctx: &Context<'_>,
+        organization_id: Uuid,
+    ) -> Result<LoginResult> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        if membership(db, organization_id, claim.user_id()?)
+            .await?
+            .is_none()
+        {
+            return Err(Error::NotFound);
+        }
+        switch_session_organization(db, claim.session_id()?, organization_id).await
+    }
+}
+
+async fn is_instance_admin(db: &DatabaseConnection, user_id: Uuid) -> Result<bool> {
+    let user = user::Entity::find_by_id(user_id).one(db).await?;
+    Ok(user.map(|user| user.is_admin).unwrap_or(false))
+}
+
+/// Org admins and admins of the whole instance may manage an organization
+async fn authorize_organization_admin(
+    ctx: &Context<'_>,
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+) -> Result<()> {
+    let user_id = Claim::from_ctx(ctx)?.user_id()?;
+    match membership(db, organization_id, user_id).await? {
+        Some(member) if member.is_admin => Ok(()),
+        _ if is_instance_admin(db, user_id).await? => Ok(()),
+        _ => Err(Error::Forbidden),
+    }
+}
+
+/// Fails if the user is the only org admin left
+async fn ensure_other_admin(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<()> {
+    match membership(db, organization_id, user_id).await? {
+        Some(member) if member.is_admin && count_admins(db, organization_id).await? <= 1 => {
+            Err(Error::LastOrganizationAdmin)
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,68 @@
+use async_graphql::{ComplexObject, Context, SimpleObject};
+use entity::{organization::Model, organization_member, user};
+use sea_orm::{prelude::DateTimeUtc, EntityTrait};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{api::database, errors::Result, user::model::User};
+
+#[derive(SimpleObject, Debug, Serialize, Clone)]
+pub struct Organization {
+    pub id: Uuid,
+    pub name: String,
+    /// New users that were not invited into an organization join the default one
+    pub is_default: bool,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+impl From<Model> for Organization {
+    fn from(model: Model) -> Self {
+        Self {
+            id: model.id,
+            name: model.name,
+            is_default: model.is_default,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+/// Organization of the current user, for the organization switcher
+#[derive(SimpleObject, Debug, Serialize, Clone)]
+pub struct MyOrganization {
+    pub organization: Organization,
+    pub is_admin: bool,
+    /// The access token the request was made with is scoped to this organization
+    pub current: bool,
+}
+
+#[derive(SimpleObject, Debug, Serialize, Clone)]
+#[graphql(complex)]
+pub struct OrganizationMember {
+    pub organization_id: Uuid,
+    #[graphql(visible = false)]
+    pub user_id: Uuid,
+    pub is_admin: bool,
+    pub created_at: DateTimeUtc,
+}
+
+#[ComplexObject]
+impl OrganizationMember {
+    async fn user(&self, ctx: &Context<'_>) -> Result<Option<User>> {
+        let db = database(ctx)?;
+        let model = user::Entity::find_by_id(self.user_id).one(db).await?;
+        Ok(model.map(User::from))
+    }
+}
+
+impl From<organization_member::Model> for OrganizationMember {
+    fn from(model: organization_member::Model) -> Self {
+        Self {
+            organization_id: model.organization_id,
+            user_id: model.user_id,
+            is_admin: model.is_admin,
+            created_at: model.created_at,
+        }
+    }
+}
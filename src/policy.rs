@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+
+use async_graphql::Context;
+use entity::{user, work_report};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use uuid::Uuid;
+
+use crate::{
+    api::database,
+    claim::Claim,
+    errors::{Error, Result},
+    project::{db::project_memberships, model::ProjectRole},
+    role::{db::user_permissions, model::Permission},
+    work_report::model::WorkReportVisibility,
+};
+
+/// The logged in user a resolver acts for
+#[derive(Debug, Clone)]
+pub struct Actor {
+    pub id: Uuid,
+    /// May use the admin mutations like deactivating users or handing out the admin flag.
+    /// It grants no permissions, admins get them through the `admin` role.
+    pub is_admin: bool,
+    pub permissions: Vec<Permission>,
+}
+
+impl Actor {
+    pub async fn from_ctx(ctx: &Context<'_>) -> Result<Actor> {
+        let user_id = Claim::from_ctx(ctx)?.user_id()?;
+        let db = database(ctx)?;
+        let user = user::Entity::find_by_id(user_id)
+            .one(db)
+            .await?
+            .ok_or(Error::ExpiredToken)?;
+        Ok(Actor {
+            id: user.id,
+            is_admin: user.is_admin,
+            permissions: user_permissions(db, user.id).await?,
+        })
+    }
+
+    pub fn has(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
+
+    /// Grant or revoke the admin flag of any user, including users that are just created
+    pub fn may_set_admin(&self) -> bool {
+        self.is_admin
+    }
+}
+
+/// Rules which actor may perform which action on an entity
+pub trait Policy {
+    type Action;
+
+    fn allows(&self, actor: &Actor, action: Self::Action) -> bool;
+
+    fn authorize(&self, actor: &Actor, action: Self::Action) -> Result<()> {
+        if self.allows(actor, action) {
+            Ok(())
+        } else {
+            Err(Error::Forbidden)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserAction {
+    Update,
+    /// Grant or revoke the admin flag
+    SetAdmin,
+}
+
+/// Users may update themselves, user admins may update everybody.
+/// Only admins may hand out the admin flag.
+impl Policy for user::Model {
+    type Action = UserAction;
+
+    fn allows(&self, actor: &Actor, action: UserAction) -> bool {
+        match action {
+            UserAction::Update => actor.id == self.id || actor.has(Permission::UserWrite),
+            UserAction::SetAdmin => actor.may_set_admin(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkReportAction {
+    Read,
+    Update,
+    Delete,
+}
+
+/// Work reports may be read and changed by their owner or a manager
+impl Policy for work_report::Model {
+    type Action = WorkReportAction;
+
+    fn allows(&self, actor: &Actor, action: WorkReportAction) -> bool {
+        if actor.id == self.owner_id {
+            return true;
+        }
+        match action {
+            WorkReportAction::Read => actor.has(Permission::WorkReportReadAll),
+            WorkReportAction::Update | WorkReportAction::Delete => {
+                actor.has(Permission::WorkReportWriteAll)
+            }
+        }
+    }
+}
+
+/// Projects an actor may see and book time onto
+#[derive(Debug, Clone)]
+pub enum ProjectScope {
+    /// Project managers see every project of the organization
+    All,
+    /// Projects the actor is a member of, by id with the customer id and the role
+    Member(HashMap<Uuid, (Uuid, ProjectRole)>),
+}
+
+impl ProjectScope {
+    pub async fn load(
+        db: &DatabaseConnection,
+        actor: &Actor,
+        organization_id: Uuid,
+    ) -> Result<ProjectScope> {
+        if actor.has(Permission::ProjectManage) {
+            return Ok(ProjectScope::All);
+        }
+        let memberships = project_memberships(db, organization_id, actor.id).await?;
+        Ok(ProjectScope::Member(
+            memberships
+                .into_iter()
+                .map(|(project_id, customer_id, role)| (project_id, (customer_id, role)))
+                .collect(),
+        ))
+    }
+
+    pub async fn from_ctx(ctx: &Context<'_>) -> Result<ProjectScope> {
+        let actor = Actor::from_ctx(ctx).await?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        ProjectScope::load(database(ctx)?, &actor, organization_id).await
+    }
+
+    /// `None` if all projects are visible
+    pub fn project_ids(&self) -> Option<Vec<Uuid>> {
+        match self {
+            ProjectScope::All => None,
+            ProjectScope::Member(projects) => Some(projects.keys().copied().collect()),
+        }
+    }
+
+    /// Customers with at least one visible project, `None` if all customers are visible
+    pub fn customer_ids(&self) -> Option<Vec<Uuid>> {
+        match self {
+            ProjectScope::All => None,
+            ProjectScope::Member(projects) => {
+                let mut ids = projects
+                    .values()
+                    .map(|(customer_id, _)| *customer_id)
+                    .collect::<Vec<_>>();
+                ids.sort_unstable();
+                ids.dedup();
+                Some(ids)
+            }
+        }
+    }
+
+    /// Members may see the project and book time onto it
+    pub fn has_project(&self, project_id: Uuid) -> bool {
+        match self {
+            ProjectScope::All => true,
+            ProjectScope::Member(projects) => projects.contains_key(&project_id),
+        }
+    }
+
+    pub fn has_customer(&self, customer_id: Uuid) -> bool {
+        match self {
+            ProjectScope::All => true,
+            ProjectScope::Member(projects) => projects
+                .values()
+                .any(|(customer, _)| *customer == customer_id),
+        }
+    }
+
+    /// Leaders and project managers may change the members of the project
+    pub fn leads(&self, project_id: Uuid) -> bool {
+        match self {
+            ProjectScope::All => true,
+            ProjectScope::Member(projects) => {
+                matches!(projects.get(&project_id), Some((_, ProjectRole::Leader)))
+            }
+        }
+    }
+
+    /// Whether time may be booked onto the project, or onto the customer if there is none.
+    /// The project has to belong to the customer.
+    pub fn may_book(&self, customer_id: Uuid, project_id: Option<Uuid>) -> bool {
+        match (self, project_id) {
+            (ProjectScope::All, _) => true,
+            (ProjectScope::Member(projects), Some(project_id)) => matches!(
+                projects.get(&project_id),
+                Some((customer, _)) if *customer == customer_id
+            ),
+            (ProjectScope::Member(_), None) => self.has_customer(customer_id),
+        }
+    }
+
+    /// Checks the work report policy and, for reports of other users,
+    /// that the report was booked onto a visible project or customer
+    pub fn authorize_work_report(
+        &self,
+        actor: &Actor,
+        wr: &work_report::Model,
+        action: WorkReportAction,
+    ) -> Result<()> {
+        wr.authorize(actor, action)?;
+        if wr.owner_id != actor.id && !self.may_book(wr.customer_id, wr.project_id) {
+            return Err(Error::Forbidden);
+        }
+        Ok(())
+    }
+
+    /// Filter for listings that contain work reports of other users, `None` for project managers
+    pub fn work_report_visibility(&self, viewer_id: Uuid) -> Option<WorkReportVisibility> {
+        Some(WorkReportVisibility {
+            viewer_id,
+            project_ids: self.project_ids()?,
+            customer_ids: self.customer_ids()?,
+        })
+    }
+}
+
+/// Returns the owner to filter a work report listing by, `None` lists the reports of all users.
+///
+/// Without `for_user_id` a listing by customer contains the reports of all users
+/// for managers and only the own reports for everybody else.
+pub fn work_report_list_owner(
+    actor: &Actor,
+    for_user_id: Option<Uuid>,
+    for_customer_id: Option<Uuid>,
+) -> Result<Option<Uuid>> {
+    let read_all = actor.has(Permission::WorkReportReadAll);
+    match for_user_id {
+        Some(user_id) if user_id != actor.id && !read_all => Err(Error::Forbidden),
+        Some(user_id) => Ok(Some(user_id)),
+        None if for_customer_id.is_some() && read_all => Ok(None),
+        None => Ok(Some(actor.id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn actor(permissions: Vec<Permission>) -> Actor {
+        Actor {
+            id: Uuid::new_v4(),
+            is_admin: false,
+            permissions,
+        }
+    }
+
+    /// Admin with the `admin` role, which has all permissions
+    fn admin() -> Actor {
+        Actor {
+            is_admin: true,
+            ..actor(Permission::ALL.to_vec())
+        }
+    }
+
+    fn user(id: Uuid) -> user::Model {
+        user::Model {
+            id,
+            email: "user@example.com".to_owned(),
+            email_verified_at: None,
+            password_hash: String::new(),
+            name: None,
+            avatar_filename: None,
+            is_admin: false,
+            totp_secret: None,
+            totp_enabled_at: None,
+            totp_last_used_step: None,
+            failed_login_attempts: 0,
+            locked_until: None,
+            deactivated_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+        }
+    }
+
+    fn work_report(owner_id: Uuid) -> work_report::Model {
+        work_report::Model {
+            id: Uuid::new_v4(),
+            organization_id: Uuid::new_v4(),
+            owner_id,
+            customer_id: Uuid::new_v4(),
+            project_id: None,
+            contact_id: None,
+            description: String::new(),
+            invoiced: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn user_can_update_self() {
+        let actor = actor(vec![]);
+        assert!(user(actor.id).allows(&actor, UserAction::Update));
+    }
+
+    #[test]
+    fn user_cannot_update_others() {
+        let actor = actor(vec![Permission::UserRead]);
+        let res = user(Uuid::new_v4()).authorize(&actor, UserAction::Update);
+        assert!(matches!(res, Err(Error::Forbidden)));
+    }
+
+    #[test]
+    fn user_admin_can_update_others() {
+        let target = user(Uuid::new_v4());
+        assert!(target.allows(&actor(vec![Permission::UserWrite]), UserAction::Update));
+        assert!(target.allows(&admin(), UserAction::Update));
+    }
+
+    #[test]
+    fn only_admin_can_set_admin() {
+        let actor = actor(vec![Permission::UserWrite]);
+        assert!(!user(actor.id).allows(&actor, UserAction::SetAdmin));
+        assert!(!user(Uuid::new_v4()).allows(&actor, UserAction::SetAdmin));
+        assert!(user(Uuid::new_v4()).allows(&admin(), UserAction::SetAdmin));
+    }
+
+    #[test]
+    fn owner_can_access_work_report() {
+        let actor = actor(vec![]);
+        let report = work_report(actor.id);
+        for action in [
+            WorkReportAction::Read,
+            WorkReportAction::Update,
+            WorkReportAction::Delete,
+        ] {
+            assert!(report.allows(&actor, action));
+        }
+    }
+
+    #[test]
+    fn others_cannot_access_work_report() {
+        let actor = actor(vec![
+            Permission::WorkReportRead,
+            Permission::WorkReportWrite,
+        ]);
+        let report = work_report(Uuid::new_v4());
+        for action in [
+            WorkReportAction::Read,
+            WorkReportAction::Update,
+            WorkReportAction::Delete,
+        ] {
+            assert!(matches!(
+                report.authorize(&actor, action),
+                Err(Error::Forbidden)
+            ));
+        }
+    }
+
+    #[test]
+    fn manager_can_access_work_report() {
+        let report = work_report(Uuid::new_v4());
+        let reader = actor(vec![Permission::WorkReportReadAll]);
+        assert!(report.allows(&reader, WorkReportAction::Read));
+        assert!(!report.allows(&reader, WorkReportAction::Update));
+        assert!(!report.allows(&reader, WorkReportAction::Delete));
+
+        let manager = actor(vec![Permission::WorkReportWriteAll]);
+        assert!(report.allows(&manager, WorkReportAction::Update));
+        assert!(report.allows(&manager, WorkReportAction::Delete));
+        assert!(report.allows(&admin(), WorkReportAction::Delete));
+    }
+
+    #[test]
+    fn work_report_listing_defaults_to_own_reports() {
+        let actor = actor(vec![]);
+        assert_eq!(
+            work_report_list_owner(&actor, None, None).unwrap(),
+            Some(actor.id)
+        );
+        assert_eq!(
+            work_report_list_owner(&actor, Some(actor.id), None).unwrap(),
+            Some(actor.id)
+        );
+        // listing by customer must not reveal reports of other users
+        assert_eq!(
+            work_report_list_owner(&actor, None, Some(Uuid::new_v4())).unwrap(),
+            Some(actor.id)
+        );
+    }
+
+    #[test]
+    fn work_report_listing_of_others_needs_read_all() {
+        let other = Uuid::new_v4();
+        assert!(matches!(
+            work_report_list_owner(&actor(vec![]), Some(other), None),
+            Err(Error::Forbidden)
+        ));
+
+        let manager = actor(vec![Permission::WorkReportReadAll]);
+        assert_eq!(
+            work_report_list_owner(&manager, Some(other), None).unwrap(),
+            Some(other)
+        );
+        assert_eq!(
+            work_report_list_owner(&manager, None, Some(Uuid::new_v4())).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn members_may_book_only_onto_their_projects() {
+        let customer_id = Uuid::new_v4();
+        let project_id = Uuid::new_v4();
+        let scope = ProjectScope::Member(
+            vec![(project_id, (customer_id, ProjectRole::Member))]
+                .into_iter()
+                .collect(),
+        );
+        assert!(scope.may_book(customer_id, Some(project_id)));
+        assert!(scope.may_book(customer_id, None));
+        assert!(!scope.may_book(customer_id, Some(Uuid::new_v4())));
+        assert!(!scope.may_book(Uuid::new_v4(), Some(project_id)));
+        assert!(!scope.may_book(Uuid::new_v4(), None));
+        assert!(!scope.leads(project_id));
+        assert!(ProjectScope::All.may_book(Uuid::new_v4(), Some(Uuid::new_v4())));
+    }
+
+    #[test]
+    fn reports_of_others_need_a_shared_project() {
+        let project_id = Uuid::new_v4();
+        let mut report = work_report(Uuid::new_v4());
+        let reader = actor(vec![Permission::WorkReportReadAll]);
+        let scope = ProjectScope::Member(
+            vec![(project_id, (report.customer_id, ProjectRole::Leader))]
+                .into_iter()
+                .collect(),
+        );
+        assert!(scope
+            .authorize_work_report(&reader, &report, WorkReportAction::Read)
+            .is_ok());
+
+        report.project_id = Some(Uuid::new_v4());
+        assert!(matches!(
+            scope.authorize_work_report(&reader, &report, WorkReportAction::Read),
+            Err(Error::Forbidden)
+        ));
+        assert!(ProjectScope::All
+            .authorize_work_report(&reader, &report, WorkReportAction::Read)
+            .is_ok());
+    }
+}
@@ -0,0 +1,139 @@
+use chrono::Utc;
+use entity::{
+    api_token, email_verification_token, login_event, organization_member, password_reset_token,
+    project_member, recovery_code, session, team_member, time_record, user, user_identity,
+    user_preference, user_role, work_report,
+};
+use sea_orm::{prelude::*, DatabaseConnection, Order, QueryOrder, Set, TransactionTrait};
+use uuid::Uuid;
+
+use crate::errors::Result;
+
+/// Work reports of the user in all organizations, oldest first
+pub async fn owned_work_reports(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<Vec<work_report::Model>> {
+    Ok(work_report::Entity::find()
+        .filter(work_report::Column::OwnerId.eq(user_id))
+        .order_by(work_report::Column::CreatedAt, Order::Asc)
+        .all(db)
+        .await?)
+}
+
+pub async fn stored_preferences(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<Option<user_preference::Model>> {
+    Ok(user_preference::Entity::find_by_id(user_id).one(db).await?)
+}
+
+pub async fn time_records_of_work_reports(
+    db: &DatabaseConnection,
+    work_report_ids: Vec<Uuid>,
+) -> Result<Vec<time_record::Model>> {
+    Ok(time_record::Entity::find()
+        .filter(time_record::Column::WorkReportId.is_in(work_report_ids))
+        .order_by(time_record::Column::Start, Order::Asc)
+        .all(db)
+        .await?)
+}
+
+/// Removes the personal data of the user and marks it as deleted.
+///
+/// Invoiced work reports stay with the anonymized user since customers were billed for them,
+/// all other work reports and their time records are deleted.
+/// The avatar files have to be removed with `remove_avatar_files`.
+pub async fn anonymize_user(db: &DatabaseConnection, user: user::Model) -> Result<user::Model> {
+    // stopping halfway would leave a user that can't log in but still has personal data
+    Ok(db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move {
+                let user_id = user.id;
+                let unbilled = work_report::Entity::find()
+                    .filter(work_report::Column::OwnerId.eq(user_id))
+                    .filter(work_report::Column::Invoiced.eq(false))
+                    .all(txn)
+                    .await?
+                    .into_iter()
+                    .map(|wr| wr.id)
+                    .collect::<Vec<_>>();
+                time_record::Entity::delete_many()
+                    .filter(time_record::Column::WorkReportId.is_in(unbilled.clone()))
+                    .exec(txn)
+                    .await?;
+                work_report::Entity::delete_many()
+                    .filter(work_report::Column::Id.is_in(unbilled))
+                    .exec(txn)
+                    .await?;
+
+                session::Entity::delete_many()
+                    .filter(session::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                api_token::Entity::delete_many()
+                    .filter(api_token::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                recovery_code::Entity::delete_many()
+                    .filter(recovery_code::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                password_reset_token::Entity::delete_many()
+                    .filter(password_reset_token::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                email_verification_token::Entity::delete_many()
+                    .filter(email_verification_token::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                login_event::Entity::delete_many()
+                    .filter(login_event::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                user_identity::Entity::delete_many()
+                    .filter(user_identity::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                user_preference::Entity::delete_many()
+                    .filter(user_preference::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                user_role::Entity::delete_many()
+                    .filter(user_role::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                project_member::Entity::delete_many()
+                    .filter(project_member::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                team_member::Entity::delete_many()
+                    .filter(team_member::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                organization_member::Entity::delete_many()
+                    .filter(organization_member::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+
+                let mut user: user::ActiveModel = user.into();
+                // keeps the email unique without revealing the old address
+                user.email = Set(format!("deleted-{user_id}@deleted.invalid"));
+                user.email_verified_at = Set(None);
+                // no password hash matches an empty string
+                user.password_hash = Set(String::new());
+                user.name = Set(None);
+                user.avatar_filename = Set(None);
+                user.is_admin = Set(false);
+                user.totp_secret = Set(None);
+                user.totp_enabled_at = Set(None);
+                user.totp_last_used_step = Set(None);
+                user.failed_login_attempts = Set(0);
+                user.locked_until = Set(None);
+                user.deactivated_at = Set(Some(Utc::now()));
+                user.deleted_at = Set(Some(Utc::now()));
+                user.update(txn).await
+            })
+        })
+        .await?)
+}
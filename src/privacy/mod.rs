@@ -0,0 +1,255 @@
+use std::{
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+    time::{Duration as StdDuration, SystemTime},
+};
+
+use actix_web::{http::header, web, HttpResponse};
+use async_graphql::{Context, Object};
+use chrono::{Duration, Utc};
+use log::{error, info};
+use serde::Serialize;
+use tokio::task::spawn_blocking;
+use zip::{write::FileOptions, ZipWriter};
+
+use crate::{
+    api::{database, MutationType},
+    audit::{audit, model::AuditEventKind},
+    avatar::{largest_avatar_path, remove_avatar_files},
+    claim::Claim,
+    config::CONFIG,
+    crypto::{generate_token, hash_token},
+    errors::{Error, Result},
+    guards::TokenGuard,
+    organization::db::{count_admins, organizations_of_user},
+    session::model::ClientInfo,
+    simple_broker::SimpleBroker,
+    user::{
+        db::user_by_id,
+        model::{User, UserChanged, UserPreferences},
+    },
+    validators::Password,
+};
+
+use self::{
+    db::{anonymize_user, owned_work_reports, stored_preferences, time_records_of_work_reports},
+    model::{DataExport, TimeRecordExport, UserExport, WorkReportExport},
+};
+
+mod db;
+pub mod model;
+
+const EXPORT_DIR: &str = "files/exports";
+
+#[derive(Default)]
+pub struct PrivacyMutation;
+
+#[Object]
+impl PrivacyMutation {
+    /// Collects the user record, preferences, work reports, time records and the avatar
+    /// of the current user in a ZIP archive of JSON files and returns a download link
+    #[graphql(guard = "TokenGuard")]
+    async fn export_my_data(&self, ctx: &Context<'_>) -> Result<DataExport> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        let user = user_by_id(db, claim.user_id()?)
+            .await?
+            .ok_or(Error::NotFound)?;
+
+        let work_reports = owned_work_reports(db, user.id).await?;
+        let time_records =
+            time_records_of_work_reports(db, work_reports.iter().map(|wr| wr.id).collect()).await?;
+        let preferences = stored_preferences(db, user.id).await?;
+        let avatar = user.avatar_filename.as_deref().map(largest_avatar_path);
+        let user_id = user.id;
+        let export = Export {
+            user: user.into(),
+            preferences: preferences.map(UserPreferences::from),
+            work_reports: work_reports.into_iter().map(Into::into).collect(),
+            time_records: time_records.into_iter().map(Into::into).collect(),
+            avatar,
+        };
+
+        let token = generate_token();
+        let path = export_path(&token);
+        match spawn_blocking(move || {
+            remove_expired_exports();
+            write_export(&path, export)
+        })
+        .await
+        {
+            Ok(res) => res?,
+            Err(e) => {
+                error!("failed to write data export: {e:?}");
+                return Err(Error::Unknown);
+            }
+        }
+
+        audit(
+            db,
+            AuditEventKind::DataExported,
+            &ClientInfo::from_ctx(ctx),
+            Some(user_id),
+            Some(user_id),
+            None,
+        )
+        .await?;
+        Ok(DataExport {
+            url: format!("{}/exports/{token}", CONFIG.base_url()),
+            expires_at: Utc::now() + Duration::seconds(CONFIG.data_export_lifetime),
+        })
+    }
+
+    /// Deletes the account of the current user, requires the password.
+    ///
+    /// The personal data is removed and the user anonymized, invoiced work reports are kept.
+    /// Fails if the user is the last admin of an organization.
+    #[graphql(guard = "TokenGuard")]
+    async fn delete_my_account(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(custom = "Password"))] password: String,
+    ) -> Result<bool> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        let user = user_by_id(db, claim.user_id()?)
+            .await?
+            .ok_or(Error::NotFound)?;
+        if !User::from(user.clone())
+            .is_password_correct(&password)
+            .await
+        {
+            return Err(Error::IncorrectPassword);
+        }
+        for (member, org) in organizations_of_user(db, user.id).await? {
+            if member.is_admin && count_admins(db, org.id).await? <= 1 {
+                return Err(Error::LastOrganizationAdmin);
+            }
+        }
+
+        let avatar = user.avatar_filename.clone();
+        let user = anonymize_user(db, user).await?;
+        if let Some(avatar) = avatar {
+            spawn_blocking(move || remove_avatar_files(&avatar));
+        }
+        info!("user {} deleted their account", user.id);
+        audit(
+            db,
+            AuditEventKind::AccountDeleted,
+            &ClientInfo::from_ctx(ctx),
+            Some(user.id),
+            Some(user.id),
+            None,
+        )
+        .await?;
+        SimpleBroker::publish(UserChanged {
+            mutation_type: MutationType::Deleted,
+            id: user.id,
+        });
+        Ok(true)
+    }
+}
+
+struct Export {
+    user: UserExport,
+    preferences: Option<UserPreferences>,
+    work_reports: Vec<WorkReportExport>,
+    time_records: Vec<TimeRecordExport>,
+    avatar: Option<PathBuf>,
+}
+
+/// Exports are stored by the hash of the token, so the file names don't reveal download links
+fn export_path(token: &str) -> PathBuf {
+    [EXPORT_DIR, &format!("{}.zip", hash_token(token))]
+        .iter()
+        .collect()
+}
+
+fn write_export(path: &Path, export: Export) -> Result<()> {
+    std::fs::create_dir_all(EXPORT_DIR)?;
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    write_json(&mut zip, "user.json", &export.user)?;
+    write_json(&mut zip, "preferences.json", &export.preferences)?;
+    write_json(&mut zip, "work_reports.json", &export.work_reports)?;
+    write_json(&mut zip, "time_records.json", &export.time_records)?;
+    if let Some(avatar) = export.avatar {
+        match std::fs::read(&avatar) {
+            Ok(data) => {
+                zip.start_file("avatar.png", FileOptions::default())
+                    .map_err(zip_error)?;
+                zip.write_all(&data)?;
+            }
+            Err(e) => error!("failed to add avatar {} to export: {e}", avatar.display()),
+        }
+    }
+    let data = zip.finish().map_err(zip_error)?.into_inner();
+    std::fs::write(path, data)?;
+    Ok(())
+}
+
+fn write_json<T: Serialize>(
+    zip: &mut ZipWriter<Cursor<Vec<u8>>>,
+    name: &str,
+    value: &T,
+) -> Result<()> {
+    zip.start_file(name, FileOptions::default())
+        .map_err(zip_error)?;
+    serde_json::to_writer_pretty(zip, value).map_err(|e| Error::Io(e.into()))?;
+    Ok(())
+}
+
+fn zip_error(e: zip::result::ZipError) -> Error {
+    Error::Io(e.into())
+}
+
+fn is_expired(modified: SystemTime) -> bool {
+    let lifetime = StdDuration::from_secs(CONFIG.data_export_lifetime as u64);
+    modified.elapsed().is_ok_and(|elapsed| elapsed > lifetime)
+}
+
+fn remove_expired_exports() {
+    let entries = match std::fs::read_dir(EXPORT_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let expired = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(is_expired);
+        if expired {
+            if let Err(e) = std::fs::remove_file(entry.path()) {
+                error!(
+                    "failed to remove expired export {}: {e}",
+                    entry.path().display()
+                );
+            }
+        }
+    }
+}
+
+/// Serves a data export until it expires
+pub async fn download_export(token: web::Path<String>) -> HttpResponse {
+    let path = export_path(&token);
+    let modified = tokio::fs::metadata(&path)
+        .await
+        .and_then(|metadata| metadata.modified());
+    match modified {
+        Ok(modified) if !is_expired(modified) => {}
+        _ => return HttpResponse::NotFound().finish(),
+    }
+    match tokio::fs::read(&path).await {
+        Ok(data) => HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"zorius-export.zip\"",
+            ))
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .body(data),
+        Err(e) => {
+            error!("failed to read data export: {e}");
+            HttpResponse::NotFound().finish()
+        }
+    }
+}
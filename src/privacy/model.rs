@@ -0,0 +1,88 @@
+use async_graphql::SimpleObject;
+use entity::{time_record, user, work_report};
+use sea_orm::prelude::DateTimeUtc;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Download of a data export, the link expires after `data_export_lifetime`
+#[derive(SimpleObject, Debug, Clone)]
+pub struct DataExport {
+    pub url: String,
+    pub expires_at: DateTimeUtc,
+}
+
+/// The user record without secrets like the password hash or the TOTP secret
+#[derive(Serialize, Debug)]
+pub struct UserExport {
+    pub id: Uuid,
+    pub email: String,
+    pub email_verified_at: Option<DateTimeUtc>,
+    pub name: Option<String>,
+    pub is_admin: bool,
+    pub totp_enabled_at: Option<DateTimeUtc>,
+    pub deactivated_at: Option<DateTimeUtc>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+impl From<user::Model> for UserExport {
+    fn from(model: user::Model) -> Self {
+        Self {
+            id: model.id,
+            email: model.email,
+            email_verified_at: model.email_verified_at,
+            name: model.name,
+            is_admin: model.is_admin,
+            totp_enabled_at: model.totp_enabled_at,
+            deactivated_at: model.deactivated_at,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct WorkReportExport {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub customer_id: Uuid,
+    pub project_id: Option<Uuid>,
+    pub description: String,
+    pub invoiced: bool,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+impl From<work_report::Model> for WorkReportExport {
+    fn from(model: work_report::Model) -> Self {
+        Self {
+            id: model.id,
+            organization_id: model.organization_id,
+            customer_id: model.customer_id,
+            project_id: model.project_id,
+            description: model.description,
+            invoiced: model.invoiced,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct TimeRecordExport {
+    pub id: Uuid,
+    pub work_report_id: Uuid,
+    pub start: DateTimeUtc,
+    pub end: Option<DateTimeUtc>,
+}
+
+impl From<time_record::Model> for TimeRecordExport {
+    fn from(model: time_record::Model) -> Self {
+        Self {
+            id: model.id,
+            work_report_id: model.work_report_id,
+            start: model.start,
+            end: model.end,
+        }
+    }
+}
@@ -1,51 +1,83 @@
-use entity::project::{ActiveModel, Column, Entity, Model};
+use entity::{
+    customer,
+    project::{ActiveModel, Column, Entity, Model},
+    project_member, team_member,
+};
 use migration::sea_query::{Expr, IntoCondition};
 use sea_orm::{prelude::*, Condition, DatabaseConnection, Order, QueryOrder, QuerySelect, Set};
 use uuid::Uuid;
 
-use super::model::{DbListOptions, NewProject, UpdateProject};
+use super::model::{DbListOptions, NewProject, ProjectRole, UpdateProject};
 
 pub async fn new_project(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     update: NewProject,
 ) -> Result<Option<Model>, sea_orm::error::DbErr> {
+    // the customer has to belong to the same organization and must not be trashed
+    let customer = customer::Entity::find_by_id(update.customer_id)
+        .filter(customer::Column::OrganizationId.eq(organization_id))
+        .filter(customer::Column::DeletedAt.is_null())
+        .one(db)
+        .await?;
+    if customer.is_none() {
+        return Ok(None);
+    }
     let new_project = ActiveModel {
+        organization_id: Set(organization_id),
         customer_id: Set(update.customer_id),
         name: Set(update.name),
         note: Set(update.note),
         ..Default::default()
     };
     let project_id = Entity::insert(new_project).exec(db).await?.last_insert_id;
-    project_by_id(db, project_id).await
+    project_by_id(db, organization_id, project_id).await
 }
 
 pub async fn project_by_id(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     id: uuid::Uuid,
 ) -> Result<Option<Model>, sea_orm::error::DbErr> {
-    Entity::find_by_id(id).one(db).await
+    Entity::find_by_id(id)
+        .filter(Column::OrganizationId.eq(organization_id))
+        .one(db)
+        .await
 }
 
-pub async fn count_projects(db: &DatabaseConnection) -> Result<usize, sea_orm::error::DbErr> {
-    Entity::find().count(db).await
+pub async fn count_projects(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    options: &DbListOptions,
+) -> Result<usize, sea_orm::error::DbErr> {
+    let mut entity = Entity::find().filter(Column::OrganizationId.eq(organization_id));
+    if let Some(visible_ids) = &options.visible_ids {
+        entity = entity.filter(Column::Id.is_in(visible_ids.clone()));
+    }
+    entity.count(db).await
 }
 
 pub async fn list_projects(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     options: DbListOptions,
 ) -> Result<Vec<Model>, sea_orm::error::DbErr> {
+    let mut entity = Entity::find().filter(Column::OrganizationId.eq(organization_id));
+    if let Some(visible_ids) = options.visible_ids {
+        entity = entity.filter(Column::Id.is_in(visible_ids));
+    }
     if let Some(ids) = options.ids {
         let con = ids.into_iter().fold(Condition::all(), |acc, id| {
             acc.add(Expr::col(Column::Id).eq(id)).into_condition()
         });
-        return Entity::find()
+        return entity
             .filter(con)
             .order_by(Column::CreatedAt, Order::Asc)
             .all(db)
             .await;
     }
 
-    Entity::find()
+    entity
         .offset(options.start)
         .limit(options.limit)
         .order_by(Column::CreatedAt, Order::Asc)
@@ -55,10 +87,11 @@ pub async fn list_projects(
 
 pub async fn update_project(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     id: Uuid,
     update: UpdateProject,
 ) -> Result<Option<Model>, sea_orm::error::DbErr> {
-    let mut project: ActiveModel = match project_by_id(db, id).await? {
+    let mut project: ActiveModel = match project_by_id(db, organization_id, id).await? {
         Some(project) => project.into(),
         None => return Ok(None),
     };
@@ -72,17 +105,118 @@ pub async fn update_project(
     }
     project.update(db).await?;
 
-    project_by_id(db, id).await
+    project_by_id(db, organization_id, id).await
 }
 
 pub async fn delete_project(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     id: Uuid,
 ) -> Result<u64, sea_orm::error::DbErr> {
-    let project: ActiveModel = match project_by_id(db, id).await? {
+    let project: ActiveModel = match project_by_id(db, organization_id, id).await? {
         Some(project) => project.into(),
         None => return Ok(0),
     };
     let res = Entity::delete(project).exec(db).await?;
     Ok(res.rows_affected)
 }
+
+/// Projects of the organization the user is a member of, directly or through a team,
+/// as `(project id, customer id, role)`. The highest role counts if there are several.
+pub async fn project_memberships(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<Vec<(Uuid, Uuid, ProjectRole)>, sea_orm::error::DbErr> {
+    let team_ids = team_member::Entity::find()
+        .filter(team_member::Column::UserId.eq(user_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|member| member.team_id)
+        .collect::<Vec<_>>();
+    let mut condition = Condition::any().add(project_member::Column::UserId.eq(user_id));
+    if !team_ids.is_empty() {
+        condition = condition.add(project_member::Column::TeamId.is_in(team_ids));
+    }
+    let members = project_member::Entity::find()
+        .filter(condition)
+        .find_also_related(Entity)
+        .filter(Column::OrganizationId.eq(organization_id))
+        .all(db)
+        .await?;
+
+    let mut memberships: Vec<(Uuid, Uuid, ProjectRole)> = vec![];
+    for (member, project) in members {
+        let project = match project {
+            Some(project) => project,
+            None => continue,
+        };
+        let role = member.role.parse().unwrap_or(ProjectRole::Member);
+        match memberships.iter_mut().find(|(id, _, _)| *id == project.id) {
+            Some(existing) => existing.2 = existing.2.max(role),
+            None => memberships.push((project.id, project.customer_id, role)),
+        }
+    }
+    Ok(memberships)
+}
+
+pub async fn list_project_members(
+    db: &DatabaseConnection,
+    project_id: Uuid,
+) -> Result<Vec<project_member::Model>, sea_orm::error::DbErr> {
+    project_member::Entity::find()
+        .filter(project_member::Column::ProjectId.eq(project_id))
+        .order_by(project_member::Column::CreatedAt, Order::Asc)
+        .all(db)
+        .await
+}
+
+pub async fn project_member_by_id(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<Option<project_member::Model>, sea_orm::error::DbErr> {
+    project_member::Entity::find_by_id(id).one(db).await
+}
+
+/// Adds the user or team to the project or changes the role of an existing membership
+pub async fn set_project_member(
+    db: &DatabaseConnection,
+    project_id: Uuid,
+    user_id: Option<Uuid>,
+    team_id: Option<Uuid>,
+    role: ProjectRole,
+) -> Result<project_member::Model, sea_orm::error::DbErr> {
+    let mut existing =
+        project_member::Entity::find().filter(project_member::Column::ProjectId.eq(project_id));
+    existing = match (user_id, team_id) {
+        (Some(user_id), _) => existing.filter(project_member::Column::UserId.eq(user_id)),
+        (None, Some(team_id)) => existing.filter(project_member::Column::TeamId.eq(team_id)),
+        (None, None) => return Err(DbErr::Custom("project member without user or team".into())),
+    };
+    if let Some(member) = existing.one(db).await? {
+        let mut member: project_member::ActiveModel = member.into();
+        member.role = Set(role.as_str().to_owned());
+        return member.update(db).await;
+    }
+    project_member::ActiveModel {
+        project_id: Set(project_id),
+        user_id: Set(user_id),
+        team_id: Set(team_id),
+        role: Set(role.as_str().to_owned()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
+
+pub async fn delete_project_member(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<u64, sea_orm::error::DbErr> {
+    Ok(project_member::Entity::delete_many()
+        .filter(project_member::Column::Id.eq(id))
+        .exec(db)
+        .await?
+        .rows_affected)
+}
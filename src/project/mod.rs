@@ -6,20 +6,30 @@ use futures::stream::{self, StreamExt};
 use futures_util::Stream;
 use uuid::Uuid;
 
-mod db;
+pub mod db;
 pub mod model;
 
 use crate::{
     api::{database, MutationType},
     claim::Claim,
     errors::{Error, Result},
-    guards::TokenGuard,
+    guards::PermissionGuard,
+    organization::db::membership,
+    policy::{Actor, ProjectScope},
     project::model::ProjectChanged,
+    role::model::Permission,
+    team::db::team_by_id,
 };
 
 use self::{
-    db::{count_projects, delete_project, list_projects, new_project, update_project},
-    model::{DbListOptions, ListProjectOptions, NewProject, Project, UpdateProject},
+    db::{
+        count_projects, delete_project, delete_project_member, list_project_members, list_projects,
+        new_project, project_by_id, project_member_by_id, set_project_member, update_project,
+    },
+    model::{
+        DbListOptions, ListProjectOptions, NewProject, Project, ProjectMember, ProjectRole,
+        UpdateProject,
+    },
 };
 
 use super::simple_broker::SimpleBroker;
@@ -29,19 +39,22 @@ pub struct ProjectQuery;
 
 #[Object]
 impl ProjectQuery {
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectRead)")]
     async fn projects(
         &self,
         ctx: &Context<'_>,
         options: Option<ListProjectOptions>,
     ) -> async_graphql::Result<Connection<usize, Project, EmptyFields, EmptyFields>> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
         let db = &database(ctx)?;
-        let count = count_projects(db).await? as usize;
+        let scope = ProjectScope::from_ctx(ctx).await?;
         let options = options.unwrap_or_default();
         let mut db_options = DbListOptions {
             ids: options.ids,
+            visible_ids: scope.project_ids(),
             ..Default::default()
         };
+        let count = count_projects(db, organization_id, &db_options).await? as usize;
 
         query(
             options.after,
@@ -60,7 +73,7 @@ impl ProjectQuery {
                 db_options.start = start as u64;
                 db_options.limit = end as u64;
 
-                let projects = list_projects(db, db_options).await?;
+                let projects = list_projects(db, organization_id, db_options).await?;
 
                 let mut connection = Connection::new(start > 0, end < count);
                 connection
@@ -75,6 +88,26 @@ impl ProjectQuery {
         )
         .await
     }
+
+    /// Users and teams working on the project
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectRead)")]
+    async fn project_members(
+        &self,
+        ctx: &Context<'_>,
+        project_id: Uuid,
+    ) -> Result<Vec<ProjectMember>> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        if project_by_id(db, organization_id, project_id)
+            .await?
+            .is_none()
+            || !ProjectScope::from_ctx(ctx).await?.has_project(project_id)
+        {
+            return Err(Error::NotFound);
+        }
+        let members = list_project_members(db, project_id).await?;
+        Ok(members.into_iter().map(ProjectMember::from).collect())
+    }
 }
 
 #[derive(Default)]
@@ -82,36 +115,107 @@ pub struct ProjectMutation;
 
 #[Object]
 impl ProjectMutation {
-    #[graphql(guard = "TokenGuard")]
+    /// Creates a project, the current user becomes its leader
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectWrite)")]
     async fn new_project(&self, ctx: &Context<'_>, new: NewProject) -> Result<Option<Project>> {
-        let _ = Claim::from_ctx(ctx)?;
+        let claim = Claim::from_ctx(ctx)?;
+        let organization_id = claim.organization_id()?;
         let db = &database(ctx)?;
-        if let Some(project) = new_project(db, new).await? {
+        if let Some(project) = new_project(db, organization_id, new).await? {
+            set_project_member(
+                db,
+                project.id,
+                Some(claim.user_id()?),
+                None,
+                ProjectRole::Leader,
+            )
+            .await?;
             return Ok(Some(project.into()));
         }
         Ok(None)
     }
 
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectWrite)")]
     async fn update_project(
         &self,
         ctx: &Context<'_>,
         id: Uuid,
         update: UpdateProject,
     ) -> Result<Option<Project>> {
-        let _ = Claim::from_ctx(ctx)?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
         let db = &database(ctx)?;
-        if let Some(project) = update_project(db, id, update).await? {
+        if !ProjectScope::from_ctx(ctx).await?.has_project(id) {
+            return Ok(None);
+        }
+        if let Some(project) = update_project(db, organization_id, id, update).await? {
             return Ok(Some(project.into()));
         }
         Ok(None)
     }
 
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectWrite)")]
     async fn delete_project(&self, ctx: &Context<'_>, id: Uuid) -> Result<bool> {
-        let _ = Claim::from_ctx(ctx)?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
         let db = &database(ctx)?;
-        Ok(delete_project(db, id).await? >= 1)
+        if !ProjectScope::from_ctx(ctx).await?.leads(id) {
+            return Err(Error::Forbidden);
+        }
+        Ok(delete_project(db, organization_id, id).await? >= 1)
+    }
+
+    /// Adds a user to the project or changes their role.
+    /// Only leaders of the project and project managers may change members.
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectWrite)")]
+    async fn set_project_user(
+        &self,
+        ctx: &Context<'_>,
+        project_id: Uuid,
+        user_id: Uuid,
+        role: ProjectRole,
+    ) -> Result<ProjectMember> {
+        let organization_id = authorize_project_leader(ctx, project_id).await?;
+        let db = database(ctx)?;
+        if membership(db, organization_id, user_id).await?.is_none() {
+            return Err(Error::NotFound);
+        }
+        Ok(
+            set_project_member(db, project_id, Some(user_id), None, role)
+                .await?
+                .into(),
+        )
+    }
+
+    /// Adds all members of a team to the project or changes the role of the team
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectWrite)")]
+    async fn set_project_team(
+        &self,
+        ctx: &Context<'_>,
+        project_id: Uuid,
+        team_id: Uuid,
+        role: ProjectRole,
+    ) -> Result<ProjectMember> {
+        let organization_id = authorize_project_leader(ctx, project_id).await?;
+        let db = database(ctx)?;
+        if team_by_id(db, organization_id, team_id).await?.is_none() {
+            return Err(Error::NotFound);
+        }
+        Ok(
+            set_project_member(db, project_id, None, Some(team_id), role)
+                .await?
+                .into(),
+        )
+    }
+
+    /// Removes a user or team from the project by the id of the membership
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectWrite)")]
+    async fn remove_project_member(&self, ctx: &Context<'_>, id: Uuid) -> Result<bool> {
+        let db = database(ctx)?;
+        let member = match project_member_by_id(db, id).await? {
+            Some(member) => member,
+            None => return Ok(false),
+        };
+        authorize_project_leader(ctx, member.project_id).await?;
+        Ok(delete_project_member(db, id).await? > 0)
     }
 }
 
@@ -120,7 +224,7 @@ pub struct ProjectSubscription;
 
 #[Subscription]
 impl ProjectSubscription {
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectRead)")]
     async fn projects(
         &self,
         mutation_type: Option<MutationType>,
@@ -135,3 +239,24 @@ impl ProjectSubscription {
         })
     }
 }
+
+/// Fails unless the current user leads the project or manages all projects.
+/// Returns the organization of the request.
+async fn authorize_project_leader(ctx: &Context<'_>, project_id: Uuid) -> Result<Uuid> {
+    let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+    let db = database(ctx)?;
+    if project_by_id(db, organization_id, project_id)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound);
+    }
+    let actor = Actor::from_ctx(ctx).await?;
+    if !ProjectScope::load(db, &actor, organization_id)
+        .await?
+        .leads(project_id)
+    {
+        return Err(Error::Forbidden);
+    }
+    Ok(organization_id)
+}
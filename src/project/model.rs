@@ -1,5 +1,7 @@
-use async_graphql::{ComplexObject, Context, InputObject, Object, SimpleObject};
-use entity::{customer, project::Model};
+use std::str::FromStr;
+
+use async_graphql::{ComplexObject, Context, Enum, InputObject, Object, SimpleObject};
+use entity::{customer, project::Model, project_member, team, user};
 use sea_orm::{prelude::DateTimeUtc, EntityTrait};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -7,7 +9,9 @@ use uuid::Uuid;
 use crate::{
     api::{database, MutationType},
     customer::model::Customer,
-    errors::Result,
+    errors::{Error, Result},
+    team::model::Team,
+    user::model::User,
 };
 
 #[derive(Serialize, Debug, Clone, SimpleObject)]
@@ -33,6 +37,85 @@ impl Project {
     }
 }
 
+/// Role of a user or team in a project
+#[derive(Enum, Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Serialize)]
+pub enum ProjectRole {
+    /// Books time onto the project
+    Member,
+    /// Additionally manages the members of the project
+    Leader,
+}
+
+impl ProjectRole {
+    /// Name of the role as it is stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectRole::Member => "member",
+            ProjectRole::Leader => "leader",
+        }
+    }
+}
+
+impl FromStr for ProjectRole {
+    type Err = Error;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "member" => Ok(ProjectRole::Member),
+            "leader" => Ok(ProjectRole::Leader),
+            _ => Err(Error::NotFound),
+        }
+    }
+}
+
+/// A user or a whole team working on a project
+#[derive(SimpleObject, Debug, Serialize, Clone)]
+#[graphql(complex)]
+pub struct ProjectMember {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    #[graphql(visible = false)]
+    pub user_id: Option<Uuid>,
+    #[graphql(visible = false)]
+    pub team_id: Option<Uuid>,
+    pub role: ProjectRole,
+    pub created_at: DateTimeUtc,
+}
+
+#[ComplexObject]
+impl ProjectMember {
+    async fn user(&self, ctx: &Context<'_>) -> Result<Option<User>> {
+        let db = database(ctx)?;
+        if let Some(id) = self.user_id {
+            let model = user::Entity::find_by_id(id).one(db).await?;
+            return Ok(model.map(User::from));
+        }
+        Ok(None)
+    }
+
+    async fn team(&self, ctx: &Context<'_>) -> Result<Option<Team>> {
+        let db = database(ctx)?;
+        if let Some(id) = self.team_id {
+            let model = team::Entity::find_by_id(id).one(db).await?;
+            return Ok(model.map(Team::from));
+        }
+        Ok(None)
+    }
+}
+
+impl From<project_member::Model> for ProjectMember {
+    fn from(model: project_member::Model) -> Self {
+        Self {
+            id: model.id,
+            project_id: model.project_id,
+            user_id: model.user_id,
+            team_id: model.team_id,
+            role: model.role.parse().unwrap_or(ProjectRole::Member),
+            created_at: model.created_at,
+        }
+    }
+}
+
 #[derive(Serialize, Debug, InputObject, Default)]
 pub struct ListProjectOptions {
     pub ids: Option<Vec<Uuid>>,
@@ -45,6 +128,8 @@ pub struct ListProjectOptions {
 #[derive(Debug, Default)]
 pub struct DbListOptions {
     pub ids: Option<Vec<Uuid>>,
+    /// Only these projects are listed, `None` lists all of the organization
+    pub visible_ids: Option<Vec<Uuid>>,
     pub start: u64,
     pub limit: u64,
 }
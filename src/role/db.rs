@@ -0,0 +1,222 @@
+use entity::{
+    role::{ActiveModel, Column, Entity, Model},
+    role_permission, user, user_role,
+};
+use sea_orm::{prelude::*, DatabaseConnection, Order, QueryOrder, Set};
+use uuid::Uuid;
+
+use crate::errors::Result;
+
+use super::model::{NewRole, Permission, UpdateRole};
+
+/// Name of the seeded role with all permissions
+pub const ADMIN_ROLE: &str = "admin";
+
+pub async fn list_roles(db: &DatabaseConnection) -> Result<Vec<Model>> {
+    Ok(Entity::find()
+        .order_by(Column::Name, Order::Asc)
+        .all(db)
+        .await?)
+}
+
+pub async fn role_by_id(db: &DatabaseConnection, id: Uuid) -> Result<Option<Model>> {
+    Ok(Entity::find_by_id(id).one(db).await?)
+}
+
+pub async fn role_by_name(db: &DatabaseConnection, name: &str) -> Result<Option<Model>> {
+    Ok(Entity::find().filter(Column::Name.eq(name)).one(db).await?)
+}
+
+pub async fn new_role(db: &DatabaseConnection, new: NewRole) -> Result<Model> {
+    let role = ActiveModel {
+        name: Set(new.name),
+        description: Set(new.description),
+        is_default: Set(new.is_default.unwrap_or(false)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    set_role_permissions(db, role.id, new.permissions).await?;
+    Ok(role)
+}
+
+pub async fn update_role(
+    db: &DatabaseConnection,
+    id: Uuid,
+    update: UpdateRole,
+) -> Result<Option<Model>> {
+    let mut role: ActiveModel = match role_by_id(db, id).await? {
+        Some(role) => role.into(),
+        None => return Ok(None),
+    };
+    if let Some(name) = update.name {
+        role.name = Set(name);
+    }
+    if let Some(description) = update.description {
+        role.description = Set(description);
+    }
+    if let Some(is_default) = update.is_default {
+        role.is_default = Set(is_default);
+    }
+    let role = role.update(db).await?;
+    if let Some(permissions) = update.permissions {
+        set_role_permissions(db, role.id, permissions).await?;
+    }
+    Ok(Some(role))
+}
+
+pub async fn delete_role(db: &DatabaseConnection, id: Uuid) -> Result<u64> {
+    Ok(Entity::delete_many()
+        .filter(Column::Id.eq(id))
+        .exec(db)
+        .await?
+        .rows_affected)
+}
+
+/// Replaces all permissions of the role
+pub async fn set_role_permissions(
+    db: &DatabaseConnection,
+    role_id: Uuid,
+    permissions: Vec<Permission>,
+) -> Result<()> {
+    role_permission::Entity::delete_many()
+        .filter(role_permission::Column::RoleId.eq(role_id))
+        .exec(db)
+        .await?;
+
+    let mut permissions = permissions;
+    permissions.sort_by_key(|permission| permission.as_str());
+    permissions.dedup();
+    if permissions.is_empty() {
+        return Ok(());
+    }
+    let models = permissions
+        .into_iter()
+        .map(|permission| role_permission::ActiveModel {
+            role_id: Set(role_id),
+            permission: Set(permission.as_str().to_owned()),
+        });
+    role_permission::Entity::insert_many(models)
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn role_permissions(db: &DatabaseConnection, role_id: Uuid) -> Result<Vec<Permission>> {
+    let models = role_permission::Entity::find()
+        .filter(role_permission::Column::RoleId.eq(role_id))
+        .all(db)
+        .await?;
+    // permissions that are unknown to this version of zorius are ignored
+    Ok(models
+        .into_iter()
+        .filter_map(|model| model.permission.parse().ok())
+        .collect())
+}
+
+pub async fn user_roles(db: &DatabaseConnection, user_id: Uuid) -> Result<Vec<Model>> {
+    let role_ids = user_role::Entity::find()
+        .filter(user_role::Column::UserId.eq(user_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|user_role| user_role.role_id)
+        .collect::<Vec<_>>();
+    if role_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(Entity::find()
+        .filter(Column::Id.is_in(role_ids))
+        .order_by(Column::Name, Order::Asc)
+        .all(db)
+        .await?)
+}
+
+/// All permissions the user has through its roles
+pub async fn user_permissions(db: &DatabaseConnection, user_id: Uuid) -> Result<Vec<Permission>> {
+    let role_ids = user_roles(db, user_id)
+        .await?
+        .into_iter()
+        .map(|role| role.id)
+        .collect::<Vec<_>>();
+    if role_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let models = role_permission::Entity::find()
+        .filter(role_permission::Column::RoleId.is_in(role_ids))
+        .all(db)
+        .await?;
+    let mut permissions: Vec<Permission> = models
+        .into_iter()
+        .filter_map(|model| model.permission.parse().ok())
+        .collect();
+    permissions.sort_by_key(|permission| permission.as_str());
+    permissions.dedup();
+    Ok(permissions)
+}
+
+/// Checks if the user has the permission through one of its roles.
+/// Admins get their permissions from the `admin` role like everybody else.
+pub async fn has_permission(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    permission: Permission,
+) -> Result<bool> {
+    Ok(user_permissions(db, user.id).await?.contains(&permission))
+}
+
+/// Returns `false` if the user already had the role
+pub async fn assign_role(db: &DatabaseConnection, user_id: Uuid, role_id: Uuid) -> Result<bool> {
+    let existing = user_role::Entity::find_by_id((user_id, role_id))
+        .one(db)
+        .await?;
+    if existing.is_some() {
+        return Ok(false);
+    }
+    user_role::ActiveModel {
+        user_id: Set(user_id),
+        role_id: Set(role_id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(true)
+}
+
+pub async fn unassign_role(db: &DatabaseConnection, user_id: Uuid, role_id: Uuid) -> Result<bool> {
+    let res = user_role::Entity::delete_many()
+        .filter(user_role::Column::UserId.eq(user_id))
+        .filter(user_role::Column::RoleId.eq(role_id))
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected > 0)
+}
+
+/// Assigns the `admin` role when the user becomes admin and removes it when the user stops being one
+pub async fn sync_admin_role(db: &DatabaseConnection, user_id: Uuid, is_admin: bool) -> Result<()> {
+    let role = match Entity::find()
+        .filter(Column::Name.eq(ADMIN_ROLE))
+        .one(db)
+        .await?
+    {
+        Some(role) => role,
+        None => return Ok(()),
+    };
+    if is_admin {
+        assign_role(db, user_id, role.id).await?;
+    } else {
+        unassign_role(db, user_id, role.id).await?;
+    }
+    Ok(())
+}
+
+pub async fn assign_default_roles(db: &DatabaseConnection, user_id: Uuid) -> Result<()> {
+    let roles = Entity::find()
+        .filter(Column::IsDefault.eq(true))
+        .all(db)
+        .await?;
+    for role in roles {
+        assign_role(db, user_id, role.id).await?;
+    }
+    Ok(())
+}
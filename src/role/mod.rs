@@ -0,0 +1,99 @@
+use async_graphql::{Context, Object};
+use sea_orm::EntityTrait;
+use uuid::Uuid;
+
+use crate::{
+    api::database,
+    errors::{Error, Result},
+    guards::{PermissionGuard, TokenGuard},
+    user::model::User,
+};
+
+use self::{
+    db::{
+        assign_role, delete_role, list_roles, new_role, role_by_id, role_by_name, unassign_role,
+        update_role,
+    },
+    model::{NewRole, Permission, Role, UpdateRole},
+};
+
+pub mod db;
+pub mod model;
+
+#[derive(Default)]
+pub struct RoleQuery;
+
+#[Object]
+impl RoleQuery {
+    #[graphql(guard = "PermissionGuard::new(Permission::RoleManage)")]
+    async fn roles(&self, ctx: &Context<'_>) -> Result<Vec<Role>> {
+        let db = database(ctx)?;
+        let roles = list_roles(db).await?;
+        Ok(roles.into_iter().map(Role::from).collect())
+    }
+
+    /// Lists all permissions that can be granted to a role
+    #[graphql(guard = "TokenGuard")]
+    async fn permissions(&self) -> Vec<Permission> {
+        Permission::ALL.to_vec()
+    }
+}
+
+#[derive(Default)]
+pub struct RoleMutation;
+
+#[Object]
+impl RoleMutation {
+    #[graphql(guard = "PermissionGuard::new(Permission::RoleManage)")]
+    async fn new_role(&self, ctx: &Context<'_>, new: NewRole) -> Result<Role> {
+        let db = database(ctx)?;
+        if role_by_name(db, &new.name).await?.is_some() {
+            return Err(Error::RoleNameTaken);
+        }
+        Ok(new_role(db, new).await?.into())
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::RoleManage)")]
+    async fn update_role(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        update: UpdateRole,
+    ) -> Result<Option<Role>> {
+        let db = database(ctx)?;
+        if let Some(name) = &update.name {
+            if let Some(role) = role_by_name(db, name).await? {
+                if role.id != id {
+                    return Err(Error::RoleNameTaken);
+                }
+            }
+        }
+        Ok(update_role(db, id, update).await?.map(Role::from))
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::RoleManage)")]
+    async fn delete_role(&self, ctx: &Context<'_>, id: Uuid) -> Result<bool> {
+        let db = database(ctx)?;
+        Ok(delete_role(db, id).await? >= 1)
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::RoleManage)")]
+    async fn assign_role(&self, ctx: &Context<'_>, user_id: Uuid, role_id: Uuid) -> Result<User> {
+        let db = database(ctx)?;
+        let user = entity::user::Entity::find_by_id(user_id)
+            .one(db)
+            .await?
+            .ok_or(Error::NotFound)?;
+        if role_by_id(db, role_id).await?.is_none() {
+            return Err(Error::NotFound);
+        }
+        assign_role(db, user_id, role_id).await?;
+        Ok(user.into())
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::RoleManage)")]
+    async fn unassign_role(&self, ctx: &Context<'_>, user_id: Uuid, role_id: Uuid) -> Result<bool> {
+        let db = database(ctx)?;
+        unassign_role(db, user_id, role_id).await
+    }
+}
@@ -0,0 +1,136 @@
+use std::str::FromStr;
+
+use async_graphql::{ComplexObject, Context, Enum, InputObject, SimpleObject};
+use entity::role::Model;
+use sea_orm::prelude::DateTimeUtc;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    api::database,
+    errors::{Error, Result},
+};
+
+use super::db::role_permissions;
+
+/// Named permission that can be granted to a role
+#[derive(Enum, Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+pub enum Permission {
+    CustomerRead,
+    CustomerWrite,
+    ProjectRead,
+    ProjectWrite,
+    /// See all customers and projects, manage teams and project members
+    ProjectManage,
+    /// Read and edit your own work reports
+    WorkReportRead,
+    WorkReportWrite,
+    /// Read work reports of other users
+    WorkReportReadAll,
+    /// Edit and delete work reports of other users
+    WorkReportWriteAll,
+    UserRead,
+    /// Edit other users
+    UserWrite,
+    /// Create, edit and assign roles
+    RoleManage,
+    MerchandiseApprove,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 13] = [
+        Permission::CustomerRead,
+        Permission::CustomerWrite,
+        Permission::ProjectRead,
+        Permission::ProjectWrite,
+        Permission::ProjectManage,
+        Permission::WorkReportRead,
+        Permission::WorkReportWrite,
+        Permission::WorkReportReadAll,
+        Permission::WorkReportWriteAll,
+        Permission::UserRead,
+        Permission::UserWrite,
+        Permission::RoleManage,
+        Permission::MerchandiseApprove,
+    ];
+
+    /// Name of the permission as it is stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::CustomerRead => "customer:read",
+            Permission::CustomerWrite => "customer:write",
+            Permission::ProjectRead => "project:read",
+            Permission::ProjectWrite => "project:write",
+            Permission::ProjectManage => "project:manage",
+            Permission::WorkReportRead => "work_report:read",
+            Permission::WorkReportWrite => "work_report:write",
+            Permission::WorkReportReadAll => "work_report:read_all",
+            Permission::WorkReportWriteAll => "work_report:write_all",
+            Permission::UserRead => "user:read",
+            Permission::UserWrite => "user:write",
+            Permission::RoleManage => "role:manage",
+            Permission::MerchandiseApprove => "merchandise:approve",
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = Error;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        Permission::ALL
+            .iter()
+            .find(|permission| permission.as_str() == value)
+            .copied()
+            .ok_or(Error::NotFound)
+    }
+}
+
+#[derive(SimpleObject, Debug, Serialize, Clone)]
+#[graphql(complex)]
+pub struct Role {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_default: bool,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[ComplexObject]
+impl Role {
+    async fn permissions(&self, ctx: &Context<'_>) -> Result<Vec<Permission>> {
+        let db = database(ctx)?;
+        role_permissions(db, self.id).await
+    }
+}
+
+impl From<Model> for Role {
+    fn from(model: Model) -> Self {
+        Self {
+            id: model.id,
+            name: model.name,
+            description: model.description,
+            is_default: model.is_default,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, InputObject)]
+pub struct NewRole {
+    pub name: String,
+    pub description: Option<String>,
+    pub is_default: Option<bool>,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Serialize, Debug, InputObject)]
+pub struct UpdateRole {
+    pub name: Option<String>,
+    pub description: Option<Option<String>>,
+    pub is_default: Option<bool>,
+    /// Replaces all permissions of the role
+    pub permissions: Option<Vec<Permission>>,
+}
@@ -0,0 +1,111 @@
+use chrono::Utc;
+use entity::session::{ActiveModel, Column, Entity, Model};
+use migration::sea_query::Expr;
+use sea_orm::{prelude::*, DatabaseConnection, DbErr, Order, QueryOrder, Set};
+use uuid::Uuid;
+
+use super::model::{ClientInfo, RefreshToken};
+
+pub async fn new_session(
+    db: &DatabaseConnection,
+    refresh_token: &RefreshToken,
+    user_id: Uuid,
+    expires_at: DateTimeUtc,
+    client: ClientInfo,
+    impersonator_id: Option<Uuid>,
+    organization_id: Option<Uuid>,
+) -> Result<Model, DbErr> {
+    let new_session = ActiveModel {
+        id: Set(refresh_token.session_id),
+        user_id: Set(user_id),
+        refresh_token_hash: Set(refresh_token.hash()),
+        expires_at: Set(expires_at),
+        user_agent: Set(client.user_agent),
+        ip: Set(client.ip),
+        impersonator_id: Set(impersonator_id),
+        organization_id: Set(organization_id),
+        ..Default::default()
+    };
+    new_session.insert(db).await
+}
+
+pub async fn session_by_id(db: &DatabaseConnection, id: Uuid) -> Result<Option<Model>, DbErr> {
+    Entity::find_by_id(id).one(db).await
+}
+
+/// Returns the session only if it is neither revoked nor expired.
+pub async fn active_session_by_id(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<Option<Model>, DbErr> {
+    Entity::find_by_id(id)
+        .filter(Column::RevokedAt.is_null())
+        .filter(Column::ExpiresAt.gt(Utc::now()))
+        .one(db)
+        .await
+}
+
+pub async fn list_active_sessions(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<Vec<Model>, DbErr> {
+    Entity::find()
+        .filter(Column::UserId.eq(user_id))
+        .filter(Column::RevokedAt.is_null())
+        .filter(Column::ExpiresAt.gt(Utc::now()))
+        .order_by(Column::LastUsedAt, Order::Desc)
+        .all(db)
+        .await
+}
+
+/// Replaces the refresh token if the session still has `old_hash`. Returns `None` if another
+/// request rotated it in the meantime, so only one of two concurrent refreshes succeeds.
+pub async fn rotate_refresh_token(
+    db: &DatabaseConnection,
+    session: Model,
+    old_hash: &str,
+    refresh_token_hash: String,
+    expires_at: DateTimeUtc,
+) -> Result<Option<Model>, DbErr> {
+    let res = Entity::update_many()
+        .col_expr(Column::RefreshTokenHash, Expr::value(refresh_token_hash))
+        .col_expr(Column::ExpiresAt, Expr::value(expires_at))
+        .col_expr(Column::LastUsedAt, Expr::value(Utc::now()))
+        .col_expr(Column::UpdatedAt, Expr::value(Utc::now()))
+        .filter(Column::Id.eq(session.id))
+        .filter(Column::RefreshTokenHash.eq(old_hash))
+        .exec(db)
+        .await?;
+    if res.rows_affected == 0 {
+        return Ok(None);
+    }
+    Entity::find_by_id(session.id).one(db).await
+}
+
+pub async fn set_session_organization(
+    db: &DatabaseConnection,
+    session: Model,
+    organization_id: Option<Uuid>,
+) -> Result<Model, DbErr> {
+    let mut session: ActiveModel = session.into();
+    session.organization_id = Set(organization_id);
+    session.update(db).await
+}
+
+pub async fn revoke_session(db: &DatabaseConnection, session: Model) -> Result<Model, DbErr> {
+    let mut session: ActiveModel = session.into();
+    session.revoked_at = Set(Some(Utc::now()));
+    session.update(db).await
+}
+
+/// Revokes all still active sessions of the user and returns how many were revoked.
+pub async fn revoke_user_sessions(db: &DatabaseConnection, user_id: Uuid) -> Result<u64, DbErr> {
+    let res = Entity::update_many()
+        .col_expr(Column::RevokedAt, Expr::value(Utc::now()))
+        .col_expr(Column::UpdatedAt, Expr::value(Utc::now()))
+        .filter(Column::UserId.eq(user_id))
+        .filter(Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected)
+}
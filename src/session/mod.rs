@@ -0,0 +1,305 @@
+use std::convert::TryInto;
+
+use async_graphql::{Context, Object};
+use chrono::{Duration, Utc};
+use entity::{session, user};
+use log::warn;
+use sea_orm::EntityTrait;
+use uuid::Uuid;
+
+use crate::{
+    api::database,
+    audit::{audit, model::AuditEventKind},
+    claim::Claim,
+    config::CONFIG,
+    errors::{Error, Result},
+    guards::TokenGuard,
+    organization::db::{first_organization_of_user, membership},
+    user::model::{ensure_active, LoginResult},
+};
+
+use self::{
+    db::{
+        active_session_by_id, list_active_sessions, new_session, revoke_session,
+        revoke_user_sessions, rotate_refresh_token, session_by_id, set_session_organization,
+    },
+    model::{ClientInfo, RefreshToken, Session},
+};
+
+pub mod db;
+pub mod model;
+
+#[derive(Default)]
+pub struct SessionQuery;
+
+#[Object]
+impl SessionQuery {
+    /// Lists all active sessions of the current user
+    #[graphql(guard = "TokenGuard")]
+    async fn my_sessions(&self, ctx: &Context<'_>) -> Result<Vec<Session>> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        let current_session_id = claim.session_id()?;
+        let sessions = list_active_sessions(db, claim.user_id()?).await?;
+
+        Ok(sessions
+            .into_iter()
+            .map(|session| Session::new(session, current_session_id))
+            .collect())
+    }
+}
+
+#[derive(Default)]
+pub struct SessionMutation;
+
+#[Object]
+impl SessionMutation {
+    /// Exchanges a refresh token for a new access token.
+    ///
+    /// The refresh token is rotated, the old one can not be used again.
+    async fn refresh_token(&self, ctx: &Context<'_>, refresh_token: String) -> Result<LoginResult> {
+        let db = database(ctx)?;
+        let refresh_token = refresh_token.parse::<RefreshToken>()?;
+
+        let session = match session_by_id(db, refresh_token.session_id).await? {
+            None => return Err(Error::ExpiredToken),
+            Some(session) => session,
+        };
+        if session.revoked_at.is_some() {
+            return Err(Error::RevokedSession);
+        }
+        if session.expires_at <= Utc::now() {
+            return Err(Error::ExpiredToken);
+        }
+        if session.refresh_token_hash != refresh_token.hash() {
+            // an already rotated refresh token was used, assume it got stolen
+            warn!(
+                "refresh token reuse detected, revoking session {}",
+                session.id
+            );
+            revoke_session(db, session).await?;
+            return Err(Error::RevokedSession);
+        }
+
+        let user = match user::Entity::find_by_id(session.user_id).one(db).await? {
+            None => return Err(Error::NotFound),
+            Some(user) => user,
+        };
+        ensure_active(&user)?;
+
+        // the user may have been removed from the organization since the last refresh
+        let organization_id = match session.organization_id {
+            Some(org) if membership(db, org, user.id).await?.is_some() => Some(org),
+            _ => first_organization_of_user(db, user.id).await?,
+        };
+        let session = if organization_id != session.organization_id {
+            set_session_organization(db, session, organization_id).await?
+        } else {
+            session
+        };
+
+        let old_hash = refresh_token.hash();
+        let refresh_token = RefreshToken::new(session.id);
+        let session = match rotate_refresh_token(
+            db,
+            session.clone(),
+            &old_hash,
+            refresh_token.hash(),
+            refresh_token_expiry(),
+        )
+        .await?
+        {
+            Some(session) => session,
+            None => {
+                // a concurrent request with the same refresh token rotated it first
+                warn!(
+                    "refresh token reuse detected, revoking session {}",
+                    session.id
+                );
+                revoke_session(db, session).await?;
+                return Err(Error::RevokedSession);
+            }
+        };
+
+        Ok(LoginResult {
+            token: Some(access_token(&user.email, user.id, &session)?),
+            refresh_token: Some(refresh_token.to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// Ends the session the request was made with
+    #[graphql(guard = "TokenGuard")]
+    async fn logout(&self, ctx: &Context<'_>) -> Result<bool> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        if let Some(session) = session_by_id(db, claim.session_id()?).await? {
+            let session = revoke_session(db, session).await?;
+            audit(
+                db,
+                AuditEventKind::TokenRevoked,
+                &ClientInfo::from_ctx(ctx),
+                Some(session.user_id),
+                Some(session.user_id),
+                Some(format!("session {} (logout)", session.id)),
+            )
+            .await?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Ends all sessions of the current user, including the current one.
+    /// Returns the number of ended sessions.
+    #[graphql(guard = "TokenGuard")]
+    async fn logout_all_sessions(&self, ctx: &Context<'_>) -> Result<u64> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        let user_id = claim.user_id()?;
+        let revoked = revoke_user_sessions(db, user_id).await?;
+        audit(
+            db,
+            AuditEventKind::TokenRevoked,
+            &ClientInfo::from_ctx(ctx),
+            Some(user_id),
+            Some(user_id),
+            Some(format!("all sessions ({})", revoked)),
+        )
+        .await?;
+        Ok(revoked)
+    }
+
+    /// Ends one session of the current user
+    #[graphql(guard = "TokenGuard")]
+    async fn revoke_session(&self, ctx: &Context<'_>, id: Uuid) -> Result<bool> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        match session_by_id(db, id).await? {
+            Some(session) if session.user_id == claim.user_id()? => {
+                if session.revoked_at.is_none() {
+                    let session = revoke_session(db, session).await?;
+                    audit(
+                        db,
+                        AuditEventKind::TokenRevoked,
+                        &ClientInfo::from_ctx(ctx),
+                        Some(session.user_id),
+                        Some(session.user_id),
+                        Some(format!("session {}", session.id)),
+                    )
+                    .await?;
+                }
+                Ok(true)
+            }
+            _ => Err(Error::NotFound),
+        }
+    }
+}
+
+/// Creates a new session for the user and returns the first access and refresh token.
+pub async fn start_session(
+    db: &sea_orm::DatabaseConnection,
+    user_id: Uuid,
+    email: &str,
+    client: ClientInfo,
+) -> Result<LoginResult> {
+    audit(
+        db,
+        AuditEventKind::LoginSucceeded,
+        &client,
+        Some(user_id),
+        Some(user_id),
+        None,
+    )
+    .await?;
+    let refresh_token = RefreshToken::new(Uuid::new_v4());
+    let session = new_session(
+        db,
+        &refresh_token,
+        user_id,
+        refresh_token_expiry(),
+        client,
+        None,
+        first_organization_of_user(db, user_id).await?,
+    )
+    .await?;
+
+    Ok(LoginResult {
+        token: Some(access_token(email, user_id, &session)?),
+        refresh_token: Some(refresh_token.to_string()),
+        ..Default::default()
+    })
+}
+
+/// Creates a session for an admin to act as the user.
+/// It has no refresh token and ends when its only access token expires.
+pub async fn start_impersonation_session(
+    db: &sea_orm::DatabaseConnection,
+    user: &user::Model,
+    impersonator_id: Uuid,
+    client: ClientInfo,
+) -> Result<LoginResult> {
+    audit(
+        db,
+        AuditEventKind::LoginSucceeded,
+        &client,
+        Some(impersonator_id),
+        Some(user.id),
+        Some("impersonation".to_owned()),
+    )
+    .await?;
+    // the refresh token is never handed out
+    let refresh_token = RefreshToken::new(Uuid::new_v4());
+    let session = new_session(
+        db,
+        &refresh_token,
+        user.id,
+        Utc::now() + Duration::seconds(CONFIG.token_lifetime),
+        client,
+        Some(impersonator_id),
+        first_organization_of_user(db, user.id).await?,
+    )
+    .await?;
+
+    Ok(LoginResult {
+        token: Some(access_token(&user.email, user.id, &session)?),
+        ..Default::default()
+    })
+}
+
+/// Scopes the session to another organization and returns an access token for it.
+/// The refresh token stays valid and hands out tokens for the new organization.
+pub async fn switch_session_organization(
+    db: &sea_orm::DatabaseConnection,
+    session_id: Uuid,
+    organization_id: Uuid,
+) -> Result<LoginResult> {
+    let session = match active_session_by_id(db, session_id).await? {
+        None => return Err(Error::RevokedSession),
+        Some(session) => session,
+    };
+    let user = match user::Entity::find_by_id(session.user_id).one(db).await? {
+        None => return Err(Error::NotFound),
+        Some(user) => user,
+    };
+    let session = set_session_organization(db, session, Some(organization_id)).await?;
+
+    Ok(LoginResult {
+        token: Some(access_token(&user.email, user.id, &session)?),
+        ..Default::default()
+    })
+}
+
+fn access_token(email: &str, user_id: Uuid, session: &session::Model) -> Result<String> {
+    let claim = Claim::new(
+        email,
+        &user_id.to_string(),
+        &session.id.to_string(),
+        session.organization_id,
+        (Utc::now() + Duration::seconds(CONFIG.token_lifetime)).timestamp() as usize,
+    );
+    Ok(claim.try_into()?)
+}
+
+fn refresh_token_expiry() -> chrono::DateTime<Utc> {
+    Utc::now() + Duration::seconds(CONFIG.refresh_token_lifetime)
+}
@@ -0,0 +1,146 @@
+use std::{fmt, net::IpAddr, str::FromStr};
+
+use actix_web::{dev::ConnectionInfo, http::header, HttpMessage, HttpRequest};
+use async_graphql::{Context, SimpleObject};
+use entity::session::Model;
+use sea_orm::prelude::DateTimeUtc;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    api::{RequestId, UserAgent},
+    config::CONFIG,
+    crypto::{generate_token, hash_token},
+    errors::Error,
+};
+
+#[derive(SimpleObject, Debug, Serialize, Clone)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    /// `true` if this is the session the request was made with
+    pub current: bool,
+    pub created_at: DateTimeUtc,
+    pub last_used_at: Option<DateTimeUtc>,
+    pub expires_at: DateTimeUtc,
+    /// Admin that used this session to act as the user
+    pub impersonator_id: Option<Uuid>,
+}
+
+impl Session {
+    pub fn new(model: Model, current_session_id: Uuid) -> Self {
+        Self {
+            id: model.id,
+            user_agent: model.user_agent,
+            ip: model.ip,
+            current: model.id == current_session_id,
+            created_at: model.created_at,
+            last_used_at: model.last_used_at,
+            expires_at: model.expires_at,
+            impersonator_id: model.impersonator_id,
+        }
+    }
+}
+
+/// Information about the client which is stored with a new session and audit events.
+#[derive(Debug, Default, Clone)]
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl ClientInfo {
+    /// Gets the client information from the async_graphql context
+    pub fn from_ctx(ctx: &Context<'_>) -> Self {
+        let ip = ctx.data_opt::<ConnectionInfo>().and_then(client_ip);
+        let user_agent = ctx
+            .data_opt::<UserAgent>()
+            .map(|user_agent| user_agent.0.clone());
+        let request_id = ctx
+            .data_opt::<RequestId>()
+            .map(|request_id| request_id.0.clone());
+        Self {
+            ip,
+            user_agent,
+            request_id,
+        }
+    }
+
+    /// Gets the client information from a plain http request
+    pub fn from_request(request: &HttpRequest) -> Self {
+        let ip = client_ip(&request.connection_info());
+        let user_agent = request
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let request_id = request
+            .extensions()
+            .get::<RequestId>()
+            .map(|request_id| request_id.0.clone());
+        Self {
+            ip,
+            user_agent,
+            request_id,
+        }
+    }
+}
+
+/// Address of the peer, or the client address of the forwarding headers if the peer is a
+/// trusted proxy. Other clients could put any address into these headers.
+fn client_ip(info: &ConnectionInfo) -> Option<String> {
+    let peer = info.peer_addr()?;
+    let trusted = peer
+        .parse::<IpAddr>()
+        .is_ok_and(|ip| CONFIG.web.trusted_proxies.contains(&ip));
+    if trusted {
+        info.realip_remote_addr().map(str::to_owned)
+    } else {
+        Some(peer.to_owned())
+    }
+}
+
+/// Refresh token handed out to the client.
+///
+/// It is formated as `<session id>.<secret>`, only the hash of the secret is stored.
+pub struct RefreshToken {
+    pub session_id: Uuid,
+    secret: String,
+}
+
+impl RefreshToken {
+    pub fn new(session_id: Uuid) -> Self {
+        Self {
+            session_id,
+            secret: generate_token(),
+        }
+    }
+
+    pub fn hash(&self) -> String {
+        hash_token(&self.secret)
+    }
+}
+
+impl fmt::Display for RefreshToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.session_id, self.secret)
+    }
+}
+
+impl FromStr for RefreshToken {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (session_id, secret) = value.split_once('.').ok_or(Error::MalformedToken)?;
+        let session_id = Uuid::parse_str(session_id).map_err(|_| Error::MalformedToken)?;
+        if secret.is_empty() {
+            return Err(Error::MalformedToken);
+        }
+        Ok(Self {
+            session_id,
+            secret: secret.to_owned(),
+        })
+    }
+}
@@ -0,0 +1,108 @@
+use entity::{
+    team::{ActiveModel, Column, Entity, Model},
+    team_member, user,
+};
+use sea_orm::{prelude::*, DatabaseConnection, Order, QueryOrder, Set};
+use uuid::Uuid;
+
+use crate::errors::Result;
+
+pub async fn list_teams(db: &DatabaseConnection, organization_id: Uuid) -> Result<Vec<Model>> {
+    Ok(Entity::find()
+        .filter(Column::OrganizationId.eq(organization_id))
+        .order_by(Column::Name, Order::Asc)
+        .all(db)
+        .await?)
+}
+
+pub async fn team_by_id(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    id: Uuid,
+) -> Result<Option<Model>> {
+    Ok(Entity::find_by_id(id)
+        .filter(Column::OrganizationId.eq(organization_id))
+        .one(db)
+        .await?)
+}
+
+pub async fn team_by_name(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    name: &str,
+) -> Result<Option<Model>> {
+    Ok(Entity::find()
+        .filter(Column::OrganizationId.eq(organization_id))
+        .filter(Column::Name.eq(name))
+        .one(db)
+        .await?)
+}
+
+pub async fn new_team(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    name: String,
+) -> Result<Model> {
+    Ok(ActiveModel {
+        organization_id: Set(organization_id),
+        name: Set(name),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?)
+}
+
+pub async fn rename_team(db: &DatabaseConnection, team: Model, name: String) -> Result<Model> {
+    let mut team: ActiveModel = team.into();
+    team.name = Set(name);
+    Ok(team.update(db).await?)
+}
+
+pub async fn delete_team(db: &DatabaseConnection, team: Model) -> Result<u64> {
+    Ok(team.delete(db).await?.rows_affected)
+}
+
+pub async fn team_users(db: &DatabaseConnection, team_id: Uuid) -> Result<Vec<user::Model>> {
+    Ok(user::Entity::find()
+        .inner_join(team_member::Entity)
+        .filter(team_member::Column::TeamId.eq(team_id))
+        .order_by(user::Column::Name, Order::Asc)
+        .all(db)
+        .await?)
+}
+
+/// Returns `false` if the user already was a member
+pub async fn add_team_member(
+    db: &DatabaseConnection,
+    team_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool> {
+    if team_member::Entity::find_by_id((team_id, user_id))
+        .one(db)
+        .await?
+        .is_some()
+    {
+        return Ok(false);
+    }
+    team_member::ActiveModel {
+        team_id: Set(team_id),
+        user_id: Set(user_id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(true)
+}
+
+pub async fn remove_team_member(
+    db: &DatabaseConnection,
+    team_id: Uuid,
+    user_id: Uuid,
+) -> Result<u64> {
+    Ok(team_member::Entity::delete_many()
+        .filter(team_member::Column::TeamId.eq(team_id))
+        .filter(team_member::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?
+        .rows_affected)
+}
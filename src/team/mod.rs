@@ -0,0 +1,111 @@
+use async_graphql::{Context, Object};
+use uuid::Uuid;
+
+use crate::{
+    api::database,
+    claim::Claim,
+    errors::{Error, Result},
+    guards::PermissionGuard,
+    organization::db::membership,
+    role::model::Permission,
+};
+
+use self::{
+    db::{
+        add_team_member, delete_team, list_teams, new_team, remove_team_member, rename_team,
+        team_by_id, team_by_name,
+    },
+    model::Team,
+};
+
+pub mod db;
+pub mod model;
+
+#[derive(Default)]
+pub struct TeamQuery;
+
+#[Object]
+impl TeamQuery {
+    /// Teams of the current organization
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectRead)")]
+    async fn teams(&self, ctx: &Context<'_>) -> Result<Vec<Team>> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        let teams = list_teams(db, organization_id).await?;
+        Ok(teams.into_iter().map(Team::from).collect())
+    }
+}
+
+#[derive(Default)]
+pub struct TeamMutation;
+
+#[Object]
+impl TeamMutation {
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectManage)")]
+    async fn new_team(&self, ctx: &Context<'_>, name: String) -> Result<Team> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        if team_by_name(db, organization_id, &name).await?.is_some() {
+            return Err(Error::TeamNameTaken);
+        }
+        Ok(new_team(db, organization_id, name).await?.into())
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectManage)")]
+    async fn rename_team(&self, ctx: &Context<'_>, id: Uuid, name: String) -> Result<Option<Team>> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        let team = match team_by_id(db, organization_id, id).await? {
+            Some(team) => team,
+            None => return Ok(None),
+        };
+        if team.name != name && team_by_name(db, organization_id, &name).await?.is_some() {
+            return Err(Error::TeamNameTaken);
+        }
+        Ok(Some(rename_team(db, team, name).await?.into()))
+    }
+
+    /// Deleting a team also ends its project memberships
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectManage)")]
+    async fn delete_team(&self, ctx: &Context<'_>, id: Uuid) -> Result<bool> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        match team_by_id(db, organization_id, id).await? {
+            Some(team) => Ok(delete_team(db, team).await? > 0),
+            None => Ok(false),
+        }
+    }
+
+    /// Returns `false` if the user already was a member of the team
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectManage)")]
+    async fn add_team_member(
+        &self,
+        ctx: &Context<'_>,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<bool> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        if team_by_id(db, organization_id, team_id).await?.is_none()
+            || membership(db, organization_id, user_id).await?.is_none()
+        {
+            return Err(Error::NotFound);
+        }
+        add_team_member(db, team_id, user_id).await
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::ProjectManage)")]
+    async fn remove_team_member(
+        &self,
+        ctx: &Context<'_>,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<bool> {
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        if team_by_id(db, organization_id, team_id).await?.is_none() {
+            return Err(Error::NotFound);
+        }
+        Ok(remove_team_member(db, team_id, user_id).await? > 0)
+    }
+}
@@ -0,0 +1,38 @@
+use async_graphql::{ComplexObject, Context, SimpleObject};
+use entity::team::Model;
+use sea_orm::prelude::DateTimeUtc;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{api::database, errors::Result, user::model::User};
+
+use super::db::team_users;
+
+#[derive(SimpleObject, Debug, Serialize, Clone)]
+#[graphql(complex)]
+pub struct Team {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[ComplexObject]
+impl Team {
+    async fn members(&self, ctx: &Context<'_>) -> Result<Vec<User>> {
+        let db = database(ctx)?;
+        let users = team_users(db, self.id).await?;
+        Ok(users.into_iter().map(User::from).collect())
+    }
+}
+
+impl From<Model> for Team {
+    fn from(model: Model) -> Self {
+        Self {
+            id: model.id,
+            name: model.name,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::Utc;
+use entity::{
+    recovery_code,
+    user::{ActiveModel, Column, Entity, Model},
+};
+use migration::sea_query::Expr;
+use sea_orm::{prelude::*, Condition, DatabaseConnection, DbErr, Set};
+use uuid::Uuid;
+
+/// Stores a new TOTP secret, TOTP stays disabled until it is confirmed.
+pub async fn set_totp_secret(
+    db: &DatabaseConnection,
+    user: Model,
+    secret: String,
+) -> Result<Model, DbErr> {
+    let mut user: ActiveModel = user.into();
+    user.totp_secret = Set(Some(secret));
+    user.totp_enabled_at = Set(None);
+    user.totp_last_used_step = Set(None);
+    user.update(db).await
+}
+
+pub async fn enable_totp(db: &DatabaseConnection, user: Model, step: i64) -> Result<Model, DbErr> {
+    let mut user: ActiveModel = user.into();
+    user.totp_enabled_at = Set(Some(Utc::now()));
+    user.totp_last_used_step = Set(Some(step));
+    user.update(db).await
+}
+
+/// Disables TOTP and removes the secret and all recovery codes of the user.
+pub async fn disable_totp(db: &DatabaseConnection, user: Model) -> Result<Model, DbErr> {
+    recovery_code::Entity::delete_many()
+        .filter(recovery_code::Column::UserId.eq(user.id))
+        .exec(db)
+        .await?;
+
+    let mut user: ActiveModel = user.into();
+    user.totp_secret = Set(None);
+    user.totp_enabled_at = Set(None);
+    user.totp_last_used_step = Set(None);
+    user.update(db).await
+}
+
+/// Remembers the time step of an accepted code.
+/// Returns `false` if a code for the same or a newer step was already used.
+pub async fn use_totp_step(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    step: i64,
+) -> Result<bool, DbErr> {
+    let res = Entity::update_many()
+        .col_expr(Column::TotpLastUsedStep, Expr::value(step))
+        .filter(Column::Id.eq(user_id))
+        .filter(
+            Condition::any()
+                .add(Column::TotpLastUsedStep.is_null())
+                .add(Column::TotpLastUsedStep.lt(step)),
+        )
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected == 1)
+}
+
+/// Replaces all recovery codes of the user with the given ones.
+pub async fn replace_recovery_codes(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    code_hashes: Vec<String>,
+) -> Result<(), DbErr> {
+    recovery_code::Entity::delete_many()
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+
+    let codes = code_hashes
+        .into_iter()
+        .map(|code_hash| recovery_code::ActiveModel {
+            user_id: Set(user_id),
+            code_hash: Set(code_hash),
+            ..Default::default()
+        });
+    recovery_code::Entity::insert_many(codes).exec(db).await?;
+    Ok(())
+}
+
+/// Marks the recovery code as used. Returns `false` if there is no unused code with this hash.
+pub async fn use_recovery_code(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    code_hash: &str,
+) -> Result<bool, DbErr> {
+    let res = recovery_code::Entity::update_many()
+        .col_expr(recovery_code::Column::UsedAt, Expr::value(Utc::now()))
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .filter(recovery_code::Column::CodeHash.eq(code_hash))
+        .filter(recovery_code::Column::UsedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected == 1)
+}
@@ -0,0 +1,229 @@
+use std::convert::TryFrom;
+
+use async_graphql::{Context, Object};
+use chrono::Utc;
+use entity::user;
+use sea_orm::{DatabaseConnection, EntityTrait};
+
+use crate::{
+    api::database,
+    audit::{audit_or_log, model::AuditEventKind},
+    claim::{Claim, TwoFactorChallenge},
+    crypto::hash_token,
+    errors::{Error, Result},
+    guards::TokenGuard,
+    session::{model::ClientInfo, start_session},
+    user::{
+        db::{record_failed_login, reset_failed_logins},
+        model::{ensure_active, LoginResult, User},
+    },
+    validators::Password,
+};
+
+use self::{
+    db::{
+        disable_totp, enable_totp, replace_recovery_codes, set_totp_secret, use_recovery_code,
+        use_totp_step,
+    },
+    model::{
+        generate_recovery_code, normalize_recovery_code, Totp, TotpSetup, RECOVERY_CODE_COUNT,
+    },
+};
+
+mod db;
+pub mod model;
+
+#[derive(Default)]
+pub struct TwoFactorMutation;
+
+#[Object]
+impl TwoFactorMutation {
+    /// Creates a new TOTP secret for the current user.
+    ///
+    /// TOTP is only enabled after a code was confirmed with `confirmTotp`.
+    #[graphql(guard = "TokenGuard")]
+    async fn enable_totp(&self, ctx: &Context<'_>) -> Result<TotpSetup> {
+        let db = database(ctx)?;
+        let user = current_user(ctx, db).await?;
+        if user.totp_enabled_at.is_some() {
+            return Err(Error::TotpAlreadyEnabled);
+        }
+
+        let secret = Totp::generate_secret();
+        let totp = Totp::new(&secret, &user.email)?;
+        set_totp_secret(db, user, secret).await?;
+
+        Ok(totp.setup())
+    }
+
+    /// Enables TOTP with a code from the authenticator app.
+    ///
+    /// Returns the recovery codes, they are only shown once.
+    #[graphql(guard = "TokenGuard")]
+    async fn confirm_totp(&self, ctx: &Context<'_>, code: String) -> Result<Vec<String>> {
+        let db = database(ctx)?;
+        let user = current_user(ctx, db).await?;
+        if user.totp_enabled_at.is_some() {
+            return Err(Error::TotpAlreadyEnabled);
+        }
+        let totp = match user.totp_secret {
+            None => return Err(Error::TotpNotSetUp),
+            Some(ref secret) => Totp::new(secret, &user.email)?,
+        };
+
+        let step = match totp.verify(&code, None) {
+            None => return Err(Error::InvalidTotpCode),
+            Some(step) => step,
+        };
+        let user = enable_totp(db, user, step).await?;
+
+        new_recovery_codes(db, &user).await
+    }
+
+    /// Disables TOTP, requires the password and a TOTP or recovery code.
+    #[graphql(guard = "TokenGuard")]
+    async fn disable_totp(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(custom = "Password"))] password: String,
+        code: String,
+    ) -> Result<bool> {
+        let db = database(ctx)?;
+        let user = current_user(ctx, db).await?;
+        if user.totp_enabled_at.is_none() {
+            return Err(Error::TotpNotSetUp);
+        }
+        if !User::from(user.clone())
+            .is_password_correct(&password)
+            .await
+        {
+            return Err(Error::IncorrectPassword);
+        }
+        check_second_factor(db, &user, &code).await?;
+
+        disable_totp(db, user).await?;
+        Ok(true)
+    }
+
+    /// Replaces all recovery codes with new ones, requires a TOTP code.
+    #[graphql(guard = "TokenGuard")]
+    async fn regenerate_recovery_codes(
+        &self,
+        ctx: &Context<'_>,
+        code: String,
+    ) -> Result<Vec<String>> {
+        let db = database(ctx)?;
+        let user = current_user(ctx, db).await?;
+        if user.totp_enabled_at.is_none() {
+            return Err(Error::TotpNotSetUp);
+        }
+        check_totp_code(db, &user, &code).await?;
+
+        new_recovery_codes(db, &user).await
+    }
+
+    /// Completes a login of a user with TOTP enabled.
+    ///
+    /// `code` can be a TOTP code or one of the recovery codes.
+    /// Wrong codes count as failed logins and lock the account like wrong passwords.
+    async fn verify_totp(
+        &self,
+        ctx: &Context<'_>,
+        challenge: String,
+        code: String,
+    ) -> Result<LoginResult> {
+        let db = database(ctx)?;
+        let challenge = TwoFactorChallenge::try_from(challenge)?;
+
+        let user = match user::Entity::find_by_id(challenge.user_id()?)
+            .one(db)
+            .await?
+        {
+            None => return Err(Error::NotFound),
+            Some(user) => user,
+        };
+        if user.totp_enabled_at.is_none() {
+            return Err(Error::TotpNotSetUp);
+        }
+        if let Some(locked_until) = user.locked_until {
+            if locked_until > Utc::now() {
+                return Err(Error::AccountLocked(locked_until));
+            }
+        }
+        // the user may have been deactivated since the password was checked
+        ensure_active(&user)?;
+        let client = ClientInfo::from_ctx(ctx);
+        if let Err(e) = check_second_factor(db, &user, &code).await {
+            audit_or_log(
+                db,
+                AuditEventKind::LoginFailed,
+                &client,
+                None,
+                Some(user.id),
+                Some("second factor".to_owned()),
+            )
+            .await;
+            if let Error::InvalidTotpCode = e {
+                let user = record_failed_login(db, user.id, &client).await?;
+                if let Some(locked_until) = user.and_then(|user| user.locked_until) {
+                    if locked_until > Utc::now() {
+                        return Err(Error::AccountLocked(locked_until));
+                    }
+                }
+            }
+            return Err(e);
+        }
+        if user.failed_login_attempts > 0 {
+            reset_failed_logins(db, user.id).await?;
+        }
+
+        start_session(db, user.id, &user.email, client).await
+    }
+}
+
+async fn current_user(ctx: &Context<'_>, db: &DatabaseConnection) -> Result<user::Model> {
+    let claim = Claim::from_ctx(ctx)?;
+    match user::Entity::find_by_id(claim.user_id()?).one(db).await? {
+        None => Err(Error::NotFound),
+        Some(user) => Ok(user),
+    }
+}
+
+async fn check_totp_code(db: &DatabaseConnection, user: &user::Model, code: &str) -> Result<()> {
+    let totp = match user.totp_secret {
+        None => return Err(Error::TotpNotSetUp),
+        Some(ref secret) => Totp::new(secret, &user.email)?,
+    };
+    match totp.verify(code, user.totp_last_used_step) {
+        Some(step) if use_totp_step(db, user.id, step).await? => Ok(()),
+        _ => Err(Error::InvalidTotpCode),
+    }
+}
+
+/// Accepts a TOTP code or an unused recovery code.
+async fn check_second_factor(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    code: &str,
+) -> Result<()> {
+    match check_totp_code(db, user, code).await {
+        Ok(()) => return Ok(()),
+        // not a valid TOTP code, it may be a recovery code
+        Err(Error::InvalidTotpCode) => {}
+        Err(e) => return Err(e),
+    }
+    let code_hash = hash_token(&normalize_recovery_code(code));
+    if use_recovery_code(db, user.id, &code_hash).await? {
+        return Ok(());
+    }
+    Err(Error::InvalidTotpCode)
+}
+
+async fn new_recovery_codes(db: &DatabaseConnection, user: &user::Model) -> Result<Vec<String>> {
+    let codes = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect::<Vec<_>>();
+    let hashes = codes.iter().map(|code| hash_token(code)).collect();
+    replace_recovery_codes(db, user.id, hashes).await?;
+    Ok(codes)
+}
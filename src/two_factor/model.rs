@@ -0,0 +1,93 @@
+use async_graphql::SimpleObject;
+use chrono::Utc;
+use rand::{rngs::OsRng, RngCore};
+use serde::Serialize;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::errors::{Error, Result};
+
+const TOTP_ISSUER: &str = "zorius";
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP: u64 = 30;
+const TOTP_SECRET_BYTES: usize = 20;
+/// Number of time steps before and after the current one a code is accepted for
+const TOTP_SKEW: i64 = 1;
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_BYTES: usize = 5;
+
+#[derive(Serialize, SimpleObject)]
+pub struct TotpSetup {
+    /// Base32 encoded secret, for authenticator apps that can't scan the uri
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// RFC 6238 TOTP of a user
+pub struct Totp(TOTP);
+
+impl Totp {
+    /// Creates the TOTP from a base32 encoded secret
+    pub fn new(secret: &str, account_name: &str) -> Result<Self> {
+        let secret = Secret::Encoded(secret.to_owned())
+            .to_bytes()
+            .map_err(|_| Error::TotpNotSetUp)?;
+        let totp = TOTP::new(
+            Algorithm::SHA1,
+            TOTP_DIGITS,
+            TOTP_SKEW as u8,
+            TOTP_STEP,
+            secret,
+            Some(TOTP_ISSUER.to_owned()),
+            account_name.to_owned(),
+        )
+        .map_err(|_| Error::TotpNotSetUp)?;
+        Ok(Self(totp))
+    }
+
+    /// Generates a new random base32 encoded secret
+    pub fn generate_secret() -> String {
+        let mut bytes = vec![0u8; TOTP_SECRET_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+        Secret::Raw(bytes).to_encoded().to_string()
+    }
+
+    pub fn setup(&self) -> TotpSetup {
+        TotpSetup {
+            secret: self.0.get_secret_base32(),
+            otpauth_uri: self.0.get_url(),
+        }
+    }
+
+    /// Checks the code and returns the time step it is valid for.
+    ///
+    /// Codes for steps up to `last_used_step` are rejected, so a code can't be used twice.
+    pub fn verify(&self, code: &str, last_used_step: Option<i64>) -> Option<i64> {
+        let code = code.trim();
+        let current_step = Utc::now().timestamp() / TOTP_STEP as i64;
+        (current_step - TOTP_SKEW..=current_step + TOTP_SKEW)
+            .filter(|step| last_used_step.is_none_or(|last| *step > last))
+            .find(|step| self.0.generate(*step as u64 * TOTP_STEP) == code)
+    }
+}
+
+/// Generates a new recovery code like `a1b2c-3d4e5`
+pub fn generate_recovery_code() -> String {
+    let mut bytes = [0u8; RECOVERY_CODE_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    let code = hex::encode(bytes);
+    format!("{}-{}", &code[..5], &code[5..])
+}
+
+/// Brings a recovery code entered by the user into the form it was hashed in
+pub fn normalize_recovery_code(code: &str) -> String {
+    let code: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    if code.len() != RECOVERY_CODE_BYTES * 2 {
+        return code;
+    }
+    format!("{}-{}", &code[..5], &code[5..])
+}
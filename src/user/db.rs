@@ -1,53 +1,126 @@
-use entity::user::{ActiveModel, Column, Entity, Model};
+use std::str::FromStr;
+
+use chrono::Utc;
+use chrono_tz::Tz;
+use entity::{
+    email_verification_token, login_event, organization_member, password_reset_token,
+    user::{ActiveModel, Column, Entity, Model},
+    user_preference, work_report,
+};
 use migration::sea_query::{Expr, IntoCondition};
 use sea_orm::{
-    prelude::*, Condition, DatabaseConnection, DbErr, Order, QueryOrder, QuerySelect, Set,
+    prelude::*, Condition, DatabaseConnection, DbErr, Order, QueryOrder, QuerySelect, Select, Set,
 };
 use uuid::Uuid;
 
-use super::model::{DbListOptions, NewUser, User, UserUpdate};
+use crate::{config::CONFIG, session::model::ClientInfo};
+
+use super::model::{
+    login_delay, DbListOptions, LoginEventKind, NewUser, UpdatePreferences, User, UserSort,
+    UserUpdate, LEGACY_HASH_PREFIX,
+};
 
-pub async fn new_user(db: &DatabaseConnection, update: NewUser) -> Result<Option<Model>, DbErr> {
+pub async fn new_user(
+    db: &DatabaseConnection,
+    update: NewUser,
+    email_verified: bool,
+) -> crate::errors::Result<Option<Model>> {
     let new_user = ActiveModel {
         email: Set(update.email),
-        password_hash: Set(User::hash_password(&update.password)),
+        email_verified_at: Set(email_verified.then(Utc::now)),
+        password_hash: Set(User::hash_password(&update.password).await?),
         name: Set(update.name),
         is_admin: Set(update.is_admin.unwrap_or(false)),
         ..Default::default()
     };
     let user_id = Entity::insert(new_user).exec(db).await?.last_insert_id;
-    user_by_id(db, user_id).await
+    Ok(user_by_id(db, user_id).await?)
 }
 
 pub async fn user_by_id(db: &DatabaseConnection, id: uuid::Uuid) -> Result<Option<Model>, DbErr> {
     Entity::find_by_id(id).one(db).await
 }
 
+/// Exact, case-insensitive match backed by the unique index on `lower(email)`
 pub async fn user_by_email(db: &DatabaseConnection, email: &str) -> Result<Option<Model>, DbErr> {
     Entity::find()
-        .filter(Column::Email.contains(email))
+        .filter(Expr::cust_with_values(
+            "lower(\"users\".\"email\") = ?",
+            vec![email.to_lowercase()],
+        ))
         .one(db)
         .await
 }
 
+/// Members of the organization matching the ids and the filter
+fn filtered(organization_id: Uuid, options: &DbListOptions) -> Select<Entity> {
+    let mut query = Entity::find()
+        .inner_join(organization_member::Entity)
+        .filter(organization_member::Column::OrganizationId.eq(organization_id));
+    if let Some(ids) = &options.ids {
+        let con = ids.iter().fold(Condition::all(), |acc, id| {
+            acc.add(Expr::col((Entity, Column::Id)).eq(*id))
+                .into_condition()
+        });
+        query = query.filter(con);
+    }
+
+    let filter = &options.filter;
+    if let Some(search) = &filter.search {
+        // LIKE wildcards in the search are matched literally
+        let pattern = format!(
+            "%{}%",
+            search
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        query = query.filter(Expr::cust_with_values(
+            "(\"users\".\"email\" ILIKE ? OR \"users\".\"name\" ILIKE ?)",
+            vec![pattern.clone(), pattern],
+        ));
+    }
+    if let Some(is_admin) = filter.is_admin {
+        query = query.filter(Column::IsAdmin.eq(is_admin));
+    }
+    if let Some(deactivated) = filter.deactivated {
+        query = query.filter(match deactivated {
+            true => Column::DeactivatedAt.is_not_null(),
+            false => Column::DeactivatedAt.is_null(),
+        });
+    }
+    if let Some(deleted) = filter.deleted {
+        query = query.filter(match deleted {
+            true => Column::DeletedAt.is_not_null(),
+            false => Column::DeletedAt.is_null(),
+        });
+    }
+    if let Some(since) = filter.created_since {
+        query = query.filter(Column::CreatedAt.gte(since));
+    }
+    if let Some(until) = filter.created_until {
+        query = query.filter(Column::CreatedAt.lt(until));
+    }
+    query
+}
+
+/// Lists the members of the organization
 pub async fn list_users(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     options: DbListOptions,
 ) -> Result<Vec<Model>, DbErr> {
-    if let Some(ids) = options.ids {
-        let con = ids.into_iter().fold(Condition::all(), |acc, id| {
-            acc.add(Expr::col(Column::Id).eq(id)).into_condition()
-        });
-        return Entity::find()
-            .filter(con)
-            .order_by(Column::CreatedAt, Order::Asc)
-            .all(db)
-            .await;
-    }
-    Entity::find()
-        .offset(options.start as u64)
-        .limit(options.limit as u64)
-        .order_by(Column::CreatedAt, Order::Asc)
+    let sort = match options.sort.unwrap_or(UserSort::CreatedAt) {
+        UserSort::CreatedAt => Column::CreatedAt,
+        UserSort::Email => Column::Email,
+        UserSort::Name => Column::Name,
+    };
+    filtered(organization_id, &options)
+        .order_by(sort, options.sort_direction.into())
+        // stable order for paging if the sort column has duplicates
+        .order_by(Column::Id, Order::Asc)
+        .offset(options.start)
+        .limit(options.limit)
         .all(db)
         .await
 }
@@ -56,6 +129,21 @@ pub async fn count_users(db: &DatabaseConnection) -> Result<usize, DbErr> {
     Entity::find().count(db).await
 }
 
+pub async fn count_organization_users(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    options: &DbListOptions,
+) -> Result<usize, DbErr> {
+    filtered(organization_id, options).count(db).await
+}
+
+pub async fn count_legacy_password_hashes(db: &DatabaseConnection) -> Result<usize, DbErr> {
+    Entity::find()
+        .filter(Column::PasswordHash.starts_with(LEGACY_HASH_PREFIX))
+        .count(db)
+        .await
+}
+
 pub async fn update_user(
     db: &DatabaseConnection,
     id: Uuid,
@@ -74,6 +162,102 @@ pub async fn update_user(
     Ok(None)
 }
 
+/// Preferences of the user, the defaults are stored on first access
+pub async fn user_preferences(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<user_preference::Model, DbErr> {
+    if let Some(preferences) = user_preference::Entity::find_by_id(user_id).one(db).await? {
+        return Ok(preferences);
+    }
+    user_preference::ActiveModel {
+        user_id: Set(user_id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
+
+/// Time zone of the user, UTC if the user never set one
+pub async fn user_timezone(db: &DatabaseConnection, user_id: Uuid) -> Result<Tz, DbErr> {
+    let preferences = user_preference::Entity::find_by_id(user_id).one(db).await?;
+    Ok(preferences
+        .and_then(|preferences| Tz::from_str(&preferences.timezone).ok())
+        .unwrap_or(Tz::UTC))
+}
+
+/// `working_minutes` are the minutes per day from monday to sunday
+pub async fn update_preferences(
+    db: &DatabaseConnection,
+    preferences: user_preference::Model,
+    update: UpdatePreferences,
+    working_minutes: Option<[i32; 7]>,
+) -> Result<user_preference::Model, DbErr> {
+    let mut preferences: user_preference::ActiveModel = preferences.into();
+    if let Some(timezone) = update.timezone {
+        preferences.timezone = Set(timezone);
+    }
+    if let Some(locale) = update.locale {
+        preferences.locale = Set(locale);
+    }
+    if let Some(first_day_of_week) = update.first_day_of_week {
+        preferences.first_day_of_week = Set(first_day_of_week.as_str().to_owned());
+    }
+    if let Some(date_format) = update.date_format {
+        preferences.date_format = Set(date_format);
+    }
+    if let Some(minutes) = working_minutes {
+        preferences.weekly_minutes = Set(minutes.iter().sum());
+        preferences.monday_minutes = Set(minutes[0]);
+        preferences.tuesday_minutes = Set(minutes[1]);
+        preferences.wednesday_minutes = Set(minutes[2]);
+        preferences.thursday_minutes = Set(minutes[3]);
+        preferences.friday_minutes = Set(minutes[4]);
+        preferences.saturday_minutes = Set(minutes[5]);
+        preferences.sunday_minutes = Set(minutes[6]);
+    }
+    preferences.update(db).await
+}
+
+/// Sets or clears `deactivated_at`
+pub async fn set_user_deactivated(
+    db: &DatabaseConnection,
+    user: Model,
+    deactivated: bool,
+) -> Result<Model, DbErr> {
+    let mut user: ActiveModel = user.into();
+    user.deactivated_at = Set(deactivated.then(Utc::now));
+    user.update(db).await
+}
+
+/// Soft deletes the user or restores it, the email stays taken while deleted
+pub async fn set_user_deleted(
+    db: &DatabaseConnection,
+    user: Model,
+    deleted: bool,
+) -> Result<Model, DbErr> {
+    let mut user: ActiveModel = user.into();
+    user.deleted_at = Set(deleted.then(Utc::now));
+    user.update(db).await
+}
+
+/// Deletes the user with its sessions, tokens and roles.
+/// The avatar files have to be removed with `remove_avatar_files`.
+pub async fn purge_user(db: &DatabaseConnection, user: Model) -> Result<u64, DbErr> {
+    let user: ActiveModel = user.into();
+    Ok(user.delete(db).await?.rows_affected)
+}
+
+pub async fn count_owned_work_reports(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<usize, DbErr> {
+    work_report::Entity::find()
+        .filter(work_report::Column::OwnerId.eq(user_id))
+        .count(db)
+        .await
+}
+
 pub async fn save_user_avatar(
     db: &DatabaseConnection,
     id: Uuid,
@@ -103,3 +287,214 @@ pub async fn reset_password(
     }
     Ok(None)
 }
+
+pub async fn new_password_reset_token(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    token_hash: String,
+    expires_at: DateTimeUtc,
+) -> Result<password_reset_token::Model, DbErr> {
+    let token = password_reset_token::ActiveModel {
+        user_id: Set(user_id),
+        token_hash: Set(token_hash),
+        expires_at: Set(expires_at),
+        ..Default::default()
+    };
+    token.insert(db).await
+}
+
+/// Returns the password reset token with the given hash if it is unused and not expired.
+pub async fn valid_password_reset_token(
+    db: &DatabaseConnection,
+    token_hash: &str,
+) -> Result<Option<password_reset_token::Model>, DbErr> {
+    password_reset_token::Entity::find()
+        .filter(password_reset_token::Column::TokenHash.eq(token_hash))
+        .filter(password_reset_token::Column::UsedAt.is_null())
+        .filter(password_reset_token::Column::ExpiresAt.gt(Utc::now()))
+        .one(db)
+        .await
+}
+
+/// Marks all unused password reset tokens of the user as used.
+/// Returns the number of tokens that got invalidated.
+pub async fn invalidate_password_reset_tokens(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<u64, DbErr> {
+    let res = password_reset_token::Entity::update_many()
+        .col_expr(
+            password_reset_token::Column::UsedAt,
+            Expr::value(Utc::now()),
+        )
+        .filter(password_reset_token::Column::UserId.eq(user_id))
+        .filter(password_reset_token::Column::UsedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected)
+}
+
+pub async fn new_email_verification_token(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    email: String,
+    token_hash: String,
+    expires_at: DateTimeUtc,
+) -> Result<email_verification_token::Model, DbErr> {
+    let token = email_verification_token::ActiveModel {
+        user_id: Set(user_id),
+        email: Set(email),
+        token_hash: Set(token_hash),
+        expires_at: Set(expires_at),
+        ..Default::default()
+    };
+    token.insert(db).await
+}
+
+/// Returns the email verification token with the given hash if it is unused and not expired.
+pub async fn valid_email_verification_token(
+    db: &DatabaseConnection,
+    token_hash: &str,
+) -> Result<Option<email_verification_token::Model>, DbErr> {
+    email_verification_token::Entity::find()
+        .filter(email_verification_token::Column::TokenHash.eq(token_hash))
+        .filter(email_verification_token::Column::UsedAt.is_null())
+        .filter(email_verification_token::Column::ExpiresAt.gt(Utc::now()))
+        .one(db)
+        .await
+}
+
+/// Marks all unused email verification tokens of the user as used.
+/// Returns the number of tokens that got invalidated.
+pub async fn invalidate_email_verification_tokens(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+) -> Result<u64, DbErr> {
+    let res = email_verification_token::Entity::update_many()
+        .col_expr(
+            email_verification_token::Column::UsedAt,
+            Expr::value(Utc::now()),
+        )
+        .filter(email_verification_token::Column::UserId.eq(user_id))
+        .filter(email_verification_token::Column::UsedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected)
+}
+
+/// Sets the (new) email address of the user and marks it as verified.
+pub async fn verify_user_email(
+    db: &DatabaseConnection,
+    id: Uuid,
+    email: String,
+) -> Result<Option<Model>, DbErr> {
+    let user = user_by_id(db, id).await?;
+    if let Some(user) = user {
+        let mut user: ActiveModel = user.into();
+        user.email = Set(email);
+        user.email_verified_at = Set(Some(Utc::now()));
+        return Ok(Some(user.update(db).await?));
+    }
+    Ok(None)
+}
+
+/// Counts a failed login and delays or locks further logins, returns the updated user.
+///
+/// Once a lockout has expired the count starts over, so the next failures are
+/// throttled again instead of locking the account right away.
+pub async fn record_failed_login(
+    db: &DatabaseConnection,
+    id: Uuid,
+    client: &ClientInfo,
+) -> Result<Option<Model>, DbErr> {
+    // increment in the database, so parallel guesses are all counted
+    Entity::update_many()
+        .col_expr(
+            Column::FailedLoginAttempts,
+            Expr::cust_with_values(
+                "CASE WHEN \"failed_login_attempts\" >= ? AND \"locked_until\" < now() \
+                 THEN 1 ELSE \"failed_login_attempts\" + 1 END",
+                vec![CONFIG.login_throttle.lockout_attempts],
+            ),
+        )
+        .filter(Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    let user = match user_by_id(db, id).await? {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+    new_login_event(db, id, LoginEventKind::Failed, client, None).await?;
+
+    let delay = match login_delay(user.failed_login_attempts) {
+        Some(delay) => delay,
+        None => return Ok(Some(user)),
+    };
+    if user.failed_login_attempts >= CONFIG.login_throttle.lockout_attempts {
+        new_login_event(db, id, LoginEventKind::Locked, client, None).await?;
+    }
+    let mut user: ActiveModel = user.into();
+    user.locked_until = Set(Some(Utc::now() + delay));
+    Ok(Some(user.update(db).await?))
+}
+
+/// Resets the failed login attempts after a successful login
+pub async fn reset_failed_logins(db: &DatabaseConnection, id: Uuid) -> Result<(), DbErr> {
+    Entity::update_many()
+        .col_expr(Column::FailedLoginAttempts, Expr::value(0))
+        .col_expr(
+            Column::LockedUntil,
+            Expr::value(Option::<DateTimeUtc>::None),
+        )
+        .filter(Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Unlocks the account before the lockout ends, `admin_id` is recorded with the unlock
+pub async fn unlock_user(
+    db: &DatabaseConnection,
+    id: Uuid,
+    admin_id: Uuid,
+    client: &ClientInfo,
+) -> Result<Option<Model>, DbErr> {
+    if user_by_id(db, id).await?.is_none() {
+        return Ok(None);
+    }
+    reset_failed_logins(db, id).await?;
+    new_login_event(db, id, LoginEventKind::Unlocked, client, Some(admin_id)).await?;
+    user_by_id(db, id).await
+}
+
+pub async fn new_login_event(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    kind: LoginEventKind,
+    client: &ClientInfo,
+    actor_id: Option<Uuid>,
+) -> Result<login_event::Model, DbErr> {
+    login_event::ActiveModel {
+        user_id: Set(user_id),
+        kind: Set(kind.as_str().to_owned()),
+        ip: Set(client.ip.clone()),
+        user_agent: Set(client.user_agent.clone()),
+        actor_id: Set(actor_id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
+
+pub async fn list_login_events(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    limit: u64,
+) -> Result<Vec<login_event::Model>, DbErr> {
+    login_event::Entity::find()
+        .filter(login_event::Column::UserId.eq(user_id))
+        .order_by(login_event::Column::CreatedAt, Order::Desc)
+        .limit(limit)
+        .all(db)
+        .await
+}
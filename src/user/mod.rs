@@ -1,40 +1,62 @@
-use std::{convert::TryInto, path::PathBuf};
+use std::convert::TryInto;
 
+use askama::Template;
 use async_graphql::{
     connection::{query, Connection, Edge, EmptyFields},
     Context, Object, Subscription, Upload, UploadValue,
 };
 use chrono::{Duration, Utc};
-use entity::user::Entity;
 use futures::stream::{self, StreamExt};
 use futures_util::{AsyncReadExt, Stream};
-use image::{imageops::FilterType, DynamicImage, ImageFormat};
-use log::{debug, error, info};
-use mime::Mime;
-use sea_orm::EntityTrait;
+use log::{debug, error, info, warn};
+use sea_orm::DatabaseConnection;
 use tokio::task::spawn_blocking;
 use uuid::Uuid;
 
 use crate::{
     api::{database, MutationType},
-    claim::Claim,
+    audit::{audit, audit_or_log, model::AuditEventKind},
+    auth::authenticate,
+    avatar::{remove_avatar_files, save_avatar, upload_format},
+    claim::{Claim, TwoFactorChallenge},
     config::CONFIG,
+    crypto::{generate_token, hash_token},
     errors::{Error, Result},
-    guards::TokenGuard,
+    guards::{AdminGuard, PermissionGuard, TokenGuard},
+    mailer::mailer,
+    organization::db::{join_default_organization, membership},
+    policy::{Actor, Policy, UserAction},
+    role::{
+        db::{assign_default_roles, sync_admin_role},
+        model::Permission,
+    },
+    session::{
+        db::revoke_user_sessions, model::ClientInfo, start_impersonation_session, start_session,
+    },
     simple_broker::SimpleBroker,
     upload::FileInfo,
     user::db::save_user_avatar,
-    validators::Password,
+    validators::{Login, Password},
 };
 
 use self::{
     db::{
-        count_users, list_users, new_user, reset_password, update_user, user_by_email, user_by_id,
+        count_legacy_password_hashes, count_organization_users, count_owned_work_reports,
+        count_users, invalidate_email_verification_tokens, invalidate_password_reset_tokens,
+        list_login_events, list_users, new_email_verification_token, new_login_event,
+        new_password_reset_token, new_user, purge_user, record_failed_login, reset_failed_logins,
+        reset_password, set_user_deactivated, set_user_deleted, unlock_user, update_preferences,
+        update_user, user_by_email, user_by_id, user_preferences, valid_email_verification_token,
+        valid_password_reset_token, verify_user_email,
+    },
+    model::{
+        ensure_active, DbListOptions, EmailVerificationTemplate, ListUserOptions, LoginEvent,
+        LoginEventKind, LoginResult, NewUser, PasswordResetTemplate, UpdatePreferences, User,
+        UserChanged, UserPreferences, UserStateChange, UserUpdate,
     },
-    model::{DbListOptions, ListUserOptions, LoginResult, NewUser, User, UserChanged, UserUpdate},
 };
 
-mod db;
+pub mod db;
 pub mod model;
 
 #[derive(Default)]
@@ -45,44 +67,113 @@ impl UserQuery {
     pub async fn login(
         &self,
         ctx: &Context<'_>,
-        #[graphql(validator(email))] email: String,
+        #[graphql(validator(custom = "Login"))] email: String,
         #[graphql(validator(custom = "Password"))] password: String,
     ) -> Result<LoginResult> {
         let db = database(ctx)?;
-
-        let user = match user_by_email(db, &email).await? {
-            None => return Err(Error::NotFound),
+        let client = ClientInfo::from_ctx(ctx);
+
+        let existing = user_by_email(db, &email)
+            .await?
+            .filter(|user| user.deleted_at.is_none());
+        if let Some(locked_until) = existing.as_ref().and_then(|user| user.locked_until) {
+            if locked_until > Utc::now() {
+                return Err(Error::AccountLocked(locked_until));
+            }
+        }
+        let user = match authenticate(db, &email, &password).await? {
             Some(user) => user,
+            None => {
+                // unknown emails are audited too, guessing them is part of an attack
+                audit_or_log(
+                    db,
+                    AuditEventKind::LoginFailed,
+                    &client,
+                    None,
+                    existing.as_ref().map(|user| user.id),
+                    Some(email),
+                )
+                .await;
+                let existing = match existing {
+                    Some(existing) => existing,
+                    None => return Err(Error::NotFound),
+                };
+                let user = record_failed_login(db, existing.id, &client).await?;
+                if let Some(locked_until) = user.and_then(|user| user.locked_until) {
+                    if locked_until > Utc::now() {
+                        return Err(Error::AccountLocked(locked_until));
+                    }
+                }
+                return Err(Error::IncorrectPassword);
+            }
         };
+        // a directory backend may have logged in a different user than the one found by email
+        if let Some(locked_until) = user.locked_until {
+            if locked_until > Utc::now() {
+                return Err(Error::AccountLocked(locked_until));
+            }
+        }
+        ensure_active(&user)?;
+        if user.failed_login_attempts > 0 {
+            reset_failed_logins(db, user.id).await?;
+        }
         let user = User::from(user);
-
-        if !user.is_password_correct(&password) {
-            return Err(Error::IncorrectPassword);
+        if CONFIG.require_email_verification && user.email_verified_at.is_none() {
+            return Err(Error::EmailNotVerified);
+        }
+        if user.totp_enabled_at.is_some() {
+            let challenge = TwoFactorChallenge::new(user.get_id()).try_into()?;
+            return Ok(LoginResult {
+                two_factor_challenge: Some(challenge),
+                ..Default::default()
+            });
         }
-        let claim = Claim::new(
-            &email,
-            &user.get_id().clone().to_string(),
-            (Utc::now() + Duration::seconds(CONFIG.token_lifetime)).timestamp() as usize,
-        );
-        let token = claim.try_into()?;
-
-        Ok(LoginResult { token })
+
+        start_session(db, *user.get_id(), &user.email, client).await
     }
 
+    /// The current user
     #[graphql(guard = "TokenGuard")]
+    async fn me(&self, ctx: &Context<'_>) -> Result<Option<User>> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        Ok(user_by_id(db, claim.user_id()?).await?.map(User::from))
+    }
+
+    /// Failed logins, lockouts and unlocks of the user, newest first
+    #[graphql(guard = "PermissionGuard::new(Permission::UserWrite)")]
+    async fn login_events(&self, ctx: &Context<'_>, user_id: Uuid) -> Result<Vec<LoginEvent>> {
+        let db = database(ctx)?;
+        ensure_member(ctx, user_id).await?;
+        let events = list_login_events(db, user_id, 100).await?;
+        Ok(events.into_iter().map(LoginEvent::from).collect())
+    }
+
+    /// Number of accounts whose password is still hashed with sha512_crypt.
+    /// They are upgraded to Argon2id on the next login.
+    #[graphql(guard = "AdminGuard")]
+    async fn legacy_password_hash_count(&self, ctx: &Context<'_>) -> Result<usize> {
+        let db = database(ctx)?;
+        Ok(count_legacy_password_hashes(db).await?)
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::UserRead)")]
     async fn users(
         &self,
         ctx: &Context<'_>,
         options: Option<ListUserOptions>,
     ) -> async_graphql::Result<Connection<usize, User, EmptyFields, EmptyFields>> {
-        let _ = Claim::from_ctx(ctx)?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
         let db = database(ctx)?;
         let options = options.unwrap_or_default();
-        let count = count_users(db).await? as usize;
         let mut db_options = DbListOptions {
             ids: options.ids,
+            filter: options.filter.unwrap_or_default(),
+            sort: options.sort,
+            sort_direction: options.sort_direction.unwrap_or_default(),
             ..Default::default()
         };
+        let count = count_organization_users(db, organization_id, &db_options).await?;
 
         query(
             options.after,
@@ -99,9 +190,9 @@ impl UserQuery {
                     start = if last > end - start { end } else { end - last };
                 }
                 db_options.start = start as u64;
-                db_options.limit = end as u64;
+                db_options.limit = (end - start) as u64;
 
-                let users = list_users(db, db_options).await?;
+                let users = list_users(db, organization_id, db_options).await?;
 
                 let mut connection = Connection::new(start > 0, end < count);
                 connection
@@ -134,22 +225,31 @@ impl UserMutation {
             return Err(Error::EmailAlreadyRegistred);
         }
 
-        if let Ok(claim) = Claim::from_ctx(ctx) {
-            let user_id = claim.user_id()?;
-            let model = Entity::find_by_id(user_id).one(db).await?;
-            if let Some(model) = model {
-                if !model.is_admin {
-                    new.is_admin = None;
-                }
-            }
+        // anonymous registrations and non admins can't create admins
+        let may_set_admin = match Claim::from_ctx(ctx) {
+            Ok(_) => Actor::from_ctx(ctx).await?.may_set_admin(),
+            Err(_) => false,
+        };
+        if !may_set_admin {
+            new.is_admin = None;
         }
 
-        if count_users(db).await? < 1 {
+        // the first user becomes admin and has no way to receive a verification email yet
+        let first_user = count_users(db).await? < 1;
+        if first_user {
             new.is_admin = Some(true);
         }
 
-        let new_user = new_user(db, new).await?;
+        let new_user = new_user(db, new, first_user).await?;
         if let Some(user) = new_user {
+            assign_default_roles(db, user.id).await?;
+            if user.is_admin {
+                sync_admin_role(db, user.id, true).await?;
+            }
+            join_default_organization(db, user.id).await?;
+            if user.email_verified_at.is_none() {
+                send_verification_email(db, &user, user.email.clone()).await?;
+            }
             SimpleBroker::publish(UserChanged {
                 mutation_type: MutationType::Created,
                 id: user.id,
@@ -176,15 +276,283 @@ impl UserMutation {
         };
         let user = User::from(user);
 
-        if !user.is_password_correct(&old_password) {
+        if !user.is_password_correct(&old_password).await {
             return Err(Error::IncorrectPassword);
         } else {
-            reset_password(db, user_id, &new_password).await?;
+            reset_password(db, user_id, &User::hash_password(&new_password).await?).await?;
+            audit(
+                db,
+                AuditEventKind::PasswordChanged,
+                &ClientInfo::from_ctx(ctx),
+                Some(user_id),
+                Some(user_id),
+                None,
+            )
+            .await?;
+        }
+
+        Ok(true)
+    }
+
+    /// Sends a link to reset the password to the given email.
+    ///
+    /// Always returns `true`, so it can not be used to find out which emails are registered.
+    async fn request_password_reset(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(email))] email: String,
+    ) -> Result<bool> {
+        if !CONFIG.password_login_enabled {
+            return Err(Error::PasswordLoginDisabled);
+        }
+        let db = database(ctx)?;
+
+        let user = match user_by_email(db, &email).await? {
+            Some(user) if ensure_active(&user).is_ok() => user,
+            _ => return Ok(true),
+        };
+        send_password_reset_email(db, user).await?;
+
+        Ok(true)
+    }
+
+    /// Sets a new password with a token from a password reset email.
+    ///
+    /// All sessions of the user are ended afterwards.
+    async fn complete_password_reset(
+        &self,
+        ctx: &Context<'_>,
+        token: String,
+        #[graphql(validator(custom = "Password"))] new_password: String,
+    ) -> Result<bool> {
+        let db = database(ctx)?;
+
+        let reset_token = match valid_password_reset_token(db, &hash_token(&token)).await? {
+            None => return Err(Error::ExpiredToken),
+            Some(reset_token) => reset_token,
+        };
+
+        // invalidating first makes sure the token can only be used once
+        if invalidate_password_reset_tokens(db, reset_token.user_id).await? < 1 {
+            return Err(Error::ExpiredToken);
+        }
+        reset_password(
+            db,
+            reset_token.user_id,
+            &User::hash_password(&new_password).await?,
+        )
+        .await?;
+        revoke_user_sessions(db, reset_token.user_id).await?;
+        audit(
+            db,
+            AuditEventKind::PasswordChanged,
+            &ClientInfo::from_ctx(ctx),
+            None,
+            Some(reset_token.user_id),
+            Some("password reset".to_owned()),
+        )
+        .await?;
+
+        Ok(true)
+    }
+
+    /// Verifies an email address with the token from a verification email.
+    ///
+    /// If the token was sent by `changeEmail`, the email of the user is replaced by the verified one.
+    async fn verify_email(&self, ctx: &Context<'_>, token: String) -> Result<Option<User>> {
+        let db = database(ctx)?;
+
+        let verification = match valid_email_verification_token(db, &hash_token(&token)).await? {
+            None => return Err(Error::ExpiredToken),
+            Some(verification) => verification,
+        };
+
+        if let Some(other) = user_by_email(db, &verification.email).await? {
+            if other.id != verification.user_id {
+                return Err(Error::EmailAlreadyRegistred);
+            }
         }
 
+        if invalidate_email_verification_tokens(db, verification.user_id).await? < 1 {
+            return Err(Error::ExpiredToken);
+        }
+        let user = verify_user_email(db, verification.user_id, verification.email).await?;
+        if let Some(user) = user {
+            SimpleBroker::publish(UserChanged {
+                mutation_type: MutationType::Updated,
+                id: user.id,
+            });
+            return Ok(Some(User::from(user)));
+        }
+        Ok(None)
+    }
+
+    /// Sends a new verification email, if the email is registered and not verified yet.
+    ///
+    /// Always returns `true`, so it can not be used to find out which emails are registered.
+    async fn resend_verification_email(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(email))] email: String,
+    ) -> Result<bool> {
+        let db = database(ctx)?;
+
+        if let Some(user) = user_by_email(db, &email).await? {
+            if user.email_verified_at.is_none() && user.deleted_at.is_none() {
+                send_verification_email(db, &user, user.email.clone()).await?;
+            }
+        }
         Ok(true)
     }
 
+    /// Requests to change the email of the current user.
+    ///
+    /// The email is only changed after the new address was verified with `verifyEmail`.
+    #[graphql(guard = "TokenGuard")]
+    async fn change_email(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(email))] new_email: String,
+        #[graphql(validator(custom = "Password"))] password: String,
+    ) -> Result<bool> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+
+        let user = match user_by_id(db, claim.user_id()?).await? {
+            None => return Err(Error::NotFound),
+            Some(user) => user,
+        };
+        if !User::from(user.clone())
+            .is_password_correct(&password)
+            .await
+        {
+            return Err(Error::IncorrectPassword);
+        }
+        if user_by_email(db, &new_email).await?.is_some() {
+            return Err(Error::EmailAlreadyRegistred);
+        }
+
+        send_verification_email(db, &user, new_email).await?;
+        Ok(true)
+    }
+
+    /// Unlocks an account that was locked after too many failed logins
+    #[graphql(guard = "PermissionGuard::new(Permission::UserWrite)")]
+    async fn unlock_user(&self, ctx: &Context<'_>, user_id: Uuid) -> Result<Option<User>> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        let client = ClientInfo::from_ctx(ctx);
+        ensure_member(ctx, user_id).await?;
+        let user = unlock_user(db, user_id, claim.user_id()?, &client).await?;
+        if let Some(user) = &user {
+            info!("user {} unlocked by {}", user.id, claim.user_id()?);
+        }
+        Ok(user.map(User::from))
+    }
+
+    /// Refuses logins and all tokens of the user until it is reactivated
+    #[graphql(guard = "AdminGuard")]
+    async fn deactivate_user(&self, ctx: &Context<'_>, user_id: Uuid) -> Result<Option<User>> {
+        let user = set_user_state(ctx, user_id, UserStateChange::Deactivate).await?;
+        Ok(user.map(User::from))
+    }
+
+    #[graphql(guard = "AdminGuard")]
+    async fn reactivate_user(&self, ctx: &Context<'_>, user_id: Uuid) -> Result<Option<User>> {
+        let user = set_user_state(ctx, user_id, UserStateChange::Reactivate).await?;
+        Ok(user.map(User::from))
+    }
+
+    /// Soft deletes the user, it can be restored until it is purged
+    #[graphql(guard = "AdminGuard")]
+    async fn delete_user(&self, ctx: &Context<'_>, user_id: Uuid) -> Result<Option<User>> {
+        let user = set_user_state(ctx, user_id, UserStateChange::Delete).await?;
+        Ok(user.map(User::from))
+    }
+
+    #[graphql(guard = "AdminGuard")]
+    async fn restore_user(&self, ctx: &Context<'_>, user_id: Uuid) -> Result<Option<User>> {
+        let user = set_user_state(ctx, user_id, UserStateChange::Restore).await?;
+        Ok(user.map(User::from))
+    }
+
+    /// Permanently deletes a soft deleted user together with its sessions, tokens and avatar.
+    /// Users that still own work reports can't be purged.
+    #[graphql(guard = "AdminGuard")]
+    async fn purge_user(&self, ctx: &Context<'_>, user_id: Uuid) -> Result<bool> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        let user = user_by_id(db, user_id).await?.ok_or(Error::NotFound)?;
+        if user.deleted_at.is_none() {
+            return Err(Error::UserNotDeleted);
+        }
+        if count_owned_work_reports(db, user.id).await? > 0 {
+            return Err(Error::UserHasWorkReports);
+        }
+        info!("user {} purged by {}", user.id, claim.user_id()?);
+        let avatar = user.avatar_filename.clone();
+        let purged = purge_user(db, user).await? > 0;
+        if let (true, Some(avatar)) = (purged, avatar) {
+            spawn_blocking(move || remove_avatar_files(&avatar));
+        }
+        if purged {
+            SimpleBroker::publish(UserChanged {
+                mutation_type: MutationType::Deleted,
+                id: user_id,
+            });
+        }
+        Ok(purged)
+    }
+
+    /// Sends the user a link to set a new password
+    #[graphql(guard = "AdminGuard")]
+    async fn admin_reset_password(&self, ctx: &Context<'_>, user_id: Uuid) -> Result<bool> {
+        if !CONFIG.password_login_enabled {
+            return Err(Error::PasswordLoginDisabled);
+        }
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        let user = user_by_id(db, user_id).await?.ok_or(Error::NotFound)?;
+        ensure_active(&user)?;
+        let client = ClientInfo::from_ctx(ctx);
+        new_login_event(
+            db,
+            user.id,
+            LoginEventKind::PasswordResetByAdmin,
+            &client,
+            Some(claim.user_id()?),
+        )
+        .await?;
+        send_password_reset_email(db, user).await?;
+        Ok(true)
+    }
+
+    /// Returns an access token to act as the user for support cases.
+    ///
+    /// The session is recorded with the admin, has no refresh token and ends with the token.
+    /// Other admins can't be impersonated.
+    #[graphql(guard = "AdminGuard")]
+    async fn impersonate_user(&self, ctx: &Context<'_>, user_id: Uuid) -> Result<LoginResult> {
+        let admin_id = Claim::from_ctx(ctx)?.user_id()?;
+        let db = database(ctx)?;
+        let user = user_by_id(db, user_id).await?.ok_or(Error::NotFound)?;
+        if user.is_admin || user.id == admin_id {
+            return Err(Error::Forbidden);
+        }
+        ensure_active(&user)?;
+        let client = ClientInfo::from_ctx(ctx);
+        new_login_event(
+            db,
+            user.id,
+            LoginEventKind::Impersonated,
+            &client,
+            Some(admin_id),
+        )
+        .await?;
+        warn!("user {} impersonated by {}", user.id, admin_id);
+        start_impersonation_session(db, &user, admin_id, client).await
+    }
+
     #[graphql(guard = "TokenGuard")]
     async fn update_user(
         &self,
@@ -192,10 +560,32 @@ impl UserMutation {
         user_id: Uuid,
         user_update: UserUpdate,
     ) -> Result<Option<User>> {
-        let _ = Claim::from_ctx(ctx)?;
+        let actor = Actor::from_ctx(ctx).await?;
         let db = database(ctx)?;
+        ensure_member(ctx, user_id).await?;
+        let target = match user_by_id(db, user_id).await? {
+            Some(target) => target,
+            None => return Ok(None),
+        };
+        target.authorize(&actor, UserAction::Update)?;
+        if user_update.is_admin.is_some() {
+            target.authorize(&actor, UserAction::SetAdmin)?;
+        }
+        let was_admin = target.is_admin;
         let updated_user = update_user(db, user_id, user_update).await?;
         if let Some(user) = updated_user {
+            if user.is_admin != was_admin {
+                sync_admin_role(db, user.id, user.is_admin).await?;
+                audit(
+                    db,
+                    AuditEventKind::AdminFlagChanged,
+                    &ClientInfo::from_ctx(ctx),
+                    Some(actor.id),
+                    Some(user.id),
+                    Some(user.is_admin.to_string()),
+                )
+                .await?;
+            }
             SimpleBroker::publish(UserChanged {
                 mutation_type: MutationType::Updated,
                 id: user.id,
@@ -205,6 +595,21 @@ impl UserMutation {
         Ok(None)
     }
 
+    #[graphql(guard = "TokenGuard")]
+    async fn update_my_preferences(
+        &self,
+        ctx: &Context<'_>,
+        update: UpdatePreferences,
+    ) -> Result<UserPreferences> {
+        let claim = Claim::from_ctx(ctx)?;
+        let db = database(ctx)?;
+        let preferences = user_preferences(db, claim.user_id()?).await?;
+        let working_minutes = update.working_minutes(&preferences)?;
+        let preferences = update_preferences(db, preferences, update, working_minutes).await?;
+        Ok(preferences.into())
+    }
+
+    /// Accepts PNG, JPEG, WebP and GIF, the avatar is stored in several sizes as PNG and WebP
     #[graphql(guard = "TokenGuard")]
     async fn upload_avatar(&self, ctx: &Context<'_>, file: Upload) -> Result<FileInfo> {
         debug!("avatar upload");
@@ -212,56 +617,41 @@ impl UserMutation {
         let db = database(ctx)?;
         let user_id = claim.user_id()?;
 
-        let file_name = format!("{}.png", Uuid::new_v4());
-        let path: PathBuf = ["static", "avatar", &file_name].iter().collect();
-
-        let value: UploadValue = file.value(ctx).unwrap();
-        let format: ImageFormat = match value.content_type {
-            Some(ref content_type) => match content_type.parse::<Mime>() {
-                Ok(r) if r == mime::IMAGE_PNG => ImageFormat::Png,
-                Ok(r) if r == mime::IMAGE_JPEG => ImageFormat::Jpeg,
-                _ => return Err(Error::WrongMediaType),
-            },
-            None => return Err(Error::WrongMediaType),
-        };
+        let value: UploadValue = file.value(ctx)?;
+        let format = upload_format(value.content_type.as_deref())?;
 
+        // read one byte more than allowed to detect too large uploads without reading all of it
         let mut vec = Vec::new();
-        let mut reader = value.into_async_read();
-        reader.read_to_end(&mut vec).await.unwrap();
-
-        let path2 = PathBuf::clone(&path);
-        match spawn_blocking(move || {
-            debug!("loading image from memory");
-            let img: DynamicImage = match image::load_from_memory_with_format(&vec, format) {
-                Err(e) => {
-                    error!("failed to load image from memory: {e}");
-                    return Err(Error::Image(e));
-                }
-                Ok(r) => r,
-            };
-            debug!("starting to resize avatar");
-            let img = img.resize(512, 512, FilterType::Lanczos3);
-            debug!("resized image to 512x512");
-            let _ = img.save_with_format(path2, ImageFormat::Png);
-            debug!("saved image in fs");
-            Ok(())
-        })
-        .await
-        {
-            Ok(_) => info!("saved new avatar"),
+        let limit = CONFIG.avatar.max_bytes as u64 + 1;
+        value
+            .into_async_read()
+            .take(limit)
+            .read_to_end(&mut vec)
+            .await?;
+
+        let file_name = match spawn_blocking(move || save_avatar(&vec, format)).await {
+            Ok(res) => res?,
             Err(e) => {
                 error!("failed to save avatar image: {e:?}");
                 return Err(Error::Unknown);
             }
-        }
+        };
+        info!("saved new avatar");
 
         if let Some(user) = user_by_id(db, user_id).await? {
+            let _ = save_user_avatar(db, user_id, file_name.clone()).await?;
             if let Some(old_avatar) = user.avatar_filename {
-                let old_avatar_path: PathBuf = ["static", "avatar", &old_avatar].iter().collect();
-                let _ = tokio::fs::remove_file(old_avatar_path).await;
+                spawn_blocking(move || remove_avatar_files(&old_avatar));
             }
-
-            let _ = save_user_avatar(db, user_id, file_name.clone()).await?;
+            audit(
+                db,
+                AuditEventKind::AvatarChanged,
+                &ClientInfo::from_ctx(ctx),
+                Some(user_id),
+                Some(user_id),
+                Some(file_name.clone()),
+            )
+            .await?;
 
             SimpleBroker::publish(UserChanged {
                 mutation_type: MutationType::Updated,
@@ -275,12 +665,113 @@ impl UserMutation {
     }
 }
 
+/// Fails with `NotFound` if the user is not a member of the organization of the token,
+/// user managers may only manage the users of their organization
+async fn ensure_member(ctx: &Context<'_>, user_id: Uuid) -> Result<()> {
+    let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+    membership(database(ctx)?, organization_id, user_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    Ok(())
+}
+
+/// Deactivates, reactivates, deletes or restores the user and records who did it.
+/// Deactivated and deleted users lose all their sessions.
+async fn set_user_state(
+    ctx: &Context<'_>,
+    user_id: Uuid,
+    change: UserStateChange,
+) -> Result<Option<entity::user::Model>> {
+    let admin_id = Claim::from_ctx(ctx)?.user_id()?;
+    if user_id == admin_id {
+        return Err(Error::Forbidden);
+    }
+    let db = database(ctx)?;
+    let user = match user_by_id(db, user_id).await? {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+    let (user, mutation_type) = match change {
+        UserStateChange::Deactivate => (
+            set_user_deactivated(db, user, true).await?,
+            MutationType::Updated,
+        ),
+        UserStateChange::Reactivate => (
+            set_user_deactivated(db, user, false).await?,
+            MutationType::Updated,
+        ),
+        UserStateChange::Delete => (
+            set_user_deleted(db, user, true).await?,
+            MutationType::Deleted,
+        ),
+        UserStateChange::Restore => (
+            set_user_deleted(db, user, false).await?,
+            MutationType::Updated,
+        ),
+    };
+    if ensure_active(&user).is_err() {
+        revoke_user_sessions(db, user.id).await?;
+    }
+    let client = ClientInfo::from_ctx(ctx);
+    let kind = change.login_event_kind();
+    new_login_event(db, user.id, kind, &client, Some(admin_id)).await?;
+    info!("user {} {} by {}", user.id, kind.as_str(), admin_id);
+    SimpleBroker::publish(UserChanged {
+        mutation_type,
+        id: user.id,
+    });
+    Ok(Some(user))
+}
+
+async fn send_password_reset_email(
+    db: &DatabaseConnection,
+    user: entity::user::Model,
+) -> Result<()> {
+    let token = generate_token();
+    let expires_at = Utc::now() + Duration::seconds(CONFIG.password_reset_token_lifetime);
+    new_password_reset_token(db, user.id, hash_token(&token), expires_at).await?;
+
+    let body = PasswordResetTemplate {
+        name: user.name,
+        link: format!("{}/reset-password?token={}", CONFIG.base_url(), token),
+        valid_minutes: CONFIG.password_reset_token_lifetime / 60,
+    }
+    .render()?;
+    let to = user.email;
+    spawn_blocking(move || mailer(&to, "Reset your zorius password", &body));
+
+    Ok(())
+}
+
+/// Creates a verification token for `email` and sends the verification link to it.
+async fn send_verification_email(
+    db: &DatabaseConnection,
+    user: &entity::user::Model,
+    email: String,
+) -> Result<()> {
+    let token = generate_token();
+    let expires_at = Utc::now() + Duration::seconds(CONFIG.email_verification_token_lifetime);
+    new_email_verification_token(db, user.id, email.clone(), hash_token(&token), expires_at)
+        .await?;
+
+    let body = EmailVerificationTemplate {
+        name: user.name.clone(),
+        email: email.clone(),
+        link: format!("{}/verify-email?token={}", CONFIG.base_url(), token),
+        valid_hours: CONFIG.email_verification_token_lifetime / 3600,
+    }
+    .render()?;
+    spawn_blocking(move || mailer(&email, "Verify your zorius email address", &body));
+
+    Ok(())
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct UserSubscription;
 
 #[Subscription]
 impl UserSubscription {
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::UserRead)")]
     async fn users(&self, mutation_type: Option<MutationType>) -> impl Stream<Item = UserChanged> {
         SimpleBroker::<UserChanged>::subscribe().filter(move |event| {
             let res = if let Some(mutation_type) = mutation_type {
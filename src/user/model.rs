@@ -1,12 +1,37 @@
-use async_graphql::{InputObject, Object, SimpleObject};
-use entity::user::Model;
+use std::{convert::TryFrom, str::FromStr};
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use askama::Template;
+use async_graphql::{ComplexObject, Context, Enum, InputObject, Object, SimpleObject};
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use entity::{login_event, user::Model, user_preference};
+use lazy_static::lazy_static;
+use log::error;
 use pwhash::sha512_crypt;
+use rand::rngs::OsRng;
 use sea_orm::prelude::DateTimeUtc;
 use serde::{Deserialize, Serialize};
+use tokio::task::spawn_blocking;
 use uuid::Uuid;
 
-use crate::api::MutationType;
-use crate::validators::Password;
+use crate::api::{database, MutationType, SortDirection};
+use crate::avatar::{avatar_url, AvatarFormat};
+use crate::config::CONFIG;
+use crate::errors::{Error, Result};
+use crate::policy::{Actor, Policy, UserAction};
+use crate::role::{
+    db::{user_permissions, user_roles},
+    model::{Permission, Role},
+};
+use crate::user::db::{user_by_id, user_preferences};
+use crate::validators::{Locale, Password, Timezone};
+
+/// Prefix of password hashes created with sha512_crypt before zorius switched to Argon2id
+pub const LEGACY_HASH_PREFIX: &str = "$6$";
 
 #[derive(Deserialize)]
 pub struct LoginData {
@@ -14,48 +39,156 @@ pub struct LoginData {
     pub password: String,
 }
 
-#[derive(Serialize, SimpleObject)]
+#[derive(Serialize, SimpleObject, Default)]
 pub struct LoginResult {
-    pub token: String,
+    /// Access token, not set if the login has to be confirmed with `verifyTotp` first
+    pub token: Option<String>,
+    pub refresh_token: Option<String>,
+    /// Challenge to pass to `verifyTotp`, only set if two factor authentication is enabled
+    pub two_factor_challenge: Option<String>,
 }
 
 #[derive(SimpleObject, Debug, Serialize, Clone)]
+#[graphql(complex)]
 pub struct User {
     pub id: Uuid,
     pub email: String,
+    pub email_verified_at: Option<DateTimeUtc>,
     #[graphql(secret = true, visible = false)]
     pub password_hash: String,
     pub name: Option<String>,
     pub avatar_filename: Option<String>,
     pub is_admin: bool,
+    pub totp_enabled_at: Option<DateTimeUtc>,
+    /// Logins are rejected until this time after too many failed attempts
+    pub locked_until: Option<DateTimeUtc>,
+    /// Deactivated users can't log in and their tokens are refused
+    pub deactivated_at: Option<DateTimeUtc>,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
     pub deleted_at: Option<DateTimeUtc>,
 }
 
+#[ComplexObject]
+impl User {
+    async fn roles(&self, ctx: &Context<'_>) -> Result<Vec<Role>> {
+        let db = database(ctx)?;
+        let roles = user_roles(db, self.id).await?;
+        Ok(roles.into_iter().map(Role::from).collect())
+    }
+
+    /// Permissions granted through the roles of the user
+    async fn permissions(&self, ctx: &Context<'_>) -> Result<Vec<Permission>> {
+        let db = database(ctx)?;
+        user_permissions(db, self.id).await
+    }
+
+    /// Url of the avatar with at least `size` pixels, a generated PNG if the user has none
+    async fn avatar_url(
+        &self,
+        #[graphql(default = 128)] size: u32,
+        #[graphql(default_with = "AvatarFormat::Webp")] format: AvatarFormat,
+    ) -> String {
+        avatar_url(self.id, self.avatar_filename.as_deref(), size, format)
+    }
+
+    /// Only visible to the user and to user admins
+    async fn preferences(&self, ctx: &Context<'_>) -> Result<Option<UserPreferences>> {
+        let actor = Actor::from_ctx(ctx).await?;
+        let db = database(ctx)?;
+        let user = match user_by_id(db, self.id).await? {
+            Some(user) if user.allows(&actor, UserAction::Update) => user,
+            _ => return Ok(None),
+        };
+        Ok(Some(user_preferences(db, user.id).await?.into()))
+    }
+}
+
 impl User {
     pub fn get_id(&self) -> &Uuid {
         &self.id
     }
 
-    pub fn hash_password(password: &str) -> String {
-        sha512_crypt::hash(password.as_bytes())
-            .expect("system random number generator cannot be opened!")
+    /// Hashes on the blocking thread pool, Argon2 is slow on purpose
+    pub async fn hash_password(password: &str) -> Result<String> {
+        let password = password.to_owned();
+        let hash = spawn_blocking(move || {
+            let salt = SaltString::generate(&mut OsRng);
+            argon2()
+                .hash_password(password.as_bytes(), &salt)
+                .map(|hash| hash.to_string())
+        })
+        .await
+        .map_err(|e| Error::PasswordHash(e.to_string()))?;
+        hash.map_err(|e| Error::PasswordHash(e.to_string()))
     }
 
-    pub fn is_password_correct(&self, password: &str) -> bool {
-        sha512_crypt::verify(password.as_bytes(), &self.password_hash)
+    /// Verifies on the blocking thread pool, Argon2 is slow on purpose
+    pub async fn is_password_correct(&self, password: &str) -> bool {
+        let password = password.to_owned();
+        let password_hash = self.password_hash.clone();
+        let correct = spawn_blocking(move || {
+            if password_hash.starts_with(LEGACY_HASH_PREFIX) {
+                return sha512_crypt::verify(password.as_bytes(), &password_hash);
+            }
+            match PasswordHash::new(&password_hash) {
+                Ok(hash) => argon2().verify_password(password.as_bytes(), &hash).is_ok(),
+                Err(_) => false,
+            }
+        })
+        .await;
+        correct.unwrap_or_else(|e| {
+            error!("failed to verify password: {e}");
+            false
+        })
+    }
+
+    /// Checks if the password hash is a legacy sha512_crypt hash
+    /// or was created with other Argon2 cost parameters than configured.
+    pub fn password_needs_rehash(&self) -> bool {
+        let hash = match PasswordHash::new(&self.password_hash) {
+            Ok(hash) => hash,
+            Err(_) => return true,
+        };
+        match Params::try_from(&hash) {
+            Ok(params) => {
+                hash.algorithm != Algorithm::Argon2id.ident()
+                    || params.m_cost() != CONFIG.password_hash.memory_cost
+                    || params.t_cost() != CONFIG.password_hash.time_cost
+                    || params.p_cost() != CONFIG.password_hash.parallelism
+            }
+            Err(_) => true,
+        }
     }
 }
 
+lazy_static! {
+    /// Initialized on startup, so invalid parameters in the config fail there
+    pub static ref ARGON2_PARAMS: Params = Params::new(
+        CONFIG.password_hash.memory_cost,
+        CONFIG.password_hash.time_cost,
+        CONFIG.password_hash.parallelism,
+        None,
+    )
+    .expect("invalid argon2 parameters in config");
+}
+
+fn argon2() -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, ARGON2_PARAMS.clone())
+}
+
 impl From<Model> for User {
     fn from(model: Model) -> Self {
         Self {
             id: model.id,
             email: model.email,
+            email_verified_at: model.email_verified_at,
             password_hash: model.password_hash,
             name: model.name,
             is_admin: model.is_admin,
+            totp_enabled_at: model.totp_enabled_at,
+            locked_until: model.locked_until,
+            deactivated_at: model.deactivated_at,
             avatar_filename: model.avatar_filename,
             created_at: model.created_at,
             updated_at: model.updated_at,
@@ -64,18 +197,45 @@ impl From<Model> for User {
     }
 }
 
-#[derive(Deserialize, Debug, InputObject, SimpleObject, Default)]
+#[derive(Deserialize, Debug, InputObject, Default)]
 pub struct ListUserOptions {
     pub ids: Option<Vec<Uuid>>,
+    pub filter: Option<UserFilter>,
+    /// Defaults to the creation time
+    pub sort: Option<UserSort>,
+    pub sort_direction: Option<SortDirection>,
     pub after: Option<String>,
     pub before: Option<String>,
     pub first: Option<i32>,
     pub last: Option<i32>,
 }
 
+#[derive(Deserialize, Debug, InputObject, Default, Clone)]
+pub struct UserFilter {
+    /// Case-insensitive substring of the name or the email address
+    pub search: Option<String>,
+    pub is_admin: Option<bool>,
+    pub deactivated: Option<bool>,
+    pub deleted: Option<bool>,
+    /// Only users created at or after this time
+    pub created_since: Option<DateTimeUtc>,
+    /// Only users created before this time
+    pub created_until: Option<DateTimeUtc>,
+}
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UserSort {
+    CreatedAt,
+    Email,
+    Name,
+}
+
 #[derive(Debug, Default)]
 pub struct DbListOptions {
     pub ids: Option<Vec<Uuid>>,
+    pub filter: UserFilter,
+    pub sort: Option<UserSort>,
+    pub sort_direction: SortDirection,
     pub start: u64,
     pub limit: u64,
 }
@@ -104,6 +264,213 @@ pub struct UserUpdate {
     pub is_admin: Option<bool>,
 }
 
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    /// Name of the day as it is stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Weekday::Monday => "monday",
+            Weekday::Tuesday => "tuesday",
+            Weekday::Wednesday => "wednesday",
+            Weekday::Thursday => "thursday",
+            Weekday::Friday => "friday",
+            Weekday::Saturday => "saturday",
+            Weekday::Sunday => "sunday",
+        }
+    }
+}
+
+impl FromStr for Weekday {
+    type Err = Error;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "monday" => Ok(Weekday::Monday),
+            "tuesday" => Ok(Weekday::Tuesday),
+            "wednesday" => Ok(Weekday::Wednesday),
+            "thursday" => Ok(Weekday::Thursday),
+            "friday" => Ok(Weekday::Friday),
+            "saturday" => Ok(Weekday::Saturday),
+            "sunday" => Ok(Weekday::Sunday),
+            _ => Err(Error::NotFound),
+        }
+    }
+}
+
+/// Contractual working time in hours per weekday
+#[derive(SimpleObject, InputObject, Debug, Serialize, Clone, Copy, PartialEq)]
+#[graphql(input_name = "WorkingHoursInput")]
+pub struct WorkingHours {
+    pub monday: f64,
+    pub tuesday: f64,
+    pub wednesday: f64,
+    pub thursday: f64,
+    pub friday: f64,
+    pub saturday: f64,
+    pub sunday: f64,
+}
+
+impl WorkingHours {
+    /// Minutes per day from monday to sunday, fails if a day has less than 0 or more than 24 hours
+    pub fn to_minutes(self) -> Result<[i32; 7]> {
+        let hours = [
+            self.monday,
+            self.tuesday,
+            self.wednesday,
+            self.thursday,
+            self.friday,
+            self.saturday,
+            self.sunday,
+        ];
+        let mut minutes = [0; 7];
+        for (day, hours) in minutes.iter_mut().zip(hours) {
+            if !(0.0..=24.0).contains(&hours) {
+                return Err(Error::InvalidWorkingHours);
+            }
+            *day = hours_to_minutes(hours);
+        }
+        Ok(minutes)
+    }
+}
+
+#[derive(SimpleObject, Debug, Serialize, Clone)]
+pub struct UserPreferences {
+    /// IANA time zone, dates in filters are days in this time zone
+    pub timezone: String,
+    pub locale: String,
+    pub first_day_of_week: Weekday,
+    pub date_format: String,
+    /// Contractual working time per week
+    pub weekly_hours: f64,
+    pub working_hours: WorkingHours,
+}
+
+impl From<user_preference::Model> for UserPreferences {
+    fn from(model: user_preference::Model) -> Self {
+        Self {
+            timezone: model.timezone,
+            locale: model.locale,
+            first_day_of_week: model.first_day_of_week.parse().unwrap_or(Weekday::Monday),
+            date_format: model.date_format,
+            weekly_hours: minutes_to_hours(model.weekly_minutes),
+            working_hours: WorkingHours {
+                monday: minutes_to_hours(model.monday_minutes),
+                tuesday: minutes_to_hours(model.tuesday_minutes),
+                wednesday: minutes_to_hours(model.wednesday_minutes),
+                thursday: minutes_to_hours(model.thursday_minutes),
+                friday: minutes_to_hours(model.friday_minutes),
+                saturday: minutes_to_hours(model.saturday_minutes),
+                sunday: minutes_to_hours(model.sunday_minutes),
+            },
+        }
+    }
+}
+
+#[derive(InputObject, Debug, Serialize)]
+pub struct UpdatePreferences {
+    #[graphql(validator(custom = "Timezone"))]
+    pub timezone: Option<String>,
+    #[graphql(validator(custom = "Locale"))]
+    pub locale: Option<String>,
+    pub first_day_of_week: Option<Weekday>,
+    #[graphql(validator(min_length = 1, max_length = 32))]
+    pub date_format: Option<String>,
+    /// Without `working_hours` the weekly hours are spread evenly over the current working days
+    pub weekly_hours: Option<f64>,
+    /// Must add up to `weekly_hours` if both are given
+    pub working_hours: Option<WorkingHours>,
+}
+
+impl UpdatePreferences {
+    /// Minutes per day from monday to sunday after the update, `None` if the working time is not changed
+    pub fn working_minutes(&self, current: &user_preference::Model) -> Result<Option<[i32; 7]>> {
+        let weekly = match self.weekly_hours {
+            Some(hours) if !(0.0..=168.0).contains(&hours) => {
+                return Err(Error::InvalidWorkingHours)
+            }
+            Some(hours) => Some(hours_to_minutes(hours)),
+            None => None,
+        };
+        match (&self.working_hours, weekly) {
+            (Some(working_hours), weekly) => {
+                let minutes = working_hours.to_minutes()?;
+                if weekly.is_some_and(|weekly| weekly != minutes.iter().sum::<i32>()) {
+                    return Err(Error::InvalidWorkingHours);
+                }
+                Ok(Some(minutes))
+            }
+            (None, Some(weekly)) => {
+                let current = [
+                    current.monday_minutes,
+                    current.tuesday_minutes,
+                    current.wednesday_minutes,
+                    current.thursday_minutes,
+                    current.friday_minutes,
+                    current.saturday_minutes,
+                    current.sunday_minutes,
+                ];
+                spread_weekly_minutes(weekly, current).map(Some)
+            }
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+/// Spreads the weekly minutes evenly over the days with working time,
+/// or over monday to friday if there are none
+fn spread_weekly_minutes(weekly: i32, current: [i32; 7]) -> Result<[i32; 7]> {
+    let mut days = current.map(|minutes| minutes > 0);
+    if !days.contains(&true) {
+        days = [true, true, true, true, true, false, false];
+    }
+    let count = days.iter().filter(|day| **day).count() as i32;
+    let mut remainder = weekly % count;
+    let mut minutes = [0; 7];
+    for (day_minutes, _) in minutes.iter_mut().zip(days).filter(|(_, day)| *day) {
+        *day_minutes = weekly / count;
+        if remainder > 0 {
+            *day_minutes += 1;
+            remainder -= 1;
+        }
+        if *day_minutes > 24 * 60 {
+            return Err(Error::InvalidWorkingHours);
+        }
+    }
+    Ok(minutes)
+}
+
+fn hours_to_minutes(hours: f64) -> i32 {
+    (hours * 60.0).round() as i32
+}
+
+fn minutes_to_hours(minutes: i32) -> f64 {
+    f64::from(minutes) / 60.0
+}
+
+/// Start of the day in the time zone as UTC.
+/// If the time zone skips midnight the day starts with the first hour that exists.
+pub fn start_of_day(date: NaiveDate, timezone: &Tz) -> DateTimeUtc {
+    let midnight = date.and_hms(0, 0, 0);
+    (0..24)
+        .find_map(|hour| {
+            timezone
+                .from_local_datetime(&(midnight + Duration::hours(hour)))
+                .earliest()
+        })
+        .map(|start| start.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+}
+
 #[derive(Clone)]
 pub struct UserChanged {
     pub mutation_type: MutationType,
@@ -120,3 +487,159 @@ impl UserChanged {
         &self.id
     }
 }
+
+#[derive(Template)]
+#[template(path = "password_reset.html")]
+pub struct PasswordResetTemplate {
+    pub(crate) name: Option<String>,
+    pub(crate) link: String,
+    pub(crate) valid_minutes: i64,
+}
+
+#[derive(Template)]
+#[template(path = "email_verification.html")]
+pub struct EmailVerificationTemplate {
+    pub(crate) name: Option<String>,
+    pub(crate) email: String,
+    pub(crate) link: String,
+    pub(crate) valid_hours: i64,
+}
+
+/// How long logins are rejected after the given number of failed attempts in a row.
+/// The delay doubles with every attempt above `free_attempts`, until the account is locked.
+pub fn login_delay(failed_attempts: i32) -> Option<Duration> {
+    let config = &CONFIG.login_throttle;
+    if failed_attempts >= config.lockout_attempts {
+        return Some(Duration::seconds(config.lockout_duration));
+    }
+    if failed_attempts <= config.free_attempts {
+        return None;
+    }
+    let exponent = (failed_attempts - config.free_attempts - 1).min(30) as u32;
+    let delay = config.base_delay.saturating_mul(2i64.pow(exponent));
+    Some(Duration::seconds(delay.min(config.max_delay)))
+}
+
+#[derive(Enum, Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+pub enum LoginEventKind {
+    Failed,
+    Locked,
+    Unlocked,
+    Deactivated,
+    Reactivated,
+    Deleted,
+    Restored,
+    PasswordResetByAdmin,
+    Impersonated,
+}
+
+impl LoginEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginEventKind::Failed => "failed",
+            LoginEventKind::Locked => "locked",
+            LoginEventKind::Unlocked => "unlocked",
+            LoginEventKind::Deactivated => "deactivated",
+            LoginEventKind::Reactivated => "reactivated",
+            LoginEventKind::Deleted => "deleted",
+            LoginEventKind::Restored => "restored",
+            LoginEventKind::PasswordResetByAdmin => "password_reset_by_admin",
+            LoginEventKind::Impersonated => "impersonated",
+        }
+    }
+}
+
+impl FromStr for LoginEventKind {
+    type Err = Error;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "failed" => Ok(LoginEventKind::Failed),
+            "locked" => Ok(LoginEventKind::Locked),
+            "unlocked" => Ok(LoginEventKind::Unlocked),
+            "deactivated" => Ok(LoginEventKind::Deactivated),
+            "reactivated" => Ok(LoginEventKind::Reactivated),
+            "deleted" => Ok(LoginEventKind::Deleted),
+            "restored" => Ok(LoginEventKind::Restored),
+            "password_reset_by_admin" => Ok(LoginEventKind::PasswordResetByAdmin),
+            "impersonated" => Ok(LoginEventKind::Impersonated),
+            _ => Err(Error::NotFound),
+        }
+    }
+}
+
+/// State changes an admin can make to an account
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum UserStateChange {
+    Deactivate,
+    Reactivate,
+    Delete,
+    Restore,
+}
+
+impl UserStateChange {
+    /// Login event the change is recorded with
+    pub fn login_event_kind(&self) -> LoginEventKind {
+        match self {
+            UserStateChange::Deactivate => LoginEventKind::Deactivated,
+            UserStateChange::Reactivate => LoginEventKind::Reactivated,
+            UserStateChange::Delete => LoginEventKind::Deleted,
+            UserStateChange::Restore => LoginEventKind::Restored,
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Serialize, Clone)]
+pub struct LoginEvent {
+    pub id: Uuid,
+    pub kind: Option<LoginEventKind>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    /// Admin that unlocked, deactivated, deleted or impersonated the account
+    pub actor_id: Option<Uuid>,
+    pub created_at: DateTimeUtc,
+}
+
+impl From<login_event::Model> for LoginEvent {
+    fn from(model: login_event::Model) -> Self {
+        Self {
+            id: model.id,
+            kind: model.kind.parse().ok(),
+            ip: model.ip,
+            user_agent: model.user_agent,
+            actor_id: model.actor_id,
+            created_at: model.created_at,
+        }
+    }
+}
+
+/// Deactivated and deleted users may neither log in nor use tokens issued before
+pub fn ensure_active(user: &Model) -> Result<()> {
+    if user.deactivated_at.is_some() || user.deleted_at.is_some() {
+        return Err(Error::AccountDeactivated);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_start_at_local_midnight() {
+        let date = NaiveDate::from_ymd(2026, 1, 15);
+        let start = start_of_day(date, &chrono_tz::Europe::Berlin);
+        assert_eq!(start, Utc.ymd(2026, 1, 14).and_hms(23, 0, 0));
+        let start = start_of_day(date, &Tz::UTC);
+        assert_eq!(start, Utc.ymd(2026, 1, 15).and_hms(0, 0, 0));
+    }
+
+    #[test]
+    fn weekly_minutes_are_spread_over_working_days() {
+        let minutes = spread_weekly_minutes(38 * 60 + 30, [0; 7]).unwrap();
+        assert_eq!(minutes, [462, 462, 462, 462, 462, 0, 0]);
+        let minutes = spread_weekly_minutes(20 * 60 + 1, [480, 0, 480, 0, 0, 0, 0]).unwrap();
+        assert_eq!(minutes, [601, 0, 600, 0, 0, 0, 0]);
+        assert!(spread_weekly_minutes(100 * 60, [0, 0, 0, 0, 0, 0, 60]).is_err());
+    }
+}
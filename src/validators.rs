@@ -1,7 +1,12 @@
-use async_graphql::CustomValidator;
+use std::str::FromStr;
+
+use async_graphql::{validators, CustomValidator};
+use chrono_tz::Tz;
 use url::Url as CrateUrl;
 use uuid::Uuid as CrateUuid;
 
+use crate::config::CONFIG;
+
 pub struct Password;
 const MIN_PW_LEN: usize = 8;
 const MAX_PW_LEN: usize = 255;
@@ -17,6 +22,25 @@ impl CustomValidator<String> for Password {
         }
     }
 }
+
+/// Email address, or any login name if the LDAP directory may use other logins
+pub struct Login;
+const MAX_LOGIN_LEN: usize = 255;
+
+impl CustomValidator<String> for Login {
+    fn check(&self, value: &String) -> Result<(), String> {
+        if CONFIG.ldap.enabled {
+            if value.is_empty() || value.len() > MAX_LOGIN_LEN {
+                return Err(format!(
+                    "login must not be empty or longer than {MAX_LOGIN_LEN} chars"
+                ));
+            }
+            return Ok(());
+        }
+        validators::email(value).map_err(|_| "invalid email".to_owned())
+    }
+}
+
 pub struct Url;
 
 impl CustomValidator<String> for Url {
@@ -40,3 +64,169 @@ impl CustomValidator<String> for Uuid {
         res
     }
 }
+
+/// IANA time zone name like `Europe/Berlin`
+pub struct Timezone;
+
+impl CustomValidator<String> for Timezone {
+    fn check(&self, value: &String) -> Result<(), String> {
+        match Tz::from_str(value) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("not a known IANA time zone".to_owned()),
+        }
+    }
+}
+
+/// Simple BCP 47 language tag like `de` or `de-AT`
+pub struct Locale;
+
+impl CustomValidator<String> for Locale {
+    fn check(&self, value: &String) -> Result<(), String> {
+        let mut parts = value.split('-');
+        let language = parts.next().unwrap_or_default();
+        let region = parts.next();
+        let valid_language =
+            (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_lowercase());
+        let valid_region = region.is_none_or(|region| {
+            (region.len() == 2 && region.chars().all(|c| c.is_ascii_uppercase()))
+                || (region.len() == 3 && region.chars().all(|c| c.is_ascii_digit()))
+        });
+        if valid_language && valid_region && parts.next().is_none() {
+            Ok(())
+        } else {
+            Err("not a valid locale like `de` or `de-AT`".to_owned())
+        }
+    }
+}
+
+/// Phone number of digits, spaces and `+-/()`, like `+49 30 1234-56`
+pub struct Phone;
+
+impl CustomValidator<String> for Phone {
+    fn check(&self, value: &String) -> Result<(), String> {
+        let digits = value.chars().filter(char::is_ascii_digit).count();
+        let valid_chars = value
+            .chars()
+            .all(|c| c.is_ascii_digit() || " +-/()".contains(c));
+        if valid_chars && (3..=20).contains(&digits) {
+            Ok(())
+        } else {
+            Err("not a valid phone number".to_owned())
+        }
+    }
+}
+
+/// Removes spaces, dots and dashes and uppercases, the way VAT IDs and IBANs are stored
+pub fn compact_identifier(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '.' | '-'))
+        .collect::<String>()
+        .to_ascii_uppercase()
+}
+
+/// VAT identification number like `DE123456789` or `ATU12345678`,
+/// spaces, dots and dashes are allowed
+pub struct VatId;
+
+impl CustomValidator<String> for VatId {
+    fn check(&self, value: &String) -> Result<(), String> {
+        let value = compact_identifier(value);
+        if !value.is_ascii() {
+            return Err("not a valid vat id like `DE123456789`".to_owned());
+        }
+        let (country, number) = value.split_at(value.len().min(2));
+        let valid = country.len() == 2
+            && country.chars().all(|c| c.is_ascii_alphabetic())
+            && (2..=13).contains(&number.len())
+            && number.chars().all(|c| c.is_ascii_alphanumeric())
+            && number.chars().any(|c| c.is_ascii_digit());
+        if valid {
+            Ok(())
+        } else {
+            Err("not a valid vat id like `DE123456789`".to_owned())
+        }
+    }
+}
+
+/// International bank account number with valid check digits, spaces are allowed
+pub struct Iban;
+
+impl CustomValidator<String> for Iban {
+    fn check(&self, value: &String) -> Result<(), String> {
+        let value = compact_identifier(value);
+        let valid_format = value.chars().all(|c| c.is_ascii_alphanumeric())
+            && (15..=34).contains(&value.len())
+            && value[..2].chars().all(|c| c.is_ascii_alphabetic())
+            && value[2..4].chars().all(|c| c.is_ascii_digit());
+        if !valid_format {
+            return Err("not a valid iban".to_owned());
+        }
+        // ISO 13616: move the first four chars to the end, letters count as 10 to 35
+        let remainder = value[4..]
+            .chars()
+            .chain(value[..4].chars())
+            .fold(0, |remainder, c| {
+                let digit = c.to_digit(36).unwrap_or_default();
+                let shift = if digit < 10 { 10 } else { 100 };
+                (remainder * shift + digit) % 97
+            });
+        if remainder == 1 {
+            Ok(())
+        } else {
+            Err("the iban has wrong check digits".to_owned())
+        }
+    }
+}
+
+/// ISO 4217 currency code like `EUR`
+pub struct Currency;
+
+impl CustomValidator<String> for Currency {
+    fn check(&self, value: &String) -> Result<(), String> {
+        if value.len() == 3 && value.chars().all(|c| c.is_ascii_uppercase()) {
+            Ok(())
+        } else {
+            Err("not a currency code like `EUR`".to_owned())
+        }
+    }
+}
+
+/// ISO 3166-1 alpha-2 country code like `DE`
+pub struct Country;
+
+impl CustomValidator<String> for Country {
+    fn check(&self, value: &String) -> Result<(), String> {
+        if value.len() == 2 && value.chars().all(|c| c.is_ascii_uppercase()) {
+            Ok(())
+        } else {
+            Err("not a country code like `DE`".to_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iban_check_digits() {
+        assert!(Iban
+            .check(&"DE89 3704 0044 0532 0130 00".to_owned())
+            .is_ok());
+        assert!(Iban.check(&"GB82WEST12345698765432".to_owned()).is_ok());
+        assert!(Iban
+            .check(&"DE88 3704 0044 0532 0130 00".to_owned())
+            .is_err());
+        assert!(Iban.check(&"DE89".to_owned()).is_err());
+    }
+
+    #[test]
+    fn vat_id_format() {
+        assert!(VatId.check(&"DE123456789".to_owned()).is_ok());
+        assert!(VatId.check(&"ATU 1234 5678".to_owned()).is_ok());
+        assert!(VatId.check(&"CHE-123.456.789".to_owned()).is_ok());
+        assert!(VatId.check(&"123456789".to_owned()).is_err());
+        assert!(VatId.check(&"DE".to_owned()).is_err());
+    }
+}
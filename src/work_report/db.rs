@@ -1,6 +1,6 @@
-use chrono::{Duration, Utc};
+use chrono::Utc;
 use entity::{
-    time_record,
+    customer, customer_contact, project, time_record,
     work_report::{ActiveModel, Column, Entity, Model},
 };
 use migration::sea_query::{Expr, IntoCondition};
@@ -11,17 +11,28 @@ use crate::errors::{Error, Result};
 
 use super::model::{
     DbListOptions, NewWorkReport, TimeRecordCommand, TimeRecordUpdate, WorkReportUpdate,
+    WorkReportVisibility,
 };
 
 pub async fn new_work_report(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     owner_id: Uuid,
     new: NewWorkReport,
 ) -> Result<Option<Model>> {
+    ensure_in_organization(db, organization_id, Some(new.customer_id), new.project_id).await?;
+    if let Some(project_id) = new.project_id {
+        ensure_project_of_customer(db, new.customer_id, project_id).await?;
+    }
+    if let Some(contact_id) = new.contact_id {
+        ensure_contact_of_customer(db, new.customer_id, contact_id).await?;
+    }
     let new_work_report = ActiveModel {
+        organization_id: Set(organization_id),
         owner_id: Set(owner_id),
         customer_id: Set(new.customer_id),
         project_id: Set(new.project_id),
+        contact_id: Set(new.contact_id),
         description: Set(new.description),
         invoiced: Set(new.invoiced),
         ..Default::default()
@@ -30,25 +41,93 @@ pub async fn new_work_report(
         .exec(db)
         .await?
         .last_insert_id;
-    work_report_by_id(db, id, owner_id).await
+    work_report_by_id(db, organization_id, id, owner_id).await
 }
 
 pub async fn work_report_by_id(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     id: Uuid,
     user_id: Uuid,
 ) -> Result<Option<Model>> {
     Ok(Entity::find_by_id(id)
+        .filter(Column::OrganizationId.eq(organization_id))
         .filter(Column::OwnerId.eq(user_id))
         .one(db)
         .await?)
 }
 
+pub async fn find_work_report(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    id: Uuid,
+) -> Result<Option<Model>> {
+    Ok(Entity::find_by_id(id)
+        .filter(Column::OrganizationId.eq(organization_id))
+        .one(db)
+        .await?)
+}
+
+/// Fails with `NotFound` if the customer or project belongs to another organization
+/// or the customer is trashed
+async fn ensure_in_organization(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    customer_id: Option<Uuid>,
+    project_id: Option<Uuid>,
+) -> Result<()> {
+    if let Some(customer_id) = customer_id {
+        customer::Entity::find_by_id(customer_id)
+            .filter(customer::Column::OrganizationId.eq(organization_id))
+            .filter(customer::Column::DeletedAt.is_null())
+            .one(db)
+            .await?
+            .ok_or(Error::NotFound)?;
+    }
+    if let Some(project_id) = project_id {
+        project::Entity::find_by_id(project_id)
+            .filter(project::Column::OrganizationId.eq(organization_id))
+            .one(db)
+            .await?
+            .ok_or(Error::NotFound)?;
+    }
+    Ok(())
+}
+
+/// Fails with `NotFound` if the project belongs to another customer
+async fn ensure_project_of_customer(
+    db: &DatabaseConnection,
+    customer_id: Uuid,
+    project_id: Uuid,
+) -> Result<()> {
+    project::Entity::find_by_id(project_id)
+        .filter(project::Column::CustomerId.eq(customer_id))
+        .one(db)
+        .await?
+        .ok_or(Error::NotFound)?;
+    Ok(())
+}
+
+/// Fails with `NotFound` if the contact belongs to another customer
+async fn ensure_contact_of_customer(
+    db: &DatabaseConnection,
+    customer_id: Uuid,
+    contact_id: Uuid,
+) -> Result<()> {
+    customer_contact::Entity::find_by_id(contact_id)
+        .filter(customer_contact::Column::CustomerId.eq(customer_id))
+        .one(db)
+        .await?
+        .ok_or(Error::NotFound)?;
+    Ok(())
+}
+
 pub async fn list_work_reports(
     db: &DatabaseConnection,
+    organization_id: Uuid,
     options: DbListOptions,
 ) -> Result<Vec<Model>> {
-    let mut entity = Entity::find();
+    let mut entity = Entity::find().filter(Column::OrganizationId.eq(organization_id));
 
     if let Some(ids) = options.ids {
         let con = ids.into_iter().fold(Condition::all(), |acc, id| {
@@ -58,20 +137,21 @@ pub async fn list_work_reports(
         entity = entity.filter(con);
     }
 
-    if let Some(start) = options.start_date {
+    if let Some(start) = options.created_from {
         entity = entity.filter(Column::CreatedAt.gte(start));
     }
-
-    if let Some(mut end) = options.end_date {
-        // workaround since lte() not includes the end Date, why ever
-        end += Duration::days(1);
+    if let Some(end) = options.created_before {
         entity = entity.filter(Column::CreatedAt.lt(end));
     }
 
     if let Some(customer_id) = options.for_customer_id {
         entity = entity.filter(Column::CustomerId.eq(customer_id))
-    } else {
-        entity = entity.filter(Column::OwnerId.eq(options.for_user_id))
+    }
+    if let Some(user_id) = options.for_user_id {
+        entity = entity.filter(Column::OwnerId.eq(user_id))
+    }
+    if let Some(visible) = &options.visible {
+        entity = entity.filter(visibility_condition(visible));
     }
 
     Ok(entity
@@ -82,57 +162,88 @@ pub async fn list_work_reports(
         .await?)
 }
 
-pub async fn count_work_reports(db: &DatabaseConnection, options: &DbListOptions) -> Result<usize> {
-    let mut entity = Entity::find();
+pub async fn count_work_reports(
+    db: &DatabaseConnection,
+    organization_id: Uuid,
+    options: &DbListOptions,
+) -> Result<usize> {
+    let mut entity = Entity::find().filter(Column::OrganizationId.eq(organization_id));
+    if let Some(start) = options.created_from {
+        entity = entity.filter(Column::CreatedAt.gte(start));
+    }
+    if let Some(end) = options.created_before {
+        entity = entity.filter(Column::CreatedAt.lt(end));
+    }
     if let Some(customer_id) = options.for_customer_id {
         entity = entity.filter(Column::CustomerId.eq(customer_id))
-    } else {
-        entity = entity.filter(Column::OwnerId.eq(options.for_user_id))
+    }
+    if let Some(user_id) = options.for_user_id {
+        entity = entity.filter(Column::OwnerId.eq(user_id))
+    }
+    if let Some(visible) = &options.visible {
+        entity = entity.filter(visibility_condition(visible));
     }
     Ok(entity.count(db).await?)
 }
 
+fn visibility_condition(visible: &WorkReportVisibility) -> Condition {
+    Condition::any()
+        .add(Column::OwnerId.eq(visible.viewer_id))
+        .add(Column::ProjectId.is_in(visible.project_ids.clone()))
+        .add(
+            Condition::all()
+                .add(Column::ProjectId.is_null())
+                .add(Column::CustomerId.is_in(visible.customer_ids.clone())),
+        )
+}
+
 pub async fn update_work_report(
     db: &DatabaseConnection,
+    wr: Model,
     update: WorkReportUpdate,
-) -> Result<Option<Model>> {
-    let user_id = update.for_user_id.expect("missing user_id");
-    let model = Entity::find_by_id(update.id)
-        .filter(Column::OwnerId.eq(user_id))
-        .one(db)
-        .await?;
-    if let Some(wr) = model {
-        let mut wr: ActiveModel = wr.into();
-        if let Some(customer_id) = update.customer_id {
-            wr.customer_id = Set(customer_id);
-        }
-        if let Some(project_id) = update.project_id {
-            wr.customer_id = Set(project_id);
+) -> Result<Model> {
+    ensure_in_organization(
+        db,
+        wr.organization_id,
+        update.customer_id,
+        update.project_id,
+    )
+    .await?;
+    let customer_id = update.customer_id.unwrap_or(wr.customer_id);
+    if update.customer_id.is_some() || update.project_id.is_some() {
+        if let Some(project_id) = update.project_id.or(wr.project_id) {
+            ensure_project_of_customer(db, customer_id, project_id).await?;
         }
-        if let Some(description) = update.description {
-            wr.description = Set(description);
-        }
-        if let Some(invoiced) = update.invoiced {
-            wr.invoiced = Set(invoiced);
-        }
-        if let Some(time_record_update) = update.time_record_update {
-            let _ = update_time_record(db, update.id, time_record_update).await?;
-        }
-        return Ok(Some(wr.update(db).await?));
     }
-
-    Ok(None)
+    let contact_id = match update.contact_id {
+        Some(contact_id) => contact_id,
+        // the contact of the previous customer can't have ordered the work
+        None if customer_id != wr.customer_id => None,
+        None => wr.contact_id,
+    };
+    if let Some(contact_id) = contact_id {
+        ensure_contact_of_customer(db, customer_id, contact_id).await?;
+    }
+    let mut wr: ActiveModel = wr.into();
+    wr.customer_id = Set(customer_id);
+    wr.contact_id = Set(contact_id);
+    if let Some(project_id) = update.project_id {
+        wr.project_id = Set(Some(project_id));
+    }
+    if let Some(description) = update.description {
+        wr.description = Set(description);
+    }
+    if let Some(invoiced) = update.invoiced {
+        wr.invoiced = Set(invoiced);
+    }
+    if let Some(time_record_update) = update.time_record_update {
+        let _ = update_time_record(db, update.id, time_record_update).await?;
+    }
+    Ok(wr.update(db).await?)
 }
 
-pub async fn delete_work_report(db: &DatabaseConnection, id: Uuid, user_id: Uuid) -> Result<u64> {
-    let wr = Entity::find_by_id(id)
-        .filter(Column::OwnerId.eq(user_id))
-        .one(db)
-        .await?;
-    if let Some(wr) = wr {
-        return Ok(wr.delete(db).await?.rows_affected);
-    }
-    Err(Error::NotFound)
+pub async fn delete_work_report(db: &DatabaseConnection, wr: Model) -> Result<u64> {
+    Ok(wr.delete(db).await?.rows_affected)
 }
 
 pub async fn update_time_record(
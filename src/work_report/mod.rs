@@ -2,22 +2,27 @@ use async_graphql::{
     connection::{query, Connection, Edge, EmptyFields},
     Context, Object, Subscription,
 };
+use chrono::Duration;
 use futures::{stream, StreamExt};
 use futures_util::Stream;
 use uuid::Uuid;
 
 use crate::{
     api::{database, MutationType},
+    api_token::model::{ApiTokenAuth, ApiTokenScope},
     claim::Claim,
     errors::{Error, Result},
-    guards::TokenGuard,
+    guards::PermissionGuard,
+    policy::{work_report_list_owner, Actor, ProjectScope, WorkReportAction},
+    role::model::Permission,
     simple_broker::SimpleBroker,
+    user::{db::user_timezone, model::start_of_day},
 };
 
 use self::{
     db::{
-        count_work_reports, delete_work_report, list_work_reports, new_work_report,
-        update_work_report,
+        count_work_reports, delete_work_report, find_work_report, list_work_reports,
+        new_work_report, update_work_report,
     },
     model::{
         DbListOptions, ListWorkReportOptions, NewWorkReport, WorkReport, WorkReportChanged,
@@ -33,23 +38,44 @@ pub struct WorkReportQuery;
 
 #[Object]
 impl WorkReportQuery {
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::WorkReportRead)")]
+    async fn work_report(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<WorkReport>> {
+        let actor = Actor::from_ctx(ctx).await?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let db = database(ctx)?;
+        let wr = match find_work_report(db, organization_id, id).await? {
+            Some(wr) => wr,
+            None => return Ok(None),
+        };
+        let scope = ProjectScope::load(db, &actor, organization_id).await?;
+        scope.authorize_work_report(&actor, &wr, WorkReportAction::Read)?;
+        Ok(Some(WorkReport::from(wr)))
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::WorkReportRead)")]
     async fn work_reports(
         &self,
         ctx: &Context<'_>,
         options: Option<ListWorkReportOptions>,
     ) -> Result<Connection<usize, WorkReport, EmptyFields, EmptyFields>> {
-        let claim = Claim::from_ctx(ctx)?;
-        let mut options = options.unwrap_or_default();
-        let user_id = claim.user_id()?;
-        options.for_user_id = Some(options.for_user_id.unwrap_or(user_id));
+        let actor = Actor::from_ctx(ctx).await?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
+        let options = options.unwrap_or_default();
+        let for_user_id =
+            work_report_list_owner(&actor, options.for_user_id, options.for_customer_id)?;
         let db = database(ctx)?;
+        let scope = ProjectScope::load(db, &actor, organization_id).await?;
+        // the dates are days in the time zone of the user, not UTC days
+        let timezone = user_timezone(db, actor.id).await?;
         let mut db_options = DbListOptions {
             ids: options.ids,
-            for_user_id: user_id,
+            for_user_id,
             for_customer_id: options.for_customer_id,
-            start_date: options.start_date,
-            end_date: options.end_date,
+            created_from: options.start_date.map(|date| start_of_day(date, &timezone)),
+            created_before: options
+                .end_date
+                .map(|date| start_of_day(date + Duration::days(1), &timezone)),
+            visible: scope.work_report_visibility(actor.id),
             ..Default::default()
         };
 
@@ -59,7 +85,7 @@ impl WorkReportQuery {
             options.first,
             options.last,
             |after, before, first, last| async move {
-                let count = count_work_reports(db, &db_options).await?;
+                let count = count_work_reports(db, organization_id, &db_options).await?;
                 let mut start = after.map(|after| after + 1).unwrap_or(0);
                 let mut end = before.unwrap_or(count);
                 if let Some(first) = first {
@@ -71,7 +97,7 @@ impl WorkReportQuery {
                 db_options.start = start as u64;
                 db_options.limit = end as u64;
 
-                let work_reports = list_work_reports(db, db_options).await?;
+                let work_reports = list_work_reports(db, organization_id, db_options).await?;
 
                 let mut connection = Connection::new(start > 0, end < count);
                 connection
@@ -93,16 +119,21 @@ pub struct WorkReportMutation;
 
 #[Object]
 impl WorkReportMutation {
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::WorkReportWrite)")]
     async fn new_work_report(
         &self,
         ctx: &Context<'_>,
         data: NewWorkReport,
     ) -> Result<Option<WorkReport>> {
-        let claim = Claim::from_ctx(ctx)?;
+        let actor = Actor::from_ctx(ctx).await?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
         let db = database(ctx)?;
-        let user_id = claim.user_id()?;
-        let wr = new_work_report(db, user_id, data).await?;
+        // only members may book time onto a project
+        let scope = ProjectScope::load(db, &actor, organization_id).await?;
+        if !scope.may_book(data.customer_id, data.project_id) {
+            return Err(Error::Forbidden);
+        }
+        let wr = new_work_report(db, organization_id, actor.id, data).await?;
         if let Some(wr) = wr {
             SimpleBroker::publish(WorkReportChanged {
                 mutation_type: MutationType::Created,
@@ -114,34 +145,52 @@ impl WorkReportMutation {
         }
     }
 
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::WorkReportWrite)
+        .or_scope(ApiTokenScope::TimeRecordsWrite)")]
     async fn update_work_report(
         &self,
         ctx: &Context<'_>,
-        mut update: WorkReportUpdate,
+        update: WorkReportUpdate,
     ) -> Result<Option<WorkReport>> {
-        let claim = Claim::from_ctx(ctx)?;
+        if let Some(auth) = ctx.data_opt::<ApiTokenAuth>() {
+            if !auth.has_scope(ApiTokenScope::WorkReportsWrite) && !update.only_time_records() {
+                return Err(Error::MissingScope);
+            }
+        }
+        let actor = Actor::from_ctx(ctx).await?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
         let db = database(ctx)?;
-        update.for_user_id = Some(update.for_user_id.unwrap_or(claim.user_id()?));
-        let wr = update_work_report(db, update).await?;
-        if let Some(wr) = wr {
-            SimpleBroker::publish(WorkReportChanged {
-                mutation_type: MutationType::Updated,
-                id: wr.id,
-            });
-            Ok(Some(WorkReport::from(wr)))
-        } else {
-            Ok(None)
+        let wr = match find_work_report(db, organization_id, update.id).await? {
+            Some(wr) => wr,
+            None => return Ok(None),
+        };
+        let scope = ProjectScope::load(db, &actor, organization_id).await?;
+        scope.authorize_work_report(&actor, &wr, WorkReportAction::Update)?;
+        let customer_id = update.customer_id.unwrap_or(wr.customer_id);
+        let project_id = update.project_id.or(wr.project_id);
+        if !scope.may_book(customer_id, project_id) {
+            return Err(Error::Forbidden);
         }
+        let wr = update_work_report(db, wr, update).await?;
+        SimpleBroker::publish(WorkReportChanged {
+            mutation_type: MutationType::Updated,
+            id: wr.id,
+        });
+        Ok(Some(WorkReport::from(wr)))
     }
 
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::WorkReportWrite)")]
     async fn delete_work_report(&self, ctx: &Context<'_>, id: Uuid) -> Result<u64> {
-        let claim = Claim::from_ctx(ctx)?;
-        let user_id = claim.user_id()?;
+        let actor = Actor::from_ctx(ctx).await?;
+        let organization_id = Claim::from_ctx(ctx)?.organization_id()?;
         let db = database(ctx)?;
+        let wr = find_work_report(db, organization_id, id)
+            .await?
+            .ok_or(Error::NotFound)?;
+        let scope = ProjectScope::load(db, &actor, organization_id).await?;
+        scope.authorize_work_report(&actor, &wr, WorkReportAction::Delete)?;
 
-        delete_work_report(db, id, user_id).await
+        delete_work_report(db, wr).await
     }
 }
 
@@ -150,7 +199,7 @@ pub struct WorkReportSubscription;
 
 #[Subscription]
 impl WorkReportSubscription {
-    #[graphql(guard = "TokenGuard")]
+    #[graphql(guard = "PermissionGuard::new(Permission::WorkReportRead)")]
     async fn users(
         &self,
         mutation_type: Option<MutationType>,
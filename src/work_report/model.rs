@@ -1,5 +1,5 @@
 use async_graphql::{ComplexObject, Context, Enum, InputObject, Object, SimpleObject};
-use entity::{customer, project, time_record, user, work_report::*};
+use entity::{customer, customer_contact, project, time_record, user, work_report::*};
 use sea_orm::{
     prelude::{Date, DateTimeUtc},
     ColumnTrait, EntityTrait, Order, QueryFilter, QueryOrder,
@@ -9,7 +9,7 @@ use uuid::Uuid;
 
 use crate::{
     api::{database, MutationType},
-    customer::model::Customer,
+    customer::model::{Customer, CustomerContact},
     errors::Result,
     project::model::Project,
     user::model::User,
@@ -25,6 +25,8 @@ pub struct WorkReport {
     pub customer_id: Uuid,
     #[graphql(visible = false)]
     pub project_id: Option<Uuid>,
+    #[graphql(visible = false)]
+    pub contact_id: Option<Uuid>,
     pub description: String,
     pub invoiced: bool,
     pub created_at: DateTimeUtc,
@@ -56,6 +58,16 @@ impl WorkReport {
         Ok(None)
     }
 
+    /// Contact person of the customer who ordered the work
+    async fn contact(&self, ctx: &Context<'_>) -> Result<Option<CustomerContact>> {
+        let db = database(ctx)?;
+        if let Some(id) = self.contact_id {
+            let model = customer_contact::Entity::find_by_id(id).one(db).await?;
+            return Ok(model.map(CustomerContact::from));
+        }
+        Ok(None)
+    }
+
     async fn time_records(&self, ctx: &Context<'_>) -> Result<Vec<TimeRecord>> {
         let db = database(ctx)?;
         let model = time_record::Entity::find()
@@ -74,6 +86,7 @@ impl From<Model> for WorkReport {
             owner_id: model.owner_id,
             customer_id: model.customer_id,
             project_id: model.project_id,
+            contact_id: model.contact_id,
             description: model.description,
             invoiced: model.invoiced,
             created_at: model.created_at,
@@ -87,7 +100,9 @@ pub struct ListWorkReportOptions {
     pub ids: Option<Vec<Uuid>>,
     pub for_user_id: Option<Uuid>,
     pub for_customer_id: Option<Uuid>,
+    /// First day in the time zone of the current user
     pub start_date: Option<Date>,
+    /// Last day in the time zone of the current user, inclusive
     pub end_date: Option<Date>,
     pub after: Option<String>,
     pub before: Option<String>,
@@ -99,6 +114,8 @@ pub struct ListWorkReportOptions {
 pub struct NewWorkReport {
     pub customer_id: Uuid,
     pub project_id: Option<Uuid>,
+    /// Contact of the customer who ordered the work
+    pub contact_id: Option<Uuid>,
     pub description: String,
     pub invoiced: bool,
 }
@@ -106,26 +123,52 @@ pub struct NewWorkReport {
 #[derive(Debug, Default)]
 pub struct DbListOptions {
     pub ids: Option<Vec<Uuid>>,
-    pub for_user_id: Uuid,
+    /// `None` lists the work reports of all users
+    pub for_user_id: Option<Uuid>,
     pub for_customer_id: Option<Uuid>,
-    pub start_date: Option<Date>,
-    pub end_date: Option<Date>,
+    /// Reports created at or after this time
+    pub created_from: Option<DateTimeUtc>,
+    /// Reports created before this time
+    pub created_before: Option<DateTimeUtc>,
+    /// `None` lists the work reports of all projects
+    pub visible: Option<WorkReportVisibility>,
     pub start: u64,
     pub limit: u64,
 }
 
+/// Work reports of other users than the viewer are only listed
+/// for these projects, or these customers if they have no project
+#[derive(Debug, Default)]
+pub struct WorkReportVisibility {
+    pub viewer_id: Uuid,
+    pub project_ids: Vec<Uuid>,
+    pub customer_ids: Vec<Uuid>,
+}
+
 #[derive(Serialize, Debug, InputObject)]
 pub struct WorkReportUpdate {
     pub id: Uuid,
-    pub for_user_id: Option<Uuid>,
     pub customer_id: Option<Uuid>,
     pub project_id: Option<Uuid>,
+    /// Contact of the customer who ordered the work, it is cleared if only the customer changes
+    pub contact_id: Option<Option<Uuid>>,
     pub description: Option<String>,
     pub invoiced: Option<bool>,
     pub start_time_record: Option<bool>,
     pub end_time_record: Option<bool>,
     pub time_record_update: Option<TimeRecordUpdate>,
 }
+
+impl WorkReportUpdate {
+    /// `true` if the update only starts, ends or changes time records
+    pub fn only_time_records(&self) -> bool {
+        self.customer_id.is_none()
+            && self.project_id.is_none()
+            && self.contact_id.is_none()
+            && self.description.is_none()
+            && self.invoiced.is_none()
+    }
+}
 #[derive(Clone)]
 pub struct WorkReportChanged {
     pub mutation_type: MutationType,